@@ -1,5 +1,8 @@
 import { homedir } from "node:os";
 
+// Mirrors schema/jd_index.schema.json (`jd-helper schema`); bump together.
+export const SCHEMA_VERSION = 2;
+
 export type JdNodeType = "Range" | "Category" | "ItemDir" | "File" | "Link" | "Other";
 
 export interface JdLink {
@@ -18,14 +21,25 @@ export interface JdNode {
   locations?: string[];
   links?: JdLink[];
   has_notes: boolean;
+  archived_from?: string;
   children: JdNode[];
 }
 
 export interface JdTree {
+  schema_version: number;
+  generated_at: string;
   roots: JdNode[];
   warnings?: string[];
 }
 
+export function parseTree(stdout: string): JdTree {
+  const tree = JSON.parse(stdout) as JdTree;
+  if ((tree.schema_version ?? 1) > SCHEMA_VERSION) {
+    throw new Error(`jd-helper writes schema ${tree.schema_version}; this extension reads up to ${SCHEMA_VERSION}. Update the extension.`);
+  }
+  return tree;
+}
+
 export interface Row {
   id: string;
   code?: string;
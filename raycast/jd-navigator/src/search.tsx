@@ -4,7 +4,7 @@ import { existsSync, promises as fs } from "node:fs";
 import path from "node:path";
 import { useMemo, useRef, useState } from "react";
 import { buildActions, Preferences } from "./actions";
-import { JdNodeType, JdTree, Row, flatten, parseRoots } from "./jd";
+import { JdNodeType, JdTree, Row, flatten, parseRoots, parseTree } from "./jd";
 
 const iconFor = (nodeType: JdNodeType) => ({ Range: Icon.Folder, ItemDir: Icon.Folder, Category: Icon.Tray, File: Icon.Document, Link: Icon.Link, Other: Icon.QuestionMark })[nodeType];
 const titleFor = (row: Row) => (row.code ? `${row.code} ${row.title}` : row.title);
@@ -19,7 +19,7 @@ export default function SearchCommand() {
     execute: helperExists,
     timeout: 120_000,
     keepPreviousData: true,
-    parseOutput: ({ stdout }) => JSON.parse(stdout) as JdTree,
+    parseOutput: ({ stdout }) => parseTree(stdout),
     failureToastOptions: { title: "jd-helper scan failed" },
     onData: (tree) => {
       const warnings = JSON.stringify(tree.warnings ?? []);
@@ -0,0 +1,578 @@
+//! Batch plans: a JSON list of create/rename/move/delete/renumber steps that
+//! is validated against one scan and applied as a single transaction.
+//!
+//! ```json
+//! [
+//!   {"op": "create", "parent": "31", "input": "31.05 Quantum notes"},
+//!   {"op": "rename", "id": "31.04", "title": "Colloquium"},
+//!   {"op": "move", "id": "31.04", "parent": "32"},
+//!   {"op": "renumber", "id": "31.01"},
+//!   {"op": "delete", "id": "31.03"}
+//! ]
+//! ```
+//!
+//! `id`/`parent` accept a node id, a JD code or an absolute path, resolved
+//! against the tree as the preceding steps leave it — step 3 above finds
+//! 31.04 under its new title. Planning runs every step against an in-memory
+//! copy of the tree, so two steps claiming the same name or the same "next
+//! free" code are caught before anything touches disk. If a step fails while
+//! executing, the steps already applied are reverted in reverse order.
+
+use crate::{
+    hooks, lock,
+    model::{self, Node, NodeType, Tree},
+    mutate,
+    plan::{self, CreatePlan, MovePlan, PlanKind, RenamePlan, RenumberPlan},
+};
+use anyhow::{anyhow, bail, Result};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "op", rename_all = "lowercase", deny_unknown_fields)]
+pub enum Step {
+    /// Same input as the Ctrl-N prompt ("21.04 Title", "notes.md", a URL).
+    Create {
+        parent: String,
+        input: String,
+        #[serde(default)]
+        kind: Option<PlanKind>,
+        #[serde(default)]
+        url: Option<String>,
+    },
+    Rename {
+        id: String,
+        title: String,
+    },
+    Move {
+        id: String,
+        parent: String,
+    },
+    Delete {
+        id: String,
+    },
+    Renumber {
+        id: String,
+    },
+}
+
+impl Step {
+    fn name(&self) -> &'static str {
+        match self {
+            Step::Create { .. } => "create",
+            Step::Rename { .. } => "rename",
+            Step::Move { .. } => "move",
+            Step::Delete { .. } => "delete",
+            Step::Renumber { .. } => "renumber",
+        }
+    }
+}
+
+/// One validated step, ready to execute.
+#[derive(Clone, Debug, Serialize)]
+#[serde(tag = "op", rename_all = "lowercase")]
+pub enum BatchOp {
+    Create(CreatePlan),
+    Rename(RenamePlan),
+    Move(MovePlan),
+    Delete { src_path: PathBuf, display: String },
+    Renumber(RenumberPlan),
+}
+
+impl BatchOp {
+    pub fn summary(&self) -> String {
+        match self {
+            BatchOp::Create(p) => plan::create_summary(p),
+            BatchOp::Rename(p) => format!("will rename {} → {}", p.old_name, p.new_name),
+            BatchOp::Move(p) => plan::move_summary(p),
+            BatchOp::Delete { display, .. } => format!("will move {} to the trash", display),
+            BatchOp::Renumber(p) => plan::renumber_summary(p),
+        }
+    }
+}
+
+pub fn parse_steps(json: &str) -> Result<Vec<Step>> {
+    serde_json::from_str(json).map_err(|e| anyhow!("invalid plan file: {}", e))
+}
+
+/// Plan every step against `tree`, simulating the effect of each on an
+/// in-memory copy so later steps see earlier ones. Fails on the first step
+/// that cannot be planned, naming it.
+pub fn plan_batch(tree: &Tree, steps: &[Step]) -> Result<Vec<BatchOp>> {
+    let mut sim = tree.clone();
+    let mut ops = Vec::new();
+    for (i, step) in steps.iter().enumerate() {
+        let op = plan_step(&mut sim, step, i)
+            .map_err(|e| anyhow!("step {} ({}): {}", i + 1, step.name(), e))?;
+        ops.push(op);
+    }
+    Ok(ops)
+}
+
+fn plan_step(sim: &mut Tree, step: &Step, i: usize) -> Result<BatchOp> {
+    match step {
+        Step::Create {
+            parent,
+            input,
+            kind,
+            url,
+        } => {
+            let selected = resolve_ref(sim, parent)?;
+            let mut p = plan::parse_new_input_forced(
+                input,
+                &plan::CreateContext {
+                    tree: sim,
+                    selected,
+                },
+                *kind,
+            )?;
+            if p.url.is_none() {
+                p.url = url.clone();
+            }
+            if p.kind == PlanKind::Link && p.url.is_none() {
+                bail!("link requires a URL (in the input or as \"url\")");
+            }
+            claim(sim, &p.dest_path)?;
+            let node = new_node(format!("batch:{}", i), &p);
+            find_mut(sim, &p.parent_id)
+                .ok_or_else(|| anyhow!("parent not found"))?
+                .children
+                .push(node);
+            Ok(BatchOp::Create(p))
+        }
+        Step::Rename { id, title } => {
+            let id = resolve_ref(sim, id)?.id.clone();
+            let p = plan::plan_rename(sim, &id, title)?;
+            if p.dest_path != p.src_path {
+                claim(sim, &p.dest_path)?;
+            }
+            let n = find_mut(sim, &id).unwrap();
+            repath(n, &p.dest_path);
+            n.title = classify(&p.new_name).1;
+            Ok(BatchOp::Rename(p))
+        }
+        Step::Move { id, parent } => {
+            let id = resolve_ref(sim, id)?.id.clone();
+            let parent_id = resolve_ref(sim, parent)?.id.clone();
+            let p = plan::plan_move(sim, &id, &parent_id)?;
+            claim(sim, &p.dest_path)?;
+            let mut n = take(sim, &id).unwrap();
+            repath(&mut n, &p.dest_path);
+            n.code = classify(&p.final_name).0;
+            find_mut(sim, &parent_id).unwrap().children.push(n);
+            Ok(BatchOp::Move(p))
+        }
+        Step::Delete { id } => {
+            let n = resolve_ref(sim, id)?;
+            if model::find_parent_id(sim, &n.id).is_none() {
+                bail!("cannot delete a root");
+            }
+            let src_path = PathBuf::from(&n.path);
+            let display = n
+                .code
+                .as_ref()
+                .map(|c| format!("{} {}", c, n.title))
+                .unwrap_or_else(|| n.title.clone());
+            let id = n.id.clone();
+            take(sim, &id);
+            Ok(BatchOp::Delete { src_path, display })
+        }
+        Step::Renumber { id } => {
+            let id = resolve_ref(sim, id)?.id.clone();
+            let p = plan::plan_renumber(sim, &id)?;
+            claim(sim, &p.dest_path)?;
+            let n = find_mut(sim, &id).unwrap();
+            repath(n, &p.dest_path);
+            n.code = Some(p.new_code.clone());
+            recode_sim(n, &p.old_code, &p.new_code);
+            Ok(BatchOp::Renumber(p))
+        }
+    }
+}
+
+/// Execute planned steps in order, writing the index once at the end. On
+/// failure the applied steps are undone newest-first and the error names the
+/// failing step. Hold `lock::lock_roots` from the scan the batch was planned
+/// against through this call, so no other session changes the tree between.
+pub fn execute_batch(roots: &[PathBuf], ops: &[BatchOp]) -> Result<()> {
+    let _lock = lock::lock_roots(roots)?;
+    hooks::around(hooks::Event::new("apply", &ops), || apply_all(roots, ops))
+}
+
+fn apply_all(roots: &[PathBuf], ops: &[BatchOp]) -> Result<()> {
+    let mut done: Vec<Undo> = Vec::new();
+    for (i, op) in ops.iter().enumerate() {
+        match apply(roots, op) {
+            Ok(undo) => done.push(undo),
+            Err(e) => {
+                let applied = done.len();
+                let failed: Vec<String> = done
+                    .into_iter()
+                    .rev()
+                    .filter_map(|u| u.revert().err().map(|e| e.to_string()))
+                    .collect();
+                let _ = mutate::index(roots);
+                let tail = if failed.is_empty() {
+                    format!("rolled back {} applied step(s)", applied)
+                } else {
+                    format!("rollback incomplete: {}", failed.join("; "))
+                };
+                bail!("step {} ({}) failed: {}; {}", i + 1, op.summary(), e, tail);
+            }
+        }
+    }
+    mutate::index(roots)
+}
+
+/// How to take back one applied step.
+enum Undo {
+    Remove(PathBuf),
+    Rename {
+        now: PathBuf,
+        was: PathBuf,
+    },
+    Recode {
+        now: PathBuf,
+        was: PathBuf,
+        new: String,
+        old: String,
+    },
+    Untrash(PathBuf),
+}
+
+impl Undo {
+    fn revert(self) -> Result<()> {
+        match self {
+            // created by this batch, template contents included
+            Undo::Remove(p) if p.is_dir() => fs::remove_dir_all(&p)?,
+            Undo::Remove(p) => fs::remove_file(&p)?,
+            Undo::Rename { now, was } => {
+                if was.exists() {
+                    bail!("{} is occupied", was.display());
+                }
+                mutate::move_path(&now, &was, &mut |_| {})?
+            }
+            Undo::Recode { now, was, new, old } => mutate::recode(&now, &was, &new, &old)?,
+            Undo::Untrash(p) => {
+                mutate::untrash(&p)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+fn apply(roots: &[PathBuf], op: &BatchOp) -> Result<Undo> {
+    let vacant = |p: &Path| {
+        if p.exists() {
+            bail!("destination already exists: {}", p.display());
+        }
+        Ok(())
+    };
+    match op {
+        BatchOp::Create(p) => {
+            vacant(&p.dest_path)?;
+            let parent = p.dest_path.parent().unwrap().to_string_lossy().to_string();
+            mutate::write_new(
+                p.kind,
+                &p.dest_path,
+                p.url.as_deref(),
+                p.location.as_deref().unwrap_or(&parent),
+            )?;
+            if let Some(t) = &p.template {
+                if let Err(e) = mutate::fill_template(t, &p.dest_path) {
+                    let _ = fs::remove_dir_all(&p.dest_path);
+                    return Err(e);
+                }
+            }
+            Ok(Undo::Remove(p.dest_path.clone()))
+        }
+        BatchOp::Rename(p) => {
+            if p.dest_path != p.src_path {
+                vacant(&p.dest_path)?;
+            }
+            fs::rename(&p.src_path, &p.dest_path)?;
+            crate::meta::carry_sidecars(&p.src_path, &p.dest_path)?;
+            Ok(Undo::Rename {
+                now: p.dest_path.clone(),
+                was: p.src_path.clone(),
+            })
+        }
+        BatchOp::Move(p) => {
+            vacant(&p.dest_path)?;
+            mutate::move_path(&p.src_path, &p.dest_path, &mut |_| {})?;
+            Ok(Undo::Rename {
+                now: p.dest_path.clone(),
+                was: p.src_path.clone(),
+            })
+        }
+        BatchOp::Delete { src_path, .. } => Ok(Undo::Untrash(mutate::trash(roots, src_path)?)),
+        BatchOp::Renumber(p) => {
+            vacant(&p.dest_path)?;
+            mutate::recode(&p.src_path, &p.dest_path, &p.old_code, &p.new_code)?;
+            Ok(Undo::Recode {
+                now: p.dest_path.clone(),
+                was: p.src_path.clone(),
+                new: p.new_code.clone(),
+                old: p.old_code.clone(),
+            })
+        }
+    }
+}
+
+/// A node by id, by JD code, or by absolute path. Files stamped with their
+/// item's code inside its folder don't make the folder's code ambiguous.
+pub fn resolve_ref<'a>(tree: &'a Tree, r: &str) -> Result<&'a Node> {
+    if let Some(n) = model::find_node(tree, r) {
+        return Ok(n);
+    }
+    fn walk<'a>(n: &'a Node, code: &str, inside: bool, out: &mut Vec<&'a Node>) {
+        let hit = n.code.as_deref() == Some(code);
+        if hit && !inside {
+            out.push(n);
+        }
+        for c in &n.children {
+            walk(c, code, inside || hit, out);
+        }
+    }
+    let mut hits = Vec::new();
+    for root in &tree.roots {
+        walk(root, r, false, &mut hits);
+    }
+    match hits.len() {
+        1 => return Ok(hits[0]),
+        0 => {}
+        n => bail!("code {} is ambiguous ({} entries) — use the id", r, n),
+    }
+    fn by_path<'a>(n: &'a Node, p: &str) -> Option<&'a Node> {
+        if n.path == p {
+            return Some(n);
+        }
+        n.children.iter().find_map(|c| by_path(c, p))
+    }
+    let p = r.trim_end_matches('/');
+    tree.roots
+        .iter()
+        .find_map(|root| by_path(root, p))
+        .ok_or_else(|| anyhow!("no node with id, code or path {}", r))
+}
+
+fn claim(sim: &Tree, dest: &Path) -> Result<()> {
+    fn walk(n: &Node, p: &str) -> bool {
+        n.path == p || n.children.iter().any(|c| walk(c, p))
+    }
+    let p = dest.to_string_lossy();
+    if sim.roots.iter().any(|r| walk(r, &p)) {
+        bail!("{} is already taken by an earlier step", dest.display());
+    }
+    Ok(())
+}
+
+/// (code, title, type) as the scanner would classify `name`.
+fn classify(name: &str) -> (Option<String>, String, NodeType) {
+    if let Some((c, t)) = model::parse_range(name) {
+        (Some(c), t, NodeType::Range)
+    } else if let Some((c, t)) = model::parse_category(name) {
+        (Some(c), t, NodeType::Category)
+    } else if let Some((c, t, _)) = model::parse_item(name) {
+        (Some(c), t, NodeType::ItemDir)
+    } else {
+        (None, name.to_string(), NodeType::Other)
+    }
+}
+
+fn new_node(id: String, p: &CreatePlan) -> Node {
+    let (code, title, dir_type) = classify(&p.final_name);
+    Node {
+        id,
+        code,
+        title,
+        path: p.dest_path.to_string_lossy().to_string(),
+        node_type: match p.kind {
+            PlanKind::Dir => dir_type,
+            PlanKind::File => NodeType::File,
+            PlanKind::Link => NodeType::Link,
+        },
+        location: None,
+        url: p.url.clone(),
+        locations: vec![],
+        links: vec![],
+        has_notes: false,
+        archived_from: None,
+        children: vec![],
+    }
+}
+
+fn find_mut<'a>(tree: &'a mut Tree, id: &str) -> Option<&'a mut Node> {
+    fn walk<'a>(n: &'a mut Node, id: &str) -> Option<&'a mut Node> {
+        if n.id == id {
+            return Some(n);
+        }
+        n.children.iter_mut().find_map(|c| walk(c, id))
+    }
+    tree.roots.iter_mut().find_map(|r| walk(r, id))
+}
+
+fn take(tree: &mut Tree, id: &str) -> Option<Node> {
+    fn walk(n: &mut Node, id: &str) -> Option<Node> {
+        if let Some(i) = n.children.iter().position(|c| c.id == id) {
+            return Some(n.children.remove(i));
+        }
+        n.children.iter_mut().find_map(|c| walk(c, id))
+    }
+    tree.roots.iter_mut().find_map(|r| walk(r, id))
+}
+
+/// Point `n` at `dest`, carrying its descendants along.
+fn repath(n: &mut Node, dest: &Path) {
+    n.path = dest.to_string_lossy().to_string();
+    for c in &mut n.children {
+        let name = Path::new(&c.path).file_name().unwrap().to_owned();
+        repath(c, &dest.join(name));
+    }
+}
+
+/// Mirror `mutate::recode`'s cascade: descendants named "{old}.…" become
+/// "{new}.…".
+fn recode_sim(n: &mut Node, old: &str, new: &str) {
+    for c in &mut n.children {
+        let name = Path::new(&c.path)
+            .file_name()
+            .unwrap()
+            .to_string_lossy()
+            .to_string();
+        let rest = name
+            .strip_prefix(old)
+            .filter(|r| r.starts_with('.') || r.starts_with('_'));
+        if let Some(rest) = rest {
+            let dest = Path::new(&n.path).join(format!("{}{}", new, rest));
+            repath(c, &dest);
+            c.code = c.code.as_ref().map(|code| code.replacen(old, new, 1));
+        }
+        recode_sim(c, old, new);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fs_walk;
+
+    /// R/30-39_Research/{31_Papers/{31.01_Existing/, 31.03_Note.txt}, 32_Empty/}
+    fn fixture() -> (tempfile::TempDir, PathBuf) {
+        let td = tempfile::tempdir().unwrap();
+        let r = td.path().join("R");
+        fs::create_dir_all(r.join("30-39_Research/31_Papers/31.01_Existing")).unwrap();
+        fs::create_dir_all(r.join("30-39_Research/32_Empty")).unwrap();
+        fs::write(r.join("30-39_Research/31_Papers/31.03_Note.txt"), b"x").unwrap();
+        fs::write(
+            r.join("30-39_Research/31_Papers/31.01_Existing/31.01.01_Sub.txt"),
+            b"x",
+        )
+        .unwrap();
+        (td, r)
+    }
+
+    fn steps(json: &str) -> Vec<Step> {
+        parse_steps(json).unwrap()
+    }
+
+    #[test]
+    fn later_steps_see_earlier_ones() {
+        let (_td, r) = fixture();
+        let roots = vec![r.clone()];
+        let tree = fs_walk::scan_roots(&roots).unwrap();
+        let ops = plan_batch(
+            &tree,
+            &steps(
+                r#"[
+                {"op": "create", "parent": "31", "input": "First"},
+                {"op": "create", "parent": "31", "input": "Second"},
+                {"op": "rename", "id": "31.02", "title": "Renamed"},
+                {"op": "move", "id": "31.01", "parent": "32"},
+                {"op": "create", "parent": "32.01", "input": "Inside"},
+                {"op": "delete", "id": "31.03"}
+            ]"#,
+            ),
+        )
+        .unwrap();
+        // two title-only creates get distinct next-free codes
+        let names: Vec<String> = ops
+            .iter()
+            .filter_map(|o| match o {
+                BatchOp::Create(p) => Some(p.final_name.clone()),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(names, ["31.02_First", "31.04_Second", "32.01.01_Inside"]);
+
+        execute_batch(&roots, &ops).unwrap();
+        let papers = r.join("30-39_Research/31_Papers");
+        assert!(papers.join("31.02_Renamed").is_dir());
+        assert!(papers.join("31.04_Second").is_dir());
+        assert!(r
+            .join("30-39_Research/32_Empty/32.01_Existing/32.01.01_Inside")
+            .is_dir());
+        assert!(r.join(".jd_trash/31.03_Note.txt").is_file());
+        assert!(r.join(".jd_index.json").is_file());
+    }
+
+    #[test]
+    fn conflicts_between_steps_are_caught_before_disk() {
+        let (_td, r) = fixture();
+        let roots = vec![r.clone()];
+        let tree = fs_walk::scan_roots(&roots).unwrap();
+        let err = plan_batch(
+            &tree,
+            &steps(
+                r#"[
+                {"op": "create", "parent": "31", "input": "31.05 Same"},
+                {"op": "create", "parent": "32", "input": "31.05 Same"}
+            ]"#,
+            ),
+        )
+        .unwrap_err();
+        assert!(err.to_string().starts_with("step 2 (create)"), "{err}");
+        // a deleted node is gone for later steps
+        let err = plan_batch(
+            &tree,
+            &steps(
+                r#"[
+                {"op": "delete", "id": "31.03"},
+                {"op": "rename", "id": "31.03", "title": "Ghost"}
+            ]"#,
+            ),
+        )
+        .unwrap_err();
+        assert!(err.to_string().contains("step 2 (rename)"), "{err}");
+        assert!(!r.join(".jd_trash").exists());
+    }
+
+    #[test]
+    fn failure_rolls_back_applied_steps() {
+        let (_td, r) = fixture();
+        let roots = vec![r.clone()];
+        let tree = fs_walk::scan_roots(&roots).unwrap();
+        let ops = plan_batch(
+            &tree,
+            &steps(
+                r#"[
+                {"op": "renumber", "id": "31.01"},
+                {"op": "delete", "id": "31.03"},
+                {"op": "create", "parent": "32", "input": "32.01 Blocked"}
+            ]"#,
+            ),
+        )
+        .unwrap();
+        // something else claims the last step's name after planning
+        let blocker = r.join("30-39_Research/32_Empty/32.01_Blocked");
+        fs::write(&blocker, b"").unwrap();
+        let err = execute_batch(&roots, &ops).unwrap_err();
+        assert!(err.to_string().contains("rolled back 2"), "{err}");
+        let papers = r.join("30-39_Research/31_Papers");
+        assert!(papers.join("31.01_Existing/31.01.01_Sub.txt").is_file());
+        assert!(!papers.join("31.02_Existing").exists());
+        assert!(papers.join("31.03_Note.txt").is_file());
+        assert!(!r.join(".jd_trash/31.03_Note.txt").exists());
+    }
+}
@@ -0,0 +1,340 @@
+//! Shell completion. `script` is clap's generated script for every
+//! subcommand plus a layer that asks `jd-helper complete` for the values of
+//! arguments naming nodes: value name `ID`, `PARENT` or `TARGET_ID` takes a
+//! node id, `CODE` an existing code. `candidates` answers those from the
+//! cached index, so completing doesn't wait for a scan.
+
+use crate::model::{Node, NodeType, Tree};
+use clap::Command;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Shell {
+    Bash,
+    Zsh,
+    Fish,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Values {
+    Codes,
+    Ids,
+}
+
+impl Values {
+    fn of(value_name: &str) -> Option<Values> {
+        match value_name {
+            "ID" | "PARENT" | "TARGET_ID" => Some(Values::Ids),
+            "CODE" => Some(Values::Codes),
+            _ => None,
+        }
+    }
+
+    /// The flag `complete` takes for these values.
+    fn flag(self) -> &'static str {
+        match self {
+            Values::Codes => "",
+            Values::Ids => " --ids",
+        }
+    }
+}
+
+/// Values for a word being completed, each with a description. Codes
+/// start with `prefix` and are described by their title. Ids are offered
+/// for nodes whose id or code starts with `prefix`, or whose title contains
+/// it, and described as "CODE Title": nobody types an inode.
+pub fn candidates(tree: &Tree, prefix: &str, values: Values) -> Vec<(String, String)> {
+    fn walk<'a>(n: &'a Node, parent: Option<&str>, out: &mut Vec<&'a Node>) {
+        // a file stamped with its item's code stands for nothing on its own
+        if n.node_type != NodeType::Other && (n.code.is_none() || n.code.as_deref() != parent) {
+            out.push(n);
+        }
+        for c in &n.children {
+            walk(c, n.code.as_deref(), out);
+        }
+    }
+    let mut nodes = Vec::new();
+    for r in &tree.roots {
+        walk(r, None, &mut nodes);
+    }
+    let lower = prefix.to_lowercase();
+    nodes
+        .into_iter()
+        .filter_map(|n| match (values, &n.code) {
+            (Values::Codes, Some(code)) if code.starts_with(prefix) => {
+                Some((code.clone(), n.title.clone()))
+            }
+            (Values::Codes, _) => None,
+            (Values::Ids, code) => {
+                let hit = n.id.starts_with(prefix)
+                    || code.as_ref().is_some_and(|c| c.starts_with(prefix))
+                    || n.title.to_lowercase().contains(&lower);
+                let display = match code {
+                    Some(c) => format!("{} {}", c, n.title),
+                    None => n.title.clone(),
+                };
+                hit.then(|| (n.id.clone(), display))
+            }
+        })
+        .collect()
+}
+
+/// One candidate per line as each shell's completion functions read them:
+/// `value:description` for zsh's `_describe`, the bare value for bash, and
+/// `value<TAB>description` for fish.
+pub fn render(shell: Shell, candidates: &[(String, String)]) -> String {
+    let mut out = String::new();
+    for (value, desc) in candidates {
+        let line = match shell {
+            Shell::Zsh => format!("{}:{}", value.replace(':', "\\:"), desc),
+            Shell::Bash => value.clone(),
+            Shell::Fish => format!("{}\t{}", value, desc),
+        };
+        out.push_str(&line);
+        out.push('\n');
+    }
+    out
+}
+
+/// An argument whose values `complete` supplies.
+struct Dynamic {
+    /// The top-level subcommand it belongs to (or to one of its actions).
+    sub: String,
+    /// `--id`, or None for a positional.
+    long: Option<String>,
+    /// clap's id, which zsh's spec uses for positionals.
+    id: String,
+    /// Value name, which zsh's spec uses for options.
+    value_name: String,
+    values: Values,
+}
+
+fn dynamic(cmd: &Command) -> Vec<Dynamic> {
+    fn walk(top: &str, cmd: &Command, out: &mut Vec<Dynamic>) {
+        for a in cmd.get_arguments() {
+            let Some(name) = a.get_value_names().and_then(|v| v.first()) else {
+                continue;
+            };
+            let long = a.get_long().map(|l| format!("--{}", l));
+            // actions of one subcommand share their flags
+            let seen = out
+                .iter()
+                .any(|d| d.sub == top && d.long == long && d.long.is_some());
+            if let (Some(values), false) = (Values::of(name), seen) {
+                out.push(Dynamic {
+                    sub: top.to_string(),
+                    long,
+                    id: a.get_id().to_string(),
+                    value_name: name.to_string(),
+                    values,
+                });
+            }
+        }
+        for s in cmd.get_subcommands() {
+            walk(top, s, out);
+        }
+    }
+    let mut out = Vec::new();
+    for s in cmd.get_subcommands() {
+        walk(s.get_name(), s, &mut out);
+    }
+    out
+}
+
+/// The completion script for `cmd` (the whole CLI), named `bin`.
+pub fn script(shell: Shell, cmd: &mut Command, bin: &str) -> String {
+    let generator = match shell {
+        Shell::Bash => clap_complete::Shell::Bash,
+        Shell::Zsh => clap_complete::Shell::Zsh,
+        Shell::Fish => clap_complete::Shell::Fish,
+    };
+    let mut buf = Vec::new();
+    clap_complete::generate(generator, cmd, bin, &mut buf);
+    let generated = String::from_utf8_lossy(&buf).into_owned();
+    let dynamic = dynamic(cmd);
+    match shell {
+        Shell::Zsh => zsh(&generated, &dynamic, bin),
+        Shell::Bash => bash(&generated, &dynamic, bin),
+        Shell::Fish => fish(&generated, &dynamic, bin),
+    }
+}
+
+/// Point the `_default` action of each dynamic argument at a function that
+/// asks `complete`, defined before the script runs `_jd-helper`.
+fn zsh(generated: &str, dynamic: &[Dynamic], bin: &str) -> String {
+    let mut out = String::new();
+    for line in generated.lines() {
+        let tail = ":_default' \\";
+        let hit = line.ends_with(tail).then(|| {
+            dynamic.iter().find(|d| match &d.long {
+                Some(long) => {
+                    line.starts_with(&format!("'{}=", long))
+                        && line.ends_with(&format!(":{}{}", d.value_name, tail))
+                }
+                None => {
+                    let spec = line.trim_start_matches('\'').trim_start_matches(':');
+                    spec.starts_with(&format!("{}:", d.id))
+                        || spec.starts_with(&format!("{} -- ", d.id))
+                }
+            })
+        });
+        match hit.flatten() {
+            Some(d) => {
+                let kind = match d.values {
+                    Values::Codes => "codes",
+                    Values::Ids => "ids",
+                };
+                let cut = line.len() - tail.len();
+                out.push_str(&format!("{}:_{}_nodes {}' \\", &line[..cut], bin, kind));
+            }
+            None => {
+                if line.starts_with("if [ \"$funcstack[1]\"") {
+                    out.push_str(&format!(
+                        "(( $+functions[_{bin}_nodes] )) ||\n\
+                         _{bin}_nodes() {{\n\
+                         \x20   local -a nodes\n\
+                         \x20   if [[ $1 == ids ]]; then\n\
+                         \x20       nodes=(\"${{(@f)$({bin} complete --shell zsh --ids -- \"$PREFIX\" 2>/dev/null)}}\")\n\
+                         \x20       # ids are offered for codes and titles too: don't filter by prefix\n\
+                         \x20       _describe -t jd-nodes 'node' nodes -U\n\
+                         \x20   else\n\
+                         \x20       nodes=(\"${{(@f)$({bin} complete --shell zsh -- \"$PREFIX\" 2>/dev/null)}}\")\n\
+                         \x20       _describe -t jd-codes 'code' nodes\n\
+                         \x20   fi\n\
+                         }}\n\n"
+                    ));
+                }
+                out.push_str(line);
+            }
+        }
+        out.push('\n');
+    }
+    out
+}
+
+/// Wrap the generated function: answer dynamic arguments first, hand
+/// everything else to it.
+fn bash(generated: &str, dynamic: &[Dynamic], bin: &str) -> String {
+    let mut cases = String::new();
+    for values in [Values::Ids, Values::Codes] {
+        let keys: Vec<String> = dynamic
+            .iter()
+            .filter(|d| d.values == values)
+            .map(|d| match &d.long {
+                Some(long) => format!("\"{} {}\"", d.sub, long),
+                None => format!("\"{} \"", d.sub),
+            })
+            .collect();
+        if !keys.is_empty() {
+            cases.push_str(&format!(
+                "        {}) what=\"{}\" ;;\n",
+                keys.join("|"),
+                values.flag()
+            ));
+        }
+    }
+    let func = bin.replace('-', "_");
+    let mut out = generated.replace(
+        &format!("complete -F _{}", bin),
+        &format!("complete -F _{}_dynamic", func),
+    );
+    out.push_str(&format!(
+        r#"
+_{func}_dynamic() {{
+    local cur="${{COMP_WORDS[COMP_CWORD]}}" prev="${{COMP_WORDS[COMP_CWORD-1]}}" what=-
+    # options by "SUBCOMMAND FLAG"; a subcommand's first positional by "SUBCOMMAND "
+    [[ $COMP_CWORD -eq 2 && $cur != -* ]] && prev=
+    case "${{COMP_WORDS[1]}} $prev" in
+{cases}    esac
+    if [[ $what != - ]]; then
+        COMPREPLY=($({bin} complete --shell bash$what -- "$cur" 2>/dev/null))
+        return 0
+    fi
+    _{bin} "$@"
+}}
+"#
+    ));
+    out
+}
+
+/// fish merges `complete` lines, so the dynamic ones are added after.
+fn fish(generated: &str, dynamic: &[Dynamic], bin: &str) -> String {
+    let mut out = generated.to_string();
+    let func = bin.replace('-', "_");
+    out.push('\n');
+    for d in dynamic {
+        let values = format!(
+            "({} complete --shell fish{} -- (commandline -ct))",
+            bin,
+            d.values.flag()
+        );
+        let line = match &d.long {
+            Some(long) => format!(
+                "complete -c {} -n \"__fish_{}_using_subcommand {}\" -l {} -x -a \"{}\"\n",
+                bin,
+                func,
+                d.sub,
+                long.trim_start_matches("--"),
+                values
+            ),
+            None => format!(
+                "complete -c {} -n \"__fish_{}_using_subcommand {}; and test (count (commandline -opc)) -eq 2\" -f -a \"{}\"\n",
+                bin, func, d.sub, values
+            ),
+        };
+        out.push_str(&line);
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fs_walk;
+    use std::fs;
+
+    #[test]
+    fn candidates_describe_codes_and_ids() {
+        let td = tempfile::tempdir().unwrap();
+        let roots = [td.path().join("R")];
+        let item = roots[0].join("30-39_Projects/31_House/31.01_Roof");
+        fs::create_dir_all(&item).unwrap();
+        fs::write(item.join("31.01_Quote.pdf"), "").unwrap();
+        fs::create_dir_all(roots[0].join("30-39_Projects/31_House/31.02_Garden")).unwrap();
+        let tree = fs_walk::scan_roots(&roots).unwrap();
+
+        let codes = candidates(&tree, "31.", Values::Codes);
+        assert_eq!(
+            codes,
+            [
+                ("31.01".to_string(), "Roof".to_string()),
+                ("31.02".to_string(), "Garden".to_string())
+            ]
+        );
+        let ids = candidates(&tree, "garden", Values::Ids);
+        assert_eq!(ids.len(), 1);
+        assert_eq!(ids[0].1, "31.02 Garden");
+        assert!(render(Shell::Zsh, &ids).starts_with("ino\\:"));
+        assert_eq!(render(Shell::Fish, &codes[..1]), "31.01\tRoof\n");
+        assert_eq!(render(Shell::Bash, &codes[..1]), "31.01\n");
+    }
+
+    #[test]
+    fn scripts_hook_node_arguments() {
+        use clap::{Arg, Command};
+        let mut cmd = Command::new("jd-helper")
+            .subcommand(Command::new("rename").arg(Arg::new("id").long("id").value_name("ID")))
+            .subcommand(Command::new("resolve").arg(Arg::new("code").value_name("CODE")));
+        let zsh = script(Shell::Zsh, &mut cmd, "jd-helper");
+        assert!(zsh.contains("'--id=[]:ID:_jd-helper_nodes ids' \\"));
+        assert!(zsh.contains(":code:_jd-helper_nodes codes'"));
+        assert!(zsh.contains("_jd-helper_nodes() {"));
+        let bash = script(Shell::Bash, &mut cmd, "jd-helper");
+        assert!(bash.contains("\"rename --id\") what=\" --ids\" ;;"));
+        assert!(bash.contains("\"resolve \") what=\"\" ;;"));
+        assert!(bash.contains("complete -F _jd_helper_dynamic"));
+        let fish = script(Shell::Fish, &mut cmd, "jd-helper");
+        assert!(fish.contains(
+            "using_subcommand rename\" -l id -x -a \"(jd-helper complete --shell fish --ids"
+        ));
+    }
+}
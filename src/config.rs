@@ -0,0 +1,262 @@
+//! User settings in `~/.config/jd/config.json` (or the file named by
+//! `JD_CONFIG`). Every key is optional:
+//!
+//! ```json
+//! {"names": {"separator": "_", "forbidden": "\\/:*?\"<>|", "normalize": "nfc", "max_len": 80}}
+//! ```
+//!
+//! `names` is the policy every new or changed title goes through (create,
+//! rename, move, file, templates) and that `check-names` holds existing
+//! names to. `hooks` are commands run around changes (see `hooks`).
+//! `archive` is where `archive` moves finished items (a category code, or a
+//! range for whole categories), looked up in the item's own root first.
+//! `snapshots` keeps dated copies of the index (see `snapshot`). `aliases`
+//! are names `resolve` takes for codes (`{"taxes": "21.04"}`). `opener` is
+//! the command `open --exec` hands links, folders and non-text files to
+//! (default `open` on macOS, `xdg-open` elsewhere). `preview.highlight`
+//! colours source files in the TUI's preview (on by default).
+
+use anyhow::{Context, Result};
+use once_cell::sync::Lazy;
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use unicode_normalization::{is_nfc, is_nfd, UnicodeNormalization};
+
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub names: NamePolicy,
+    pub hooks: Hooks,
+    pub archive: Option<String>,
+    pub snapshots: Snapshots,
+    pub aliases: BTreeMap<String, String>,
+    pub opener: Option<String>,
+    pub preview: Preview,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Preview {
+    pub highlight: bool,
+}
+
+impl Default for Preview {
+    fn default() -> Self {
+        Preview { highlight: true }
+    }
+}
+
+/// Off while `keep` is 0.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Snapshots {
+    /// How many to keep; the oldest go first.
+    pub keep: usize,
+    /// Least time between two, in seconds; written as an age like `1d`.
+    #[serde(deserialize_with = "age")]
+    pub every: u64,
+}
+
+impl Default for Snapshots {
+    fn default() -> Self {
+        Snapshots {
+            keep: 0,
+            every: 86_400,
+        }
+    }
+}
+
+fn age<'de, D: serde::Deserializer<'de>>(d: D) -> std::result::Result<u64, D::Error> {
+    let s = String::deserialize(d)?;
+    crate::date::parse_age(&s).map_err(serde::de::Error::custom)
+}
+
+/// Shell commands by operation kind (or `*`), see `hooks`.
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Hooks {
+    pub pre: BTreeMap<String, String>,
+    pub post: BTreeMap<String, String>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Normalize {
+    Nfc,
+    Nfd,
+    None,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct NamePolicy {
+    /// Replaces spaces (and runs of them) in titles.
+    pub separator: String,
+    /// Characters dropped from titles; control characters always are.
+    pub forbidden: String,
+    pub normalize: Normalize,
+    /// Longest title in characters (0: no limit). The code and extension
+    /// come on top.
+    pub max_len: usize,
+}
+
+impl Default for NamePolicy {
+    fn default() -> Self {
+        NamePolicy {
+            separator: "_".into(),
+            // what Windows and SMB shares refuse
+            forbidden: "\\/:*?\"<>|".into(),
+            normalize: Normalize::Nfc,
+            max_len: 0,
+        }
+    }
+}
+
+/// Names Windows reserves whatever the extension.
+const RESERVED: &[&str] = &[
+    "con", "prn", "aux", "nul", "com1", "com2", "com3", "com4", "com5", "com6", "com7", "com8",
+    "com9", "lpt1", "lpt2", "lpt3", "lpt4", "lpt5", "lpt6", "lpt7", "lpt8", "lpt9",
+];
+
+impl NamePolicy {
+    /// A title as it should appear in a file name.
+    pub fn sanitize(&self, s: &str) -> String {
+        let s = self.normalized(s);
+        let kept: String = s
+            .chars()
+            .filter(|c| !c.is_control() && !self.forbidden.contains(*c))
+            .collect();
+        let mut out = kept
+            .split_whitespace()
+            .collect::<Vec<_>>()
+            .join(&self.separator);
+        if self.max_len > 0 {
+            out = out.chars().take(self.max_len).collect();
+        }
+        let mut out = out.as_str();
+        // no dangling separator after a cut, nor a trailing dot
+        while let Some(rest) = out
+            .strip_suffix(self.separator.as_str())
+            .filter(|_| !self.separator.is_empty())
+            .or_else(|| out.strip_suffix(['.', ' ']))
+        {
+            out = rest;
+        }
+        out.to_string()
+    }
+
+    /// What's wrong with an existing file name under this policy (empty if
+    /// nothing). Separators aren't checked; names with spaces still sync.
+    pub fn problems(&self, name: &str) -> Vec<String> {
+        let mut out = Vec::new();
+        let bad: String = name
+            .chars()
+            .filter(|c| c.is_control() || self.forbidden.contains(*c))
+            .collect();
+        if !bad.is_empty() {
+            out.push(format!("forbidden character(s) {:?}", bad));
+        }
+        if name.ends_with(['.', ' ']) {
+            out.push("ends with a dot or space".into());
+        }
+        match self.normalize {
+            Normalize::Nfc if !is_nfc(name) => out.push("not NFC-normalised".into()),
+            Normalize::Nfd if !is_nfd(name) => out.push("not NFD-normalised".into()),
+            _ => {}
+        }
+        let stem = name.split('.').next().unwrap_or(name).to_lowercase();
+        if RESERVED.contains(&stem.as_str()) {
+            out.push("reserved name on Windows".into());
+        }
+        let title = crate::model::parse_item(name)
+            .map(|(_, t, _)| t)
+            .or_else(|| crate::model::parse_category(name).map(|(_, t)| t))
+            .or_else(|| crate::model::parse_range(name).map(|(_, t)| t))
+            .unwrap_or_else(|| name.to_string());
+        if self.max_len > 0 && title.chars().count() > self.max_len {
+            out.push(format!("title longer than {} characters", self.max_len));
+        }
+        out
+    }
+
+    fn normalized(&self, s: &str) -> String {
+        match self.normalize {
+            Normalize::Nfc => s.nfc().collect(),
+            Normalize::Nfd => s.nfd().collect(),
+            Normalize::None => s.to_string(),
+        }
+    }
+}
+
+pub fn config_path() -> PathBuf {
+    if let Some(p) = std::env::var_os("JD_CONFIG") {
+        return PathBuf::from(p);
+    }
+    let cfg = home::home_dir().unwrap_or_else(|| PathBuf::from("."));
+    cfg.join(".config").join("jd").join("config.json")
+}
+
+/// Read `path`; a missing file means all defaults.
+pub fn load(path: &Path) -> Result<Config> {
+    match fs::read_to_string(path) {
+        Ok(s) => serde_json::from_str(&s).with_context(|| format!("bad config {}", path.display())),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Config::default()),
+        Err(e) => Err(e.into()),
+    }
+}
+
+static CONFIG: Lazy<Config> = Lazy::new(|| load(&config_path()).unwrap_or_default());
+
+/// The process-wide config, read once. An unreadable file falls back to
+/// the defaults; `check-names` reports it.
+pub fn get() -> &'static Config {
+    &CONFIG
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn policy_sanitizes_and_reports() {
+        let p = NamePolicy::default();
+        assert_eq!(p.sanitize("  Q3: plan?  draft. "), "Q3_plan_draft");
+        // decomposed é (e + U+0301) is composed
+        assert_eq!(p.sanitize("Cafe\u{301} notes"), "Caf\u{e9}_notes");
+        let short = NamePolicy {
+            separator: "-".into(),
+            max_len: 5,
+            ..NamePolicy::default()
+        };
+        assert_eq!(short.sanitize("a b c d e f"), "a-b-c");
+        assert_eq!(short.sanitize("ab cd ef"), "ab-cd");
+
+        assert!(p.problems("31.04_Fine_name.pdf").is_empty());
+        assert_eq!(p.problems("31.05_Q3: plan.").len(), 2);
+        assert_eq!(p.problems("Cafe\u{301}"), ["not NFC-normalised"]);
+        assert_eq!(p.problems("nul.txt"), ["reserved name on Windows"]);
+        assert_eq!(
+            short.problems("31.04_Longer"),
+            ["title longer than 5 characters"]
+        );
+
+        let td = tempfile::tempdir().unwrap();
+        let f = td.path().join("config.json");
+        assert_eq!(load(&f).unwrap().names.separator, "_");
+        fs::write(&f, r#"{"names": {"separator": ".", "normalize": "none"}}"#).unwrap();
+        let c = load(&f).unwrap();
+        assert_eq!(c.names.normalize, Normalize::None);
+        assert_eq!(c.names.forbidden, NamePolicy::default().forbidden);
+        fs::write(&f, r#"{"names": {"sep": "."}}"#).unwrap();
+        assert!(load(&f).is_err());
+        fs::write(&f, r#"{"snapshots": {"keep": 5, "every": "12h"}}"#).unwrap();
+        assert_eq!(load(&f).unwrap().snapshots.every, 12 * 3600);
+        fs::write(&f, r#"{"snapshots": {"every": "daily"}}"#).unwrap();
+        assert!(load(&f).is_err());
+        // a config error, not a panic in every command
+        fs::write(&f, r#"{"snapshots": {"every": "1日"}}"#).unwrap();
+        assert!(load(&f).is_err());
+    }
+}
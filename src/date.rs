@@ -0,0 +1,133 @@
+//! Just enough calendar for timestamps in manifests and names: UTC civil
+//! dates from Unix seconds and short ages like `30d`.
+
+use anyhow::{bail, Result};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+pub fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+/// Seconds since the epoch for a filesystem timestamp.
+pub fn secs(t: SystemTime) -> u64 {
+    t.duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+/// (year, month, day, hour, minute, second) in UTC.
+pub fn civil(secs: u64) -> (i64, u32, u32, u32, u32, u32) {
+    let days = (secs / 86_400) as i64;
+    let rem = secs % 86_400;
+    // Howard Hinnant's days-to-civil.
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let d = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let m = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let y = yoe + era * 400 + (m <= 2) as i64;
+    (
+        y,
+        m,
+        d,
+        (rem / 3600) as u32,
+        (rem / 60 % 60) as u32,
+        (rem % 60) as u32,
+    )
+}
+
+/// `2026-10-19`
+pub fn date(secs: u64) -> String {
+    let (y, m, d, ..) = civil(secs);
+    format!("{:04}-{:02}-{:02}", y, m, d)
+}
+
+/// `2026-10-19 14:05`
+pub fn date_time(secs: u64) -> String {
+    let (y, m, d, h, min, _) = civil(secs);
+    format!("{:04}-{:02}-{:02} {:02}:{:02}", y, m, d, h, min)
+}
+
+/// `20261019-140502`, safe inside file names.
+pub fn stamp(secs: u64) -> String {
+    let (y, m, d, h, min, s) = civil(secs);
+    format!("{:04}{:02}{:02}-{:02}{:02}{:02}", y, m, d, h, min, s)
+}
+
+/// Seconds since the epoch for a `stamp`, or None if it isn't one.
+pub fn parse_stamp(s: &str) -> Option<u64> {
+    let (d, t) = s.split_once('-')?;
+    let digits = d.bytes().chain(t.bytes()).all(|b| b.is_ascii_digit());
+    if d.len() != 8 || t.len() != 6 || !digits {
+        return None;
+    }
+    let n = |s: &str| s.parse::<i64>().unwrap_or(0);
+    let (y, m, day) = (n(&d[..4]), n(&d[4..6]), n(&d[6..]));
+    if !(1..=12).contains(&m) || !(1..=31).contains(&day) {
+        return None;
+    }
+    // Howard Hinnant's days-from-civil, the inverse of `civil`.
+    let y = if m <= 2 { y - 1 } else { y };
+    let era = y.div_euclid(400);
+    let yoe = y.rem_euclid(400);
+    let doy = (153 * ((m + 9) % 12) + 2) / 5 + day - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    let days = era * 146_097 + doe - 719_468;
+    let secs = days * 86_400 + n(&t[..2]) * 3600 + n(&t[2..4]) * 60 + n(&t[4..]);
+    u64::try_from(secs).ok()
+}
+
+/// `2026-10-19T14:05:02Z` (RFC 3339, UTC).
+pub fn rfc3339(secs: u64) -> String {
+    let (y, m, d, h, min, s) = civil(secs);
+    format!("{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z", y, m, d, h, min, s)
+}
+
+/// `90s`, `15m`, `12h`, `30d`, `2w` → seconds.
+pub fn parse_age(s: &str) -> Result<u64> {
+    let s = s.trim();
+    let (n, unit) = s.split_at(s.char_indices().last().map_or(0, |(i, _)| i));
+    let Ok(n) = n.parse::<u64>() else {
+        bail!("bad age '{}' (expected e.g. 30d, 12h, 2w)", s);
+    };
+    let mult = match unit {
+        "s" => 1,
+        "m" => 60,
+        "h" => 3600,
+        "d" => 86_400,
+        "w" => 7 * 86_400,
+        _ => bail!("bad age '{}' (expected e.g. 30d, 12h, 2w)", s),
+    };
+    n.checked_mul(mult)
+        .ok_or_else(|| anyhow::anyhow!("age '{}' is too large", s))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn civil_dates_and_ages() {
+        assert_eq!(date(0), "1970-01-01");
+        // leap day, and the last second of a year
+        assert_eq!(date_time(951_782_400), "2000-02-29 00:00");
+        assert_eq!(stamp(1_767_225_599), "20251231-235959");
+        assert_eq!(rfc3339(1_767_225_599), "2025-12-31T23:59:59Z");
+        assert_eq!(parse_stamp("20251231-235959"), Some(1_767_225_599));
+        assert_eq!(parse_stamp(&stamp(951_782_400)), Some(951_782_400));
+        assert_eq!(parse_stamp("20251331-000000"), None);
+        assert_eq!(parse_stamp("2025123-1235959"), None);
+        assert_eq!(parse_age("30d").unwrap(), 30 * 86_400);
+        assert_eq!(parse_age("2w").unwrap(), 14 * 86_400);
+        assert!(parse_age("30").is_err());
+        assert!(parse_age("d").is_err());
+        assert!(parse_age("7é").is_err());
+        assert!(parse_age("99999999999999999w").is_err());
+    }
+}
@@ -0,0 +1,528 @@
+//! What changed between two scans of the same roots, node by node. Ids
+//! come from inodes, so a renamed or moved node keeps its id and shows up
+//! as changed rather than as one removal plus one addition.
+//!
+//! `differences` says what the change was (for `jd-helper diff`). Trees
+//! from another machine or a restored backup share no inodes, so nodes
+//! left unmatched by id are matched by code where that's unambiguous.
+
+use crate::model::{Node, NodeType, Tree};
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Change {
+    Added,
+    Removed,
+    Changed,
+}
+
+/// Ids found in only one of the trees, or whose own fields (not their
+/// children) differ between them.
+pub fn changes(old: &Tree, new: &Tree) -> HashMap<String, Change> {
+    let before = by_id(old);
+    let after = by_id(new);
+    let mut out = HashMap::new();
+    for (id, n) in &after {
+        match before.get(id) {
+            None => {
+                out.insert(id.to_string(), Change::Added);
+            }
+            Some(o) if !same(o, n) => {
+                out.insert(id.to_string(), Change::Changed);
+            }
+            _ => {}
+        }
+    }
+    for id in before.keys() {
+        if !after.contains_key(id) {
+            out.insert(id.to_string(), Change::Removed);
+        }
+    }
+    out
+}
+
+/// Removed subtrees of `old` with the id of the parent they hung under,
+/// outermost only (their removed descendants come along inside them).
+pub fn removed<'a>(old: &'a Tree, new: &Tree) -> Vec<(&'a str, &'a Node)> {
+    fn walk<'a>(n: &'a Node, after: &HashMap<&str, &Node>, out: &mut Vec<(&'a str, &'a Node)>) {
+        for c in &n.children {
+            if after.contains_key(c.id.as_str()) {
+                walk(c, after, out);
+            } else {
+                out.push((n.id.as_str(), c));
+            }
+        }
+    }
+    let after = by_id(new);
+    let mut out = Vec::new();
+    for r in &old.roots {
+        walk(r, &after, &mut out);
+    }
+    out
+}
+
+/// A node as `diff` reports it: its state in the newer tree, or in the
+/// older one if it's gone.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct Brief {
+    pub id: String,
+    pub code: Option<String>,
+    pub title: String,
+    pub path: String,
+}
+
+impl Brief {
+    fn of(n: &Node) -> Brief {
+        Brief {
+            id: n.id.clone(),
+            code: n.code.clone(),
+            title: n.title.clone(),
+            path: n.path.clone(),
+        }
+    }
+
+    fn display(&self) -> String {
+        match &self.code {
+            Some(c) => format!("{} {}", c, self.title),
+            None => self.title.clone(),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+#[serde(tag = "change", rename_all = "lowercase")]
+pub enum Difference {
+    /// A new subtree; `inside` counts its descendants.
+    Added {
+        node: Brief,
+        inside: usize,
+    },
+    Removed {
+        node: Brief,
+        inside: usize,
+    },
+    /// `from` is the old title.
+    Renamed {
+        node: Brief,
+        from: String,
+    },
+    /// `from` and `to` are the old and new parents.
+    Moved {
+        node: Brief,
+        from: String,
+        to: String,
+    },
+    /// `from` is the old code.
+    Recoded {
+        node: Brief,
+        from: String,
+    },
+    /// `.jdmeta` locations (and a pointer file's `LOCATION=`).
+    Locations {
+        node: Brief,
+        added: Vec<String>,
+        removed: Vec<String>,
+    },
+    /// `.jdmeta` links as `URL label`.
+    Links {
+        node: Brief,
+        added: Vec<String>,
+        removed: Vec<String>,
+    },
+}
+
+impl Difference {
+    /// The JSON `change` tag: `added`, `moved`, …
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Difference::Added { .. } => "added",
+            Difference::Removed { .. } => "removed",
+            Difference::Renamed { .. } => "renamed",
+            Difference::Moved { .. } => "moved",
+            Difference::Recoded { .. } => "recoded",
+            Difference::Locations { .. } => "locations",
+            Difference::Links { .. } => "links",
+        }
+    }
+
+    pub fn describe(&self) -> String {
+        let list = |added: &[String], removed: &[String]| {
+            let plus = added.iter().map(|a| format!("+ {}", a));
+            let minus = removed.iter().map(|r| format!("- {}", r));
+            plus.chain(minus).collect::<Vec<_>>().join(", ")
+        };
+        let inside = |n: usize| match n {
+            0 => String::new(),
+            n => format!(" (and {} inside)", n),
+        };
+        match self {
+            Difference::Added { node, inside: n } => {
+                format!("added      {}{}", node.display(), inside(*n))
+            }
+            Difference::Removed { node, inside: n } => {
+                format!("removed    {}{}", node.display(), inside(*n))
+            }
+            Difference::Renamed { node, from } => {
+                format!("renamed    {} → {}", from, node.title)
+            }
+            Difference::Moved { node, from, to } => {
+                format!("moved      {}: {} → {}", node.display(), from, to)
+            }
+            Difference::Recoded { node, from } => {
+                format!(
+                    "recoded    {} → {} {}",
+                    from,
+                    node.code.as_deref().unwrap_or("(none)"),
+                    node.title
+                )
+            }
+            Difference::Locations {
+                node,
+                added,
+                removed,
+            } => format!("locations  {}: {}", node.display(), list(added, removed)),
+            Difference::Links {
+                node,
+                added,
+                removed,
+            } => format!("links      {}: {}", node.display(), list(added, removed)),
+        }
+    }
+}
+
+/// Counts by kind, `2 added · 1 moved`, or `no changes`.
+pub fn summary(diffs: &[Difference]) -> String {
+    const KINDS: [&str; 7] = [
+        "added",
+        "removed",
+        "renamed",
+        "moved",
+        "recoded",
+        "locations",
+        "links",
+    ];
+    let parts: Vec<String> = KINDS
+        .iter()
+        .filter_map(|k| {
+            let n = diffs.iter().filter(|d| d.kind() == *k).count();
+            (n > 0).then(|| format!("{} {}", n, k))
+        })
+        .collect();
+    match parts.is_empty() {
+        true => "no changes".into(),
+        false => parts.join(" · "),
+    }
+}
+
+/// Everything that differs between `old` and `new`, in the newer tree's
+/// order with removals last. Added and removed subtrees are reported once,
+/// at their outermost node.
+pub fn differences(old: &Tree, new: &Tree) -> Vec<Difference> {
+    let (before, old_parent) = with_parents(old);
+    let (after, new_parent) = with_parents(new);
+    let pairs = pair(old, new, &before, &after);
+    let back: HashMap<&str, &str> = pairs.iter().map(|(o, n)| (*n, *o)).collect();
+    let label = |n: Option<&&Node>| n.map(|n| Brief::of(n).display()).unwrap_or_default();
+
+    let mut out = Vec::new();
+    for n in walk(new) {
+        let np = new_parent.get(n.id.as_str()).copied();
+        let Some(o) = back.get(n.id.as_str()).map(|id| before[id]) else {
+            if np.is_none_or(|p| back.contains_key(p)) {
+                out.push(Difference::Added {
+                    node: Brief::of(n),
+                    inside: count(n) - 1,
+                });
+            }
+            continue;
+        };
+        let node = Brief::of(n);
+        let op = old_parent.get(o.id.as_str()).copied();
+        if let (Some(op), Some(np)) = (op, np) {
+            if pairs.get(op).copied() != Some(np) {
+                out.push(Difference::Moved {
+                    node: node.clone(),
+                    from: label(before.get(op)),
+                    to: label(after.get(np)),
+                });
+            }
+        }
+        if o.code != n.code {
+            out.push(Difference::Recoded {
+                node: node.clone(),
+                from: o.code.clone().unwrap_or_else(|| "(none)".into()),
+            });
+        }
+        // roots are matched by position; their folder names are just where they are
+        if o.title != n.title && np.is_some() {
+            out.push(Difference::Renamed {
+                node: node.clone(),
+                from: o.title.clone(),
+            });
+        }
+        let (added, removed) = delta(&locations(o), &locations(n));
+        if !added.is_empty() || !removed.is_empty() {
+            out.push(Difference::Locations {
+                node: node.clone(),
+                added,
+                removed,
+            });
+        }
+        let (added, removed) = delta(&links(o), &links(n));
+        if !added.is_empty() || !removed.is_empty() {
+            out.push(Difference::Links {
+                node,
+                added,
+                removed,
+            });
+        }
+    }
+    for o in walk(old) {
+        let outermost = old_parent
+            .get(o.id.as_str())
+            .is_none_or(|p| pairs.contains_key(p));
+        if !pairs.contains_key(o.id.as_str()) && outermost {
+            out.push(Difference::Removed {
+                node: Brief::of(o),
+                inside: count(o) - 1,
+            });
+        }
+    }
+    out
+}
+
+/// Old id → new id: the same id, else roots by position, else the same
+/// code where it's unique among the unmatched nodes on both sides (and both
+/// are folders, or both files).
+fn pair<'a>(
+    old: &'a Tree,
+    new: &'a Tree,
+    before: &HashMap<&'a str, &'a Node>,
+    after: &HashMap<&'a str, &'a Node>,
+) -> HashMap<&'a str, &'a str> {
+    let mut pairs: HashMap<&str, &str> = before
+        .keys()
+        .filter(|id| after.contains_key(*id))
+        .map(|id| (*id, *id))
+        .collect();
+    for (o, n) in old.roots.iter().zip(&new.roots) {
+        if !pairs.contains_key(o.id.as_str()) && !after.contains_key(o.id.as_str()) {
+            pairs.insert(&o.id, &n.id);
+        }
+    }
+    let taken: HashSet<&str> = pairs.values().copied().collect();
+    let by_code = |nodes: &HashMap<&'a str, &'a Node>, skip: &dyn Fn(&str) -> bool| {
+        let mut m: HashMap<&'a str, Vec<&'a Node>> = HashMap::new();
+        for (id, n) in nodes {
+            if let (Some(c), false) = (&n.code, skip(id)) {
+                m.entry(c.as_str()).or_default().push(n);
+            }
+        }
+        m
+    };
+    let old_codes = by_code(before, &|id| pairs.contains_key(id));
+    let new_codes = by_code(after, &|id| taken.contains(id));
+    let file = |n: &Node| matches!(n.node_type, NodeType::File | NodeType::Link);
+    for (code, olds) in &old_codes {
+        let news = new_codes.get(code).map(|v| v.as_slice());
+        if let ([o], Some([n])) = (olds.as_slice(), news) {
+            if file(o) == file(n) {
+                pairs.insert(&o.id, &n.id);
+            }
+        }
+    }
+    pairs
+}
+
+fn with_parents(t: &Tree) -> (HashMap<&str, &Node>, HashMap<&str, &str>) {
+    let mut parents = HashMap::new();
+    for n in walk(t) {
+        for c in &n.children {
+            parents.insert(c.id.as_str(), n.id.as_str());
+        }
+    }
+    (by_id(t), parents)
+}
+
+/// Every node, depth first.
+fn walk(t: &Tree) -> Vec<&Node> {
+    fn go<'a>(n: &'a Node, out: &mut Vec<&'a Node>) {
+        out.push(n);
+        n.children.iter().for_each(|c| go(c, out));
+    }
+    let mut out = Vec::new();
+    t.roots.iter().for_each(|r| go(r, &mut out));
+    out
+}
+
+fn count(n: &Node) -> usize {
+    1 + n.children.iter().map(count).sum::<usize>()
+}
+
+fn locations(n: &Node) -> Vec<String> {
+    n.location.iter().chain(&n.locations).cloned().collect()
+}
+
+fn links(n: &Node) -> Vec<String> {
+    n.links
+        .iter()
+        .map(|l| match &l.label {
+            Some(label) => format!("{} {}", l.url, label),
+            None => l.url.clone(),
+        })
+        .collect()
+}
+
+/// What `new` has that `old` lacks, and the reverse.
+fn delta(old: &[String], new: &[String]) -> (Vec<String>, Vec<String>) {
+    let added = new.iter().filter(|x| !old.contains(x)).cloned().collect();
+    let removed = old.iter().filter(|x| !new.contains(x)).cloned().collect();
+    (added, removed)
+}
+
+fn by_id(t: &Tree) -> HashMap<&str, &Node> {
+    fn walk<'a>(n: &'a Node, out: &mut HashMap<&'a str, &'a Node>) {
+        out.insert(n.id.as_str(), n);
+        for c in &n.children {
+            walk(c, out);
+        }
+    }
+    let mut out = HashMap::new();
+    for r in &t.roots {
+        walk(r, &mut out);
+    }
+    out
+}
+
+fn same(a: &Node, b: &Node) -> bool {
+    a.code == b.code
+        && a.title == b.title
+        && a.path == b.path
+        && a.node_type == b.node_type
+        && a.location == b.location
+        && a.url == b.url
+        && a.locations == b.locations
+        && a.links == b.links
+        && a.has_notes == b.has_notes
+        && a.archived_from == b.archived_from
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fs_walk;
+    use std::fs;
+    use std::path::Path;
+
+    #[test]
+    fn added_removed_and_renamed_nodes() {
+        let td = tempfile::tempdir().unwrap();
+        let roots = [td.path().join("R")];
+        let cat = roots[0].join("20-29_Admin/21_Taxes");
+        for d in ["21.01_Returns", "21.02_Receipts", "21.03_Gone/21.03_inner"] {
+            fs::create_dir_all(cat.join(d)).unwrap();
+        }
+        let old = fs_walk::scan_roots(&roots).unwrap();
+        fs::rename(cat.join("21.01_Returns"), cat.join("21.01_Tax_returns")).unwrap();
+        // created first so it can't reuse a freed inode
+        fs::create_dir(cat.join("21.04_New")).unwrap();
+        fs::remove_dir_all(cat.join("21.03_Gone")).unwrap();
+        let new = fs_walk::scan_roots(&roots).unwrap();
+
+        let id = |t: &Tree, name: &str| {
+            let p = cat
+                .join(name)
+                .to_string_lossy()
+                .trim_end_matches('/')
+                .to_string();
+            fn find<'a>(n: &'a Node, p: &str) -> Option<&'a Node> {
+                if n.path == p {
+                    return Some(n);
+                }
+                n.children.iter().find_map(|c| find(c, p))
+            }
+            find(&t.roots[0], &p).unwrap().id.clone()
+        };
+        let c = changes(&old, &new);
+        assert_eq!(c.len(), 4, "{:?}", c);
+        assert_eq!(c[&id(&new, "21.01_Tax_returns")], Change::Changed);
+        assert_eq!(c[&id(&new, "21.04_New")], Change::Added);
+        assert_eq!(c[&id(&old, "21.03_Gone")], Change::Removed);
+        assert!(!c.contains_key(&id(&new, "21.02_Receipts")));
+
+        let gone = removed(&old, &new);
+        assert_eq!(gone.len(), 1);
+        assert_eq!(gone[0].0, id(&old, ""));
+        assert_eq!(gone[0].1.title, "Gone");
+    }
+
+    #[test]
+    fn differences_name_each_kind_of_change() {
+        let td = tempfile::tempdir().unwrap();
+        let roots = [td.path().join("R")];
+        let admin = roots[0].join("20-29_Admin");
+        for d in [
+            "21_Taxes/21.01_Returns",
+            "21_Taxes/21.02_Receipts",
+            "21_Taxes/21.03_Gone/21.03.01_Inner",
+            "22_Bills",
+        ] {
+            fs::create_dir_all(admin.join(d)).unwrap();
+        }
+        fs::write(
+            admin.join("21_Taxes/21.01_Returns/.jdmeta"),
+            "LOCATION=drawer\n",
+        )
+        .unwrap();
+        let old = fs_walk::scan_roots(&roots).unwrap();
+
+        let taxes = admin.join("21_Taxes");
+        fs::rename(taxes.join("21.01_Returns"), taxes.join("21.01_Tax_returns")).unwrap();
+        fs::write(
+            taxes.join("21.01_Tax_returns/.jdmeta"),
+            "LOCATION=box 4\nLINK=https://example.org Portal\n",
+        )
+        .unwrap();
+        fs::rename(
+            taxes.join("21.02_Receipts"),
+            admin.join("22_Bills/22.01_Receipts"),
+        )
+        .unwrap();
+        fs::create_dir(taxes.join("21.04_New")).unwrap();
+        fs::remove_dir_all(taxes.join("21.03_Gone")).unwrap();
+        let new = fs_walk::scan_roots(&roots).unwrap();
+
+        let diffs = differences(&old, &new);
+        let lines: Vec<String> = diffs.iter().map(|d| d.describe()).collect();
+        assert_eq!(
+            lines,
+            [
+                "renamed    Returns → Tax returns",
+                "locations  21.01 Tax returns: + box 4, - drawer",
+                "links      21.01 Tax returns: + https://example.org Portal",
+                "added      21.04 New",
+                "moved      22.01 Receipts: 21 Taxes → 22 Bills",
+                "recoded    21.02 → 22.01 Receipts",
+                "removed    21.03 Gone (and 1 inside)",
+            ]
+        );
+        assert_eq!(
+            summary(&diffs),
+            "1 added · 1 removed · 1 renamed · 1 moved · 1 recoded · 1 locations · 1 links"
+        );
+        assert_eq!(summary(&[]), "no changes");
+
+        // the same structure built elsewhere shares no inodes but every code
+        let copy = [td.path().join("Copy")];
+        for n in walk(&new).into_iter().skip(1) {
+            let rel = Path::new(&n.path).strip_prefix(&new.roots[0].path).unwrap();
+            fs::create_dir_all(copy[0].join(rel)).unwrap();
+        }
+        let elsewhere = fs_walk::scan_roots(&copy).unwrap();
+        let d = differences(&new, &elsewhere);
+        assert_eq!(d.len(), 2, "{:?}", d); // only the .jdmeta entries
+        assert!(matches!(d[0], Difference::Locations { .. }));
+    }
+}
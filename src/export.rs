@@ -0,0 +1,510 @@
+//! A printable JDex: the tree as a nested Markdown or HTML outline with each
+//! node's locations, links and rendered `.jdmeta.md` notes, for people who
+//! don't run jd-helper. Roots, ranges and categories become headings (the
+//! first exported node is level 1); items become nested lists.
+//!
+//! CSV and OPML carry the bare skeleton instead (code, title, type, path,
+//! locations, links) for spreadsheets and outliners, in the shape `import`
+//! reads back.
+
+use crate::date;
+use crate::meta::{self, Entry};
+use crate::model::{Node, NodeType};
+use pulldown_cmark::{html, Event, Options as MdOptions, Parser};
+use std::fmt::Write;
+use std::fs;
+use std::path::Path;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Format {
+    Markdown,
+    Html,
+    Csv,
+    Opml,
+}
+
+pub struct Options {
+    /// Show each node's filesystem path; off for a copy that leaves the machine.
+    pub paths: bool,
+}
+
+pub fn export(nodes: &[&Node], format: Format, opts: &Options) -> String {
+    let items: usize = nodes.iter().map(|n| count_items(n)).sum();
+    let stamp = format!(
+        "Exported {} by jd-helper · {} item{}",
+        date::date(date::now()),
+        items,
+        if items == 1 { "" } else { "s" }
+    );
+    let mut out = String::new();
+    match format {
+        Format::Markdown => {
+            let _ = writeln!(out, "_{}_", stamp);
+            for n in nodes {
+                markdown(n, 1, opts, &mut out);
+            }
+        }
+        Format::Html => {
+            let title = match nodes {
+                [n] => label(n),
+                _ => "JDex".to_string(),
+            };
+            let _ = write!(
+                out,
+                "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n\
+                 <title>{}</title>\n<style>{}</style>\n</head>\n<body>\n\
+                 <p class=\"stamp\">{}</p>\n",
+                esc(&title),
+                STYLE,
+                esc(&stamp)
+            );
+            for n in nodes {
+                html_node(n, 1, opts, &mut out);
+            }
+            out.push_str("</body>\n</html>\n");
+        }
+        Format::Csv => {
+            out.push_str("code,title,type,path,locations,links\n");
+            for n in nodes {
+                csv_rows(n, opts, &mut out);
+            }
+        }
+        Format::Opml => {
+            let title = match nodes {
+                [n] => label(n),
+                _ => "JDex".to_string(),
+            };
+            let _ = write!(
+                out,
+                "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<opml version=\"2.0\">\n\
+                 <head>\n<title>{}</title>\n</head>\n<body>\n",
+                attr(&title)
+            );
+            for n in nodes {
+                opml_node(n, 1, opts, &mut out);
+            }
+            out.push_str("</body>\n</opml>\n");
+        }
+    }
+    out
+}
+
+const STYLE: &str = "body{font:14px/1.45 -apple-system,Helvetica,sans-serif;max-width:52em;margin:2em auto;padding:0 1em}\
+.code{font-variant-numeric:tabular-nums;font-weight:600;margin-right:.4em}\
+.meta,.stamp,.path{color:#666;font-size:.9em}\
+.notes{border-left:3px solid #ddd;padding-left:.8em;margin:.3em 0}\
+h1,h2,h3,h4{break-after:avoid}li{break-inside:avoid}";
+
+fn is_item(n: &Node) -> bool {
+    matches!(
+        n.node_type,
+        NodeType::ItemDir | NodeType::File | NodeType::Link
+    )
+}
+
+fn count_items(n: &Node) -> usize {
+    usize::from(is_item(n)) + n.children.iter().map(count_items).sum::<usize>()
+}
+
+fn label(n: &Node) -> String {
+    match &n.code {
+        Some(c) => format!("{} {}", c, n.title),
+        None => n.title.clone(),
+    }
+}
+
+/// Locations and links from the node's `.jdmeta` (or a file item's
+/// sidecar), plus a file item's own `LOCATION=`.
+fn entries(n: &Node) -> Vec<Entry> {
+    let mut out: Vec<Entry> = n.location.iter().cloned().map(Entry::Location).collect();
+    out.extend(n.locations.iter().cloned().map(Entry::Location));
+    out.extend(n.links.iter().cloned().map(Entry::Link));
+    out
+}
+
+/// The node's notes without the `# Title` line `ensure_notes` starts them with.
+fn notes(n: &Node) -> Option<String> {
+    let path = Path::new(&n.path);
+    let text = if path.is_dir() {
+        meta::read_notes(path)?
+    } else {
+        let [_, notes] = meta::sidecars(path);
+        fs::read_to_string(notes).ok()?
+    };
+    let heading = format!("# {}", n.title);
+    let body = match text.split_once('\n') {
+        Some((first, rest)) if first.trim_end() == heading => rest,
+        _ if text.trim_end() == heading => "",
+        _ => text.as_str(),
+    };
+    let body = body.trim();
+    (!body.is_empty()).then(|| body.to_string())
+}
+
+fn markdown(n: &Node, level: usize, opts: &Options, out: &mut String) {
+    let mut extras: Vec<String> = entries(n).iter().map(md_entry).collect();
+    if opts.paths {
+        extras.push(format!("`{}`", n.path));
+    }
+    let _ = writeln!(out, "\n{} {}", "#".repeat(level.min(6)), md_esc(&label(n)));
+    if !extras.is_empty() {
+        let _ = writeln!(out, "\n{}", extras.join(" · "));
+    }
+    if let Some(text) = notes(n) {
+        let _ = writeln!(out);
+        quote(&text, "", out);
+    }
+    let (items, groups): (Vec<&Node>, Vec<&Node>) = n.children.iter().partition(|c| is_item(c));
+    if !items.is_empty() {
+        let _ = writeln!(out);
+        for c in items {
+            md_item(c, 0, opts, out);
+        }
+    }
+    for c in groups {
+        markdown(c, level + 1, opts, out);
+    }
+}
+
+fn md_item(n: &Node, depth: usize, opts: &Options, out: &mut String) {
+    let indent = "  ".repeat(depth);
+    let code = n
+        .code
+        .as_deref()
+        .map(|c| format!("**{}** ", c))
+        .unwrap_or_default();
+    let title = match &n.url {
+        Some(url) => md_link(url, &n.title),
+        None => md_esc(&n.title),
+    };
+    let mut extras: Vec<String> = entries(n).iter().map(md_entry).collect();
+    if opts.paths {
+        extras.push(format!("`{}`", n.path));
+    }
+    let _ = write!(out, "{}- {}{}", indent, code, title);
+    if !extras.is_empty() {
+        let _ = write!(out, " — {}", extras.join(" · "));
+    }
+    out.push('\n');
+    if let Some(text) = notes(n) {
+        quote(&text, &format!("{}  ", indent), out);
+    }
+    for c in &n.children {
+        md_item(c, depth + 1, opts, out);
+    }
+}
+
+fn md_entry(e: &Entry) -> String {
+    match e {
+        Entry::Location(s) => format!("⌂ {}", md_esc(s)),
+        Entry::Link(l) => md_link(&l.url, l.label.as_deref().unwrap_or(&l.url)),
+    }
+}
+
+/// `[label](url)`, with the characters that would end the destination early
+/// percent-encoded; just the label for a scheme `linkable` refuses.
+fn md_link(url: &str, label: &str) -> String {
+    if !linkable(url) {
+        return md_esc(label);
+    }
+    let mut dest = String::with_capacity(url.len());
+    for c in url.chars() {
+        match c {
+            ' ' => dest.push_str("%20"),
+            '(' => dest.push_str("%28"),
+            ')' => dest.push_str("%29"),
+            '<' => dest.push_str("%3C"),
+            '>' => dest.push_str("%3E"),
+            c => dest.push(c),
+        }
+    }
+    format!("[{}]({})", md_esc(label), dest)
+}
+
+fn quote(text: &str, indent: &str, out: &mut String) {
+    for line in text.lines() {
+        let _ = match line {
+            "" => writeln!(out, "{}>", indent),
+            _ => writeln!(out, "{}> {}", indent, line),
+        };
+    }
+}
+
+fn md_esc(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for c in s.chars() {
+        if matches!(c, '\\' | '*' | '_' | '[' | ']' | '`' | '<' | '>' | '#') {
+            out.push('\\');
+        }
+        out.push(c);
+    }
+    out
+}
+
+fn html_node(n: &Node, level: usize, opts: &Options, out: &mut String) {
+    let h = level.min(6);
+    let code = n
+        .code
+        .as_deref()
+        .map(|c| format!("<span class=\"code\">{}</span>", esc(c)))
+        .unwrap_or_default();
+    let _ = writeln!(out, "<section>\n<h{h}>{}{}</h{h}>", code, esc(&n.title));
+    html_details(n, opts, out);
+    let (items, groups): (Vec<&Node>, Vec<&Node>) = n.children.iter().partition(|c| is_item(c));
+    html_items(&items, opts, out);
+    for c in groups {
+        html_node(c, level + 1, opts, out);
+    }
+    out.push_str("</section>\n");
+}
+
+fn html_items(items: &[&Node], opts: &Options, out: &mut String) {
+    if items.is_empty() {
+        return;
+    }
+    out.push_str("<ul>\n");
+    for n in items {
+        let code = n
+            .code
+            .as_deref()
+            .map(|c| format!("<span class=\"code\">{}</span>", esc(c)))
+            .unwrap_or_default();
+        let title = match &n.url {
+            Some(url) => html_link(url, &n.title),
+            None => esc(&n.title),
+        };
+        let _ = writeln!(out, "<li>{}{}", code, title);
+        html_details(n, opts, out);
+        let children: Vec<&Node> = n.children.iter().collect();
+        html_items(&children, opts, out);
+        out.push_str("</li>\n");
+    }
+    out.push_str("</ul>\n");
+}
+
+fn html_details(n: &Node, opts: &Options, out: &mut String) {
+    let entries: Vec<String> = entries(n)
+        .iter()
+        .map(|e| match e {
+            Entry::Location(s) => format!("⌂ {}", esc(s)),
+            Entry::Link(l) => html_link(&l.url, l.label.as_deref().unwrap_or(&l.url)),
+        })
+        .collect();
+    if !entries.is_empty() {
+        let _ = writeln!(out, "<div class=\"meta\">{}</div>", entries.join(" · "));
+    }
+    if opts.paths {
+        let _ = writeln!(
+            out,
+            "<div class=\"path\"><code>{}</code></div>",
+            esc(&n.path)
+        );
+    }
+    if let Some(text) = notes(n) {
+        out.push_str("<div class=\"notes\">\n");
+        // raw HTML in a note is shown as text, never run in the page
+        let events = Parser::new_ext(&text, MdOptions::all()).map(|e| match e {
+            Event::Html(s) | Event::InlineHtml(s) => Event::Text(s),
+            e => e,
+        });
+        html::push_html(out, events);
+        out.push_str("</div>\n");
+    }
+}
+
+/// The `locations` and `links` columns: one `.jdmeta` entry per line, links
+/// as `URL label`. A link item's own URL comes first.
+fn entry_cells(n: &Node) -> [String; 2] {
+    let mut locations: Vec<String> = n.location.iter().cloned().collect();
+    locations.extend(n.locations.iter().cloned());
+    let links: Vec<String> = n
+        .url
+        .iter()
+        .cloned()
+        .chain(n.links.iter().map(|l| match &l.label {
+            Some(label) => format!("{} {}", l.url, label),
+            None => l.url.clone(),
+        }))
+        .collect();
+    [locations.join("\n"), links.join("\n")]
+}
+
+/// Coded nodes only: roots and other unnumbered folders don't round-trip.
+fn csv_rows(n: &Node, opts: &Options, out: &mut String) {
+    if let Some(code) = &n.code {
+        let [locations, links] = entry_cells(n);
+        let path = if opts.paths { n.path.as_str() } else { "" };
+        let ty = format!("{:?}", n.node_type);
+        let cells = [code.as_str(), &n.title, &ty, path, &locations, &links];
+        let cells: Vec<String> = cells.iter().map(|c| csv_field(c)).collect();
+        let _ = writeln!(out, "{}", cells.join(","));
+    }
+    for c in &n.children {
+        csv_rows(c, opts, out);
+    }
+}
+
+pub fn csv_field(s: &str) -> String {
+    if s.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", s.replace('"', "\"\""))
+    } else {
+        s.to_string()
+    }
+}
+
+fn opml_node(n: &Node, depth: usize, opts: &Options, out: &mut String) {
+    let indent = "  ".repeat(depth);
+    let [locations, links] = entry_cells(n);
+    let _ = write!(out, "{}<outline text=\"{}\"", indent, attr(&label(n)));
+    if let Some(code) = &n.code {
+        let _ = write!(out, " code=\"{}\"", attr(code));
+    }
+    let _ = write!(
+        out,
+        " title=\"{}\" nodeType=\"{:?}\"",
+        attr(&n.title),
+        n.node_type
+    );
+    if let Some(url) = &n.url {
+        let _ = write!(out, " type=\"link\" url=\"{}\"", attr(url));
+    }
+    if opts.paths {
+        let _ = write!(out, " path=\"{}\"", attr(&n.path));
+    }
+    for (key, value) in [("locations", &locations), ("links", &links)] {
+        if !value.is_empty() {
+            let _ = write!(out, " {}=\"{}\"", key, attr(value));
+        }
+    }
+    if n.children.is_empty() {
+        out.push_str("/>\n");
+        return;
+    }
+    out.push_str(">\n");
+    for c in &n.children {
+        opml_node(c, depth + 1, opts, out);
+    }
+    let _ = writeln!(out, "{}</outline>", indent);
+}
+
+/// `esc` plus newlines, which XML would otherwise fold into spaces.
+fn attr(s: &str) -> String {
+    esc(s).replace('\n', "&#10;")
+}
+
+/// A link for a scheme `linkable` accepts; anything else is plain text, so a
+/// `javascript:` or `data:` URL can't run in a published page.
+fn html_link(url: &str, label: &str) -> String {
+    match linkable(url) {
+        true => format!("<a href=\"{}\">{}</a>", esc(url), esc(label)),
+        false => esc(label),
+    }
+}
+
+fn linkable(url: &str) -> bool {
+    url.split_once(':').is_some_and(|(scheme, _)| {
+        ["http", "https", "mailto", "file"]
+            .iter()
+            .any(|s| s.eq_ignore_ascii_case(scheme.trim_start()))
+    })
+}
+
+fn esc(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fs_walk;
+
+    #[test]
+    fn outline_with_entries_notes_and_optional_paths() {
+        let td = tempfile::tempdir().unwrap();
+        let roots = [td.path().join("R")];
+        let cat = roots[0].join("20-29_Admin/21_Taxes");
+        let item = cat.join("21.01_Returns");
+        fs::create_dir_all(&item).unwrap();
+        fs::write(
+            item.join(".jdmeta"),
+            "LOCATION=filing cabinet\nLINK=https://example.org/tax Portal\n",
+        )
+        .unwrap();
+        fs::write(
+            item.join(".jdmeta.md"),
+            "# Returns\n\nFiled by **March**.\n\n<script>alert(1)</script>\n\nSee <b>box</b>.\n",
+        )
+        .unwrap();
+        fs::write(cat.join("21.02_Receipts.pdf"), b"").unwrap();
+        let links = cat.join("21.03_Links");
+        fs::create_dir_all(&links).unwrap();
+        fs::write(
+            links.join(".jdmeta"),
+            "LINK=javascript:alert(1) Evil\nLINK=https://en.wikipedia.org/wiki/Tax_(law) Law\n",
+        )
+        .unwrap();
+        let tree = fs_walk::scan_roots(&roots).unwrap();
+        let root = &tree.roots[0];
+
+        let md = export(&[root], Format::Markdown, &Options { paths: false });
+        assert!(md.contains("\n# R\n"), "{}", md);
+        assert!(md.contains("\n## 20-29 Admin\n"), "{}", md);
+        assert!(md.contains("\n### 21 Taxes\n"), "{}", md);
+        assert!(md.contains(
+            "- **21.01** Returns — ⌂ filing cabinet · [Portal](https://example.org/tax)\n  > Filed by **March**.\n"
+        ), "{}", md);
+        assert!(md.contains("- **21.02** Receipts\n"), "{}", md);
+        // no live script link; a `)` in a URL doesn't end it early
+        assert!(
+            md.contains(
+                "- **21.03** Links — Evil · [Law](https://en.wikipedia.org/wiki/Tax_%28law%29)\n"
+            ),
+            "{}",
+            md
+        );
+        assert!(md.contains("· 3 items_"), "{}", md);
+        assert!(!md.contains(&*td.path().to_string_lossy()), "{}", md);
+
+        let admin = &root.children[0];
+        let html = export(&[admin], Format::Html, &Options { paths: true });
+        assert!(
+            html.contains("<h1><span class=\"code\">20-29</span>Admin</h1>"),
+            "{}",
+            html
+        );
+        assert!(
+            html.contains("<h2><span class=\"code\">21</span>Taxes</h2>"),
+            "{}",
+            html
+        );
+        assert!(
+            html.contains("<p>Filed by <strong>March</strong>.</p>"),
+            "{}",
+            html
+        );
+        assert!(!html.contains("javascript:"), "{}", html);
+        assert!(html.contains("Evil · <a href="), "{}", html);
+        assert!(
+            !html.contains("<script>") && !html.contains("<b>"),
+            "{}",
+            html
+        );
+        assert!(
+            html.contains("&lt;script&gt;alert(1)&lt;/script&gt;"),
+            "{}",
+            html
+        );
+        assert!(
+            html.contains("<a href=\"https://example.org/tax\">Portal</a>"),
+            "{}",
+            html
+        );
+        assert!(html.contains(&format!(
+            "<code>{}</code>",
+            item.canonicalize().unwrap().display()
+        )));
+    }
+}
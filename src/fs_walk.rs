@@ -88,6 +88,7 @@ fn scan_dir(path: &Path, is_root: bool, warnings: &mut Vec<String>) -> Result<No
     let mut locations: Vec<String> = Vec::new();
     let mut links: Vec<crate::meta::MetaLink> = Vec::new();
     let mut has_notes = false;
+    let mut archived_from = None;
     if path.is_dir() {
         let mut entries: Vec<PathBuf> = Vec::new();
         let mut has_meta = false;
@@ -107,6 +108,7 @@ fn scan_dir(path: &Path, is_root: bool, warnings: &mut Vec<String>) -> Result<No
         }
         entries.sort();
         if has_meta {
+            archived_from = meta::field(&path.join(meta::META_FILE), meta::ARCHIVED_FROM);
             for entry in meta::entries(path) {
                 match entry {
                     Entry::Location(s) => locations.push(s),
@@ -143,6 +145,16 @@ fn scan_dir(path: &Path, is_root: bool, warnings: &mut Vec<String>) -> Result<No
                             (NodeType::File, None, loc)
                         }
                     };
+                    // an unwrapped item's entries, kept in `.NAME.jdmeta`
+                    let (mut locations, mut links) = (vec![], vec![]);
+                    for entry in meta::file_entries(&child) {
+                        match entry {
+                            Entry::Location(s) => locations.push(s),
+                            Entry::Link(l) => links.push(l),
+                        }
+                    }
+                    let archived_from =
+                        meta::field(&meta::sidecars(&child)[0], meta::ARCHIVED_FROM);
                     children.push(Node {
                         id: make_id(&child),
                         code: Some(code),
@@ -151,9 +163,10 @@ fn scan_dir(path: &Path, is_root: bool, warnings: &mut Vec<String>) -> Result<No
                         node_type: nt,
                         location,
                         url: url_opt,
-                        locations: vec![],
-                        links: vec![],
+                        locations,
+                        links,
                         has_notes: false,
+                        archived_from,
                         children: vec![],
                     });
                 }
@@ -182,6 +195,7 @@ fn scan_dir(path: &Path, is_root: bool, warnings: &mut Vec<String>) -> Result<No
         locations,
         links,
         has_notes,
+        archived_from,
         children,
     })
 }
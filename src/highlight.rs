@@ -0,0 +1,333 @@
+//! Colours for source files in the preview pane: keywords, strings, comments
+//! and numbers. A scanner, not a parser, carried line to line only for block
+//! comments; enough to make a script or config readable at a glance.
+
+use crate::ui::theme;
+use ratatui::text::{Line, Span};
+
+pub struct Lang {
+    keywords: &'static [&'static str],
+    line_comment: &'static [&'static str],
+    block_comment: Option<(&'static str, &'static str)>,
+    quotes: &'static [char],
+}
+
+const C_LIKE_QUOTES: &[char] = &['"', '\''];
+
+const RUST: Lang = Lang {
+    keywords: &[
+        "as", "async", "await", "break", "const", "continue", "crate", "dyn", "else", "enum",
+        "false", "fn", "for", "if", "impl", "in", "let", "loop", "match", "mod", "move", "mut",
+        "pub", "ref", "return", "self", "Self", "static", "struct", "super", "trait", "true",
+        "type", "unsafe", "use", "where", "while",
+    ],
+    line_comment: &["//"],
+    block_comment: Some(("/*", "*/")),
+    // '\'' is a lifetime as often as a char
+    quotes: &['"'],
+};
+
+const PYTHON: Lang = Lang {
+    keywords: &[
+        "and", "as", "assert", "async", "await", "break", "class", "continue", "def", "del",
+        "elif", "else", "except", "False", "finally", "for", "from", "global", "if", "import",
+        "in", "is", "lambda", "None", "nonlocal", "not", "or", "pass", "raise", "return", "True",
+        "try", "while", "with", "yield",
+    ],
+    line_comment: &["#"],
+    block_comment: None,
+    quotes: C_LIKE_QUOTES,
+};
+
+const SHELL: Lang = Lang {
+    keywords: &[
+        "case", "do", "done", "elif", "else", "esac", "export", "fi", "for", "function", "if",
+        "in", "local", "return", "select", "then", "until", "while",
+    ],
+    line_comment: &["#"],
+    block_comment: None,
+    quotes: C_LIKE_QUOTES,
+};
+
+const JS: Lang = Lang {
+    keywords: &[
+        "async",
+        "await",
+        "break",
+        "case",
+        "catch",
+        "class",
+        "const",
+        "continue",
+        "default",
+        "delete",
+        "else",
+        "export",
+        "extends",
+        "false",
+        "finally",
+        "for",
+        "from",
+        "function",
+        "if",
+        "import",
+        "in",
+        "instanceof",
+        "interface",
+        "let",
+        "new",
+        "null",
+        "of",
+        "return",
+        "switch",
+        "this",
+        "throw",
+        "true",
+        "try",
+        "type",
+        "typeof",
+        "undefined",
+        "var",
+        "while",
+        "yield",
+    ],
+    line_comment: &["//"],
+    block_comment: Some(("/*", "*/")),
+    quotes: &['"', '\'', '`'],
+};
+
+const C: Lang = Lang {
+    keywords: &[
+        "auto",
+        "bool",
+        "break",
+        "case",
+        "char",
+        "class",
+        "const",
+        "continue",
+        "default",
+        "define",
+        "do",
+        "double",
+        "else",
+        "enum",
+        "extern",
+        "false",
+        "float",
+        "for",
+        "if",
+        "include",
+        "int",
+        "long",
+        "namespace",
+        "nullptr",
+        "private",
+        "public",
+        "return",
+        "short",
+        "signed",
+        "sizeof",
+        "static",
+        "struct",
+        "switch",
+        "template",
+        "true",
+        "typedef",
+        "union",
+        "unsigned",
+        "void",
+        "while",
+    ],
+    line_comment: &["//"],
+    block_comment: Some(("/*", "*/")),
+    quotes: C_LIKE_QUOTES,
+};
+
+const GO: Lang = Lang {
+    keywords: &[
+        "break",
+        "case",
+        "chan",
+        "const",
+        "continue",
+        "default",
+        "defer",
+        "else",
+        "false",
+        "for",
+        "func",
+        "go",
+        "if",
+        "import",
+        "interface",
+        "map",
+        "nil",
+        "package",
+        "range",
+        "return",
+        "select",
+        "struct",
+        "switch",
+        "true",
+        "type",
+        "var",
+    ],
+    line_comment: &["//"],
+    block_comment: Some(("/*", "*/")),
+    quotes: &['"', '\'', '`'],
+};
+
+const SQL: Lang = Lang {
+    keywords: &[
+        "and", "as", "by", "create", "delete", "from", "group", "insert", "into", "join", "left",
+        "not", "null", "on", "or", "order", "select", "set", "table", "update", "values", "where",
+    ],
+    line_comment: &["--"],
+    block_comment: Some(("/*", "*/")),
+    quotes: &['\''],
+};
+
+/// Config and data: strings, numbers, comments and the literals.
+const DATA: Lang = Lang {
+    keywords: &["true", "false", "null", "yes", "no"],
+    line_comment: &["#"],
+    block_comment: None,
+    quotes: C_LIKE_QUOTES,
+};
+
+const JSON: Lang = Lang {
+    keywords: &["true", "false", "null"],
+    line_comment: &[],
+    block_comment: None,
+    quotes: &['"'],
+};
+
+/// The language for a file extension, if it's one we colour.
+pub fn language(ext: &str) -> Option<&'static Lang> {
+    Some(match ext.to_ascii_lowercase().as_str() {
+        "rs" => &RUST,
+        "py" => &PYTHON,
+        "sh" | "bash" | "zsh" | "fish" => &SHELL,
+        "js" | "mjs" | "cjs" | "jsx" | "ts" | "tsx" => &JS,
+        "c" | "h" | "cc" | "cpp" | "hpp" | "java" | "swift" | "kt" => &C,
+        "go" => &GO,
+        "sql" => &SQL,
+        "toml" | "yaml" | "yml" | "ini" | "conf" => &DATA,
+        "json" => &JSON,
+        _ => return None,
+    })
+}
+
+pub fn render(src: &str, lang: &Lang) -> Vec<Line<'static>> {
+    let mut in_block = false;
+    src.lines()
+        .map(|l| Line::from(line(l, lang, &mut in_block)))
+        .collect()
+}
+
+fn line(s: &str, lang: &Lang, in_block: &mut bool) -> Vec<Span<'static>> {
+    let mut spans = Vec::new();
+    let mut plain = String::new();
+    let flush = |plain: &mut String, spans: &mut Vec<Span<'static>>| {
+        if !plain.is_empty() {
+            spans.push(Span::raw(std::mem::take(plain)));
+        }
+    };
+    let mut i = 0;
+    while i < s.len() {
+        let rest = &s[i..];
+        if *in_block {
+            let (_, close) = lang.block_comment.unwrap();
+            let end = rest.find(close).map_or(rest.len(), |e| e + close.len());
+            *in_block = end == rest.len() && !rest.ends_with(close);
+            spans.push(Span::styled(rest[..end].to_string(), theme::COMMENT));
+            i += end;
+            continue;
+        }
+        if let Some((open, _)) = lang.block_comment.filter(|(o, _)| rest.starts_with(o)) {
+            flush(&mut plain, &mut spans);
+            *in_block = true;
+            spans.push(Span::styled(open.to_string(), theme::COMMENT));
+            i += open.len();
+            continue;
+        }
+        if lang.line_comment.iter().any(|c| rest.starts_with(c)) {
+            flush(&mut plain, &mut spans);
+            spans.push(Span::styled(rest.to_string(), theme::COMMENT));
+            break;
+        }
+        let c = rest.chars().next().unwrap();
+        if lang.quotes.contains(&c) {
+            flush(&mut plain, &mut spans);
+            // to the closing quote, skipping escaped ones
+            let mut end = rest.len();
+            let mut escaped = false;
+            for (j, d) in rest.char_indices().skip(1) {
+                if escaped {
+                    escaped = false;
+                } else if d == '\\' {
+                    escaped = true;
+                } else if d == c {
+                    end = j + d.len_utf8();
+                    break;
+                }
+            }
+            spans.push(Span::styled(rest[..end].to_string(), theme::STRING));
+            i += end;
+            continue;
+        }
+        if c.is_alphanumeric() || c == '_' {
+            let end = rest
+                .find(|d: char| {
+                    !(d.is_alphanumeric() || d == '_' || d == '.' && c.is_ascii_digit())
+                })
+                .unwrap_or(rest.len());
+            let word = &rest[..end];
+            // the first character of an identifier can't be a digit, so
+            // this is a number (12, 0x1f, 1.5)
+            let style = if c.is_ascii_digit() {
+                Some(theme::NUMBER)
+            } else if lang.keywords.contains(&word) {
+                Some(theme::KEYWORD)
+            } else {
+                None
+            };
+            match style {
+                Some(st) => {
+                    flush(&mut plain, &mut spans);
+                    spans.push(Span::styled(word.to_string(), st));
+                }
+                None => plain.push_str(word),
+            }
+            i += end;
+            continue;
+        }
+        plain.push(c);
+        i += c.len_utf8();
+    }
+    flush(&mut plain, &mut spans);
+    spans
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keywords_strings_comments_and_numbers() {
+        let lines = render(
+            "fn main() { let s = \"a \\\" // b\"; } // done\n/* one\ntwo */ 42",
+            language("rs").unwrap(),
+        );
+        let styled =
+            |l: &Line, text: &str| l.spans.iter().find(|s| s.content == text).map(|s| s.style);
+        assert_eq!(styled(&lines[0], "fn"), Some(theme::KEYWORD));
+        assert_eq!(styled(&lines[0], "\"a \\\" // b\""), Some(theme::STRING));
+        assert_eq!(styled(&lines[0], "// done"), Some(theme::COMMENT));
+        // the block comment carries over to the next line, then ends
+        assert_eq!(styled(&lines[2], "two */"), Some(theme::COMMENT));
+        assert_eq!(styled(&lines[2], "42"), Some(theme::NUMBER));
+        assert!(language("pdf").is_none());
+    }
+}
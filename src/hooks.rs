@@ -0,0 +1,195 @@
+//! Shell commands run before and after each kind of change, configured in
+//! `config.json`:
+//!
+//! ```json
+//! {"hooks": {
+//!   "pre":  {"delete": "test \"$JD_CODE\" != 00.00"},
+//!   "post": {"*": "git -C ~/jd add -A && git -C ~/jd commit -qm \"$JD_OP $JD_NEW_PATH\""}
+//! }}
+//! ```
+//!
+//! Keys are operation kinds (`create`, `rename`, `move`, `delete`,
+//! `restore`, `purge`, `renumber`, `recode`, `merge`, `file`, `wrap`,
+//! `unwrap`, `archive`, `unarchive`, `apply`); `*` covers the ones without
+//! their own entry. A hook gets the plan as JSON on stdin and `JD_OP`, `JD_OLD_PATH`,
+//! `JD_NEW_PATH` and `JD_CODE` in its environment (unset when they don't
+//! apply). A failing pre-hook aborts the operation; a failing post-hook is
+//! collected for `take_failures` — the change itself has happened.
+
+use crate::config::{self, Hooks};
+use anyhow::{bail, Result};
+use serde::Serialize;
+use std::cell::{Cell, RefCell};
+use std::collections::BTreeMap;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+pub struct Event {
+    pub op: &'static str,
+    pub old_path: Option<PathBuf>,
+    pub new_path: Option<PathBuf>,
+    pub code: Option<String>,
+    pub plan: serde_json::Value,
+}
+
+impl Event {
+    pub fn new(op: &'static str, plan: &impl Serialize) -> Event {
+        Event {
+            op,
+            old_path: None,
+            new_path: None,
+            code: None,
+            plan: serde_json::to_value(plan).unwrap_or_default(),
+        }
+    }
+
+    pub fn paths(mut self, old: Option<&Path>, new: Option<&Path>) -> Event {
+        self.old_path = old.map(Path::to_path_buf);
+        self.new_path = new.map(Path::to_path_buf);
+        self
+    }
+
+    pub fn code(mut self, code: Option<&str>) -> Event {
+        self.code = code.map(str::to_string);
+        self
+    }
+}
+
+thread_local! {
+    static RUNNING: Cell<bool> = const { Cell::new(false) };
+    static FAILURES: RefCell<Vec<String>> = const { RefCell::new(Vec::new()) };
+}
+
+/// Run `f` between the configured pre- and post-hooks for `ev`. Operations
+/// started from inside `f` (a merge trashing its source) don't fire hooks
+/// of their own.
+pub fn around<T>(ev: Event, f: impl FnOnce() -> Result<T>) -> Result<T> {
+    around_with(&config::get().hooks, ev, f)
+}
+
+pub fn around_with<T>(hooks: &Hooks, ev: Event, f: impl FnOnce() -> Result<T>) -> Result<T> {
+    if RUNNING.with(Cell::get) {
+        return f();
+    }
+    if let Some(cmd) = pick(&hooks.pre, ev.op) {
+        if let Err(e) = run(cmd, &ev) {
+            bail!("pre-{} hook failed, nothing changed: {}", ev.op, e);
+        }
+    }
+    RUNNING.with(|r| r.set(true));
+    let out = f();
+    RUNNING.with(|r| r.set(false));
+    if out.is_ok() {
+        if let Some(cmd) = pick(&hooks.post, ev.op) {
+            if let Err(e) = run(cmd, &ev) {
+                let msg = format!("post-{} hook failed: {}", ev.op, e);
+                FAILURES.with(|f| f.borrow_mut().push(msg));
+            }
+        }
+    }
+    out
+}
+
+/// Post-hook failures since the last call, oldest first.
+pub fn take_failures() -> Vec<String> {
+    FAILURES.with(|f| std::mem::take(&mut *f.borrow_mut()))
+}
+
+fn pick<'a>(table: &'a BTreeMap<String, String>, op: &str) -> Option<&'a str> {
+    table
+        .get(op)
+        .or_else(|| table.get("*"))
+        .map(String::as_str)
+        .filter(|c| !c.trim().is_empty())
+}
+
+/// Run one hook command with `sh -c`. Its output is captured (the TUI owns
+/// the terminal); the last line of stderr explains a failure.
+pub fn run(cmd: &str, ev: &Event) -> Result<()> {
+    let mut c = Command::new("sh");
+    c.arg("-c")
+        .arg(cmd)
+        .env("JD_OP", ev.op)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped());
+    for (key, val) in [
+        ("JD_OLD_PATH", ev.old_path.as_ref().map(|p| p.as_os_str())),
+        ("JD_NEW_PATH", ev.new_path.as_ref().map(|p| p.as_os_str())),
+        ("JD_CODE", ev.code.as_deref().map(std::ffi::OsStr::new)),
+    ] {
+        match val {
+            Some(v) => c.env(key, v),
+            None => c.env_remove(key),
+        };
+    }
+    let mut child = c.spawn()?;
+    let json = serde_json::to_vec(&ev.plan)?;
+    if let Some(mut stdin) = child.stdin.take() {
+        // a hook that ignores its stdin may exit before reading it
+        let _ = stdin.write_all(&json);
+    }
+    let out = child.wait_with_output()?;
+    if !out.status.success() {
+        let stderr = String::from_utf8_lossy(&out.stderr);
+        let last = stderr.lines().rev().find(|l| !l.trim().is_empty());
+        bail!(
+            "`{}` {}{}",
+            cmd,
+            out.status,
+            last.map(|l| format!(": {}", l.trim())).unwrap_or_default()
+        );
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hooks_see_plan_and_env_and_pre_failure_aborts() {
+        let td = tempfile::tempdir().unwrap();
+        let log = td.path().join("log");
+        let mut hooks = Hooks::default();
+        hooks.post.insert(
+            "*".into(),
+            format!(
+                "{{ echo \"$JD_OP|$JD_OLD_PATH|$JD_NEW_PATH|$JD_CODE\"; cat; }} > {}",
+                log.display()
+            ),
+        );
+        hooks
+            .post
+            .insert("delete".into(), "echo gone >&2; exit 3".into());
+        hooks
+            .pre
+            .insert("move".into(), "echo no moves >&2; false".into());
+        let ev = |op| {
+            Event::new(op, &serde_json::json!({"k": 1}))
+                .paths(None, Some(Path::new("/r/31.05_New")))
+                .code(Some("31.05"))
+        };
+
+        around_with(&hooks, ev("create"), || Ok(())).unwrap();
+        let seen = std::fs::read_to_string(&log).unwrap();
+        assert_eq!(seen, "create||/r/31.05_New|31.05\n{\"k\":1}");
+
+        let mut ran = false;
+        let err = around_with(&hooks, ev("move"), || {
+            ran = true;
+            Ok(())
+        })
+        .unwrap_err();
+        assert!(!ran);
+        assert!(err.to_string().contains("no moves"), "{}", err);
+
+        around_with(&hooks, ev("delete"), || Ok(())).unwrap();
+        let failed = take_failures();
+        assert_eq!(failed.len(), 1);
+        assert!(failed[0].starts_with("post-delete hook failed"));
+        assert!(failed[0].ends_with(": gone"), "{}", failed[0]);
+        assert!(take_failures().is_empty());
+    }
+}
@@ -21,13 +21,15 @@ pub fn is_ignored_dir_name(name: &str) -> bool {
             | ".tmp"
             | "logs"
             | ".jd_trash"
+            | ".jd_snapshots"
+            | ".jdtemplates"
     )
 }
 
 pub fn is_ignored_file_name(name: &str) -> bool {
     let n = name.to_lowercase();
     // macOS Finder metadata; jd's own per-directory metadata file
-    if n == ".ds_store" || n == ".jdmeta" || n == ".jdmeta.md" {
+    if n == ".ds_store" || n == ".jdmeta" || n == ".jdmeta.md" || n == ".jd_lock" {
         return true;
     }
     // Logs and backups
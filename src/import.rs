@@ -0,0 +1,500 @@
+//! Creating a skeleton planned elsewhere: rows of a CSV or OPML file (as
+//! `export --format csv|opml` writes them, or typed into a spreadsheet or an
+//! outliner) become batch `create` steps for the codes a root doesn't have
+//! yet. Each step goes through `plan::parse_new_input_forced` like a Ctrl-N
+//! entry, so names are sanitised and templates applied the same way.
+
+use crate::batch::{BatchOp, Step};
+use crate::meta::{self, Entry};
+use crate::model::{Node, NodeType};
+use crate::plan::PlanKind;
+use anyhow::{bail, Result};
+use once_cell::sync::Lazy;
+use quick_xml::events::Event;
+use quick_xml::Reader;
+use regex::Regex;
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+
+#[derive(Clone, Debug, Default)]
+pub struct Row {
+    pub code: String,
+    pub title: String,
+    pub node_type: Option<NodeType>,
+    /// Extension of the `path` column, which keeps a file item's kind.
+    pub ext: Option<String>,
+    pub locations: Vec<String>,
+    /// `URL label` lines; a link item's own URL first.
+    pub links: Vec<String>,
+}
+
+impl Row {
+    fn label(&self) -> String {
+        format!("{} {}", self.code, self.title)
+    }
+}
+
+/// Rows of a CSV with a header naming its columns: `code`, `title`, `type`,
+/// `path`, `locations`, `links` (any order, only `title` required). Without
+/// a code, a title like "21 Taxes" supplies one.
+pub fn read_csv(text: &str) -> Result<(Vec<Row>, Vec<String>)> {
+    let mut records = csv_records(text.trim_start_matches('\u{feff}'))?.into_iter();
+    let Some(header) = records.next() else {
+        bail!("empty file")
+    };
+    let col: HashMap<String, usize> = header
+        .iter()
+        .enumerate()
+        .map(|(i, h)| (h.trim().to_lowercase(), i))
+        .collect();
+    if !col.contains_key("title") {
+        bail!("no \"title\" column in the header");
+    }
+    let mut rows = Vec::new();
+    let mut skipped = Vec::new();
+    for (i, rec) in records.enumerate() {
+        let get = |k: &str| {
+            col.get(k)
+                .and_then(|&c| rec.get(c))
+                .map(|s| s.trim())
+                .unwrap_or("")
+        };
+        let fields = HashMap::from([
+            ("code", get("code")),
+            ("title", get("title")),
+            ("type", get("type")),
+            ("path", get("path")),
+            ("locations", get("locations")),
+            ("links", get("links")),
+        ]);
+        match row(&fields) {
+            Ok(Some(r)) => rows.push(r),
+            Ok(None) if fields["title"].is_empty() => {}
+            Ok(None) => skipped.push(format!("line {}: {}: no code", i + 2, fields["title"])),
+            Err(e) => skipped.push(format!("line {}: {}", i + 2, e)),
+        }
+    }
+    Ok((rows, skipped))
+}
+
+/// Rows of an OPML outline. Outlines carry `code`/`title`/`nodeType` as
+/// `export` writes them, or just `text` ("21.04 Quantum notes") as most
+/// outliners do; `type="link"` with `url` is a link item. The nesting
+/// itself is ignored — codes say where things go. Uncoded outlines with
+/// children (the root, grouping headings) are passed over silently.
+pub fn read_opml(text: &str) -> Result<(Vec<Row>, Vec<String>)> {
+    let mut reader = Reader::from_str(text);
+    let mut rows = Vec::new();
+    let mut skipped = Vec::new();
+    loop {
+        let (e, leaf) = match reader.read_event()? {
+            Event::Start(e) => (e, false),
+            Event::Empty(e) => (e, true),
+            Event::Eof => break,
+            _ => continue,
+        };
+        if e.local_name().as_ref() != b"outline" {
+            continue;
+        }
+        let mut attrs: HashMap<String, String> = HashMap::new();
+        for a in e.attributes() {
+            let a = a?;
+            let key = String::from_utf8_lossy(a.key.local_name().as_ref()).to_string();
+            attrs.insert(key, a.unescape_value()?.to_string());
+        }
+        let get = |k: &str| attrs.get(k).map(|s| s.trim()).unwrap_or("");
+        let mut links = get("links").to_string();
+        if get("type").eq_ignore_ascii_case("link")
+            && !get("url").is_empty()
+            && !get("links").starts_with(get("url"))
+        {
+            links = format!("{}\n{}", get("url"), links);
+        }
+        let node_type = match get("nodeType") {
+            "" if get("type").eq_ignore_ascii_case("link") => "Link",
+            t => t,
+        };
+        let title = match get("title") {
+            "" => get("text"),
+            t => t,
+        };
+        let fields = HashMap::from([
+            ("code", get("code")),
+            ("title", title),
+            ("type", node_type),
+            ("path", get("path")),
+            ("locations", get("locations")),
+            ("links", links.trim()),
+        ]);
+        match row(&fields) {
+            Ok(Some(r)) => rows.push(r),
+            Ok(None) if !leaf || title.is_empty() => {}
+            Ok(None) => skipped.push(format!("{}: no code", title)),
+            Err(e) => skipped.push(e.to_string()),
+        }
+    }
+    Ok((rows, skipped))
+}
+
+/// None for a row without a code.
+fn row(f: &HashMap<&str, &str>) -> Result<Option<Row>> {
+    static LABEL: Lazy<Regex> = Lazy::new(|| {
+        Regex::new(r"^(\d{2}-\d{2}|\d{2}\.\d{2,4}(?:\.\d{2})*|\d{2})[ _-]+(.+)$").unwrap()
+    });
+    let (code, title) = match (f["code"], f["title"]) {
+        ("", t) => match LABEL.captures(t) {
+            Some(c) => (c[1].to_string(), c[2].trim().to_string()),
+            None => return Ok(None),
+        },
+        // a label repeated in the title column ("21 Taxes" beside "21")
+        (c, t) => match t.strip_prefix(c) {
+            Some(rest) if rest.starts_with(' ') => (c.to_string(), rest.trim().to_string()),
+            _ => (c.to_string(), t.to_string()),
+        },
+    };
+    if title.is_empty() {
+        bail!("{}: no title", code);
+    }
+    let node_type = match f["type"].to_lowercase().as_str() {
+        "" => None,
+        "range" => Some(NodeType::Range),
+        "category" => Some(NodeType::Category),
+        "itemdir" | "item" | "folder" => Some(NodeType::ItemDir),
+        "file" => Some(NodeType::File),
+        "link" => Some(NodeType::Link),
+        t => bail!("{} {}: unknown type {:?}", code, title, t),
+    };
+    let ext = Path::new(f["path"])
+        .extension()
+        .map(|e| e.to_string_lossy().to_string());
+    let lines = |s: &str| -> Vec<String> {
+        s.lines()
+            .map(str::trim)
+            .filter(|l| !l.is_empty())
+            .map(str::to_string)
+            .collect()
+    };
+    Ok(Some(Row {
+        code,
+        title,
+        node_type,
+        ext,
+        locations: lines(f["locations"]),
+        links: lines(f["links"]),
+    }))
+}
+
+/// RFC 4180 records: quoted fields may hold commas, doubled quotes and
+/// newlines; blank lines are dropped.
+fn csv_records(text: &str) -> Result<Vec<Vec<String>>> {
+    let mut records = Vec::new();
+    let mut rec = Vec::new();
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        if quoted {
+            match c {
+                '"' if chars.peek() == Some(&'"') => {
+                    field.push('"');
+                    chars.next();
+                }
+                '"' => quoted = false,
+                _ => field.push(c),
+            }
+            continue;
+        }
+        match c {
+            '"' if field.is_empty() => quoted = true,
+            ',' => rec.push(std::mem::take(&mut field)),
+            '\r' => {}
+            '\n' => {
+                rec.push(std::mem::take(&mut field));
+                records.push(std::mem::take(&mut rec));
+            }
+            _ => field.push(c),
+        }
+    }
+    if quoted {
+        bail!("unterminated quoted field");
+    }
+    if !field.is_empty() || !rec.is_empty() {
+        rec.push(field);
+        records.push(rec);
+    }
+    records.retain(|r| !(r.len() == 1 && r[0].is_empty()));
+    Ok(records)
+}
+
+pub struct Import {
+    /// One `create` per new node, parents before children.
+    pub steps: Vec<Step>,
+    /// `.jdmeta` entries for each step's folder, written once it exists.
+    pub entries: Vec<Vec<Entry>>,
+    /// Rows left out, and why.
+    pub skipped: Vec<String>,
+}
+
+/// Steps creating the rows whose codes `root` doesn't have yet. Rows whose
+/// code exists, repeats, or whose parent is neither in the tree nor in the
+/// file are skipped and reported.
+pub fn plan_import(root: &Node, rows: &[Row]) -> Import {
+    let existing: HashMap<String, &Node> = {
+        fn walk<'a>(n: &'a Node, out: &mut HashMap<String, &'a Node>) {
+            if let Some(c) = &n.code {
+                out.entry(c.clone()).or_insert(n);
+            }
+            n.children.iter().for_each(|c| walk(c, out));
+        }
+        let mut out = HashMap::new();
+        walk(root, &mut out);
+        out
+    };
+    let mut skipped = Vec::new();
+    let mut seen = HashSet::new();
+    let mut rows: Vec<&Row> = rows
+        .iter()
+        .filter(|r| {
+            if let Some(n) = existing.get(&r.code) {
+                let what = match n.title == r.title {
+                    true => "exists".to_string(),
+                    false => format!("exists as {} {}", r.code, n.title),
+                };
+                skipped.push(format!("{}: {}", r.label(), what));
+                return false;
+            }
+            if !seen.insert(r.code.clone()) {
+                skipped.push(format!("{}: {} is listed twice", r.label(), r.code));
+                return false;
+            }
+            true
+        })
+        .collect();
+    rows.sort_by_key(|r| depth(&r.code));
+
+    let ranges: Vec<String> = existing
+        .keys()
+        .chain(rows.iter().map(|r| &r.code))
+        .filter(|c| c.contains('-'))
+        .cloned()
+        .collect();
+    let mut known: HashSet<String> = existing.keys().cloned().collect();
+    let mut steps = Vec::new();
+    let mut entries = Vec::new();
+    for r in rows {
+        let shape = match depth(&r.code) {
+            0 => NodeType::Range,
+            1 => NodeType::Category,
+            _ => NodeType::ItemDir,
+        };
+        let node_type = r
+            .node_type
+            .clone()
+            .unwrap_or(match (&shape, r.ext.as_deref()) {
+                (NodeType::ItemDir, Some("webloc" | "url")) => NodeType::Link,
+                (NodeType::ItemDir, Some(_)) => NodeType::File,
+                _ => shape.clone(),
+            });
+        let fits = match shape {
+            NodeType::ItemDir => matches!(
+                node_type,
+                NodeType::ItemDir | NodeType::File | NodeType::Link
+            ),
+            _ => node_type == shape,
+        };
+        if !fits {
+            skipped.push(format!(
+                "{}: {:?} doesn't fit code {}",
+                r.label(),
+                node_type,
+                r.code
+            ));
+            continue;
+        }
+        let parent = match shape {
+            NodeType::Range => Some(root.path.clone()),
+            NodeType::Category => ranges
+                .iter()
+                .find(|range| covers(range, &r.code) && known.contains(*range))
+                .cloned(),
+            _ => {
+                Some(r.code[..r.code.rfind('.').unwrap()].to_string()).filter(|p| known.contains(p))
+            }
+        };
+        let Some(parent) = parent else {
+            skipped.push(format!("{}: no parent in the tree or the file", r.label()));
+            continue;
+        };
+        let (kind, input, url) = match node_type {
+            NodeType::File => {
+                let ext = r.ext.as_deref().filter(|e| !matches!(*e, "webloc" | "url"));
+                let input = match ext {
+                    Some(ext) => format!("{}.{}", r.label(), ext),
+                    None => r.label(),
+                };
+                (PlanKind::File, input, None)
+            }
+            NodeType::Link => {
+                let url = r
+                    .links
+                    .first()
+                    .and_then(|l| l.split_whitespace().next())
+                    .map(str::to_string);
+                if url.is_none() {
+                    skipped.push(format!("{}: link item without a URL", r.label()));
+                    continue;
+                }
+                (PlanKind::Link, r.label(), url)
+            }
+            _ => (PlanKind::Dir, r.label(), None),
+        };
+        let mut es = Vec::new();
+        if kind == PlanKind::Dir {
+            es.extend(r.locations.iter().cloned().map(Entry::Location));
+            es.extend(r.links.iter().filter_map(|l| Entry::from_input(l)));
+        } else if r.locations.len() + r.links.len() > usize::from(kind == PlanKind::Link) {
+            skipped.push(format!(
+                "{}: locations and links of file items aren't imported",
+                r.label()
+            ));
+        }
+        known.insert(r.code.clone());
+        steps.push(Step::Create {
+            parent,
+            input,
+            kind: Some(kind),
+            url,
+        });
+        entries.push(es);
+    }
+    Import {
+        steps,
+        entries,
+        skipped,
+    }
+}
+
+/// 0 for a range, 1 for a category, 2 and up for items and their sub-items.
+fn depth(code: &str) -> usize {
+    if code.contains('-') {
+        0
+    } else {
+        1 + code.matches('.').count()
+    }
+}
+
+fn covers(range: &str, category: &str) -> bool {
+    let num = |s: &str| s.parse::<u32>().ok();
+    match (range.split_once('-'), num(category)) {
+        (Some((a, b)), Some(c)) => num(a).is_some_and(|a| a <= c) && num(b).is_some_and(|b| c <= b),
+        _ => false,
+    }
+}
+
+/// Write the imported `.jdmeta` entries of the created folders.
+pub fn write_entries(ops: &[BatchOp], entries: &[Vec<Entry>]) -> Result<()> {
+    for (op, es) in ops.iter().zip(entries) {
+        if let BatchOp::Create(p) = op {
+            for e in es {
+                meta::add_entry(&p.dest_path, e)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{batch, export, fs_walk};
+    use std::fs;
+
+    #[test]
+    fn csv_round_trip_creates_missing_codes_only() {
+        let td = tempfile::tempdir().unwrap();
+        let src = [td.path().join("A")];
+        let cat = src[0].join("20-29_Admin/21_Taxes");
+        fs::create_dir_all(cat.join("21.01_Returns")).unwrap();
+        fs::write(
+            cat.join("21.01_Returns/.jdmeta"),
+            "LOCATION=drawer 2, left\nLINK=https://example.org/tax Portal\n",
+        )
+        .unwrap();
+        fs::write(cat.join("21.02_Receipts.pdf"), b"").unwrap();
+        fs::create_dir_all(src[0].join("20-29_Admin/22_Bills")).unwrap();
+        let tree = fs_walk::scan_roots(&src).unwrap();
+        let csv = export::export(
+            &[&tree.roots[0]],
+            export::Format::Csv,
+            &export::Options { paths: true },
+        );
+        assert!(csv.contains("21.01,Returns,ItemDir,"), "{}", csv);
+        assert!(
+            csv.contains(",\"drawer 2, left\",https://example.org/tax Portal\n"),
+            "{}",
+            csv
+        );
+
+        // the destination already has 21 under another title
+        let dest = [td.path().join("B")];
+        fs::create_dir_all(dest[0].join("20-29_Admin/21_Tax")).unwrap();
+        let tree = fs_walk::scan_roots(&dest).unwrap();
+        let (rows, bad) = read_csv(&csv).unwrap();
+        assert!(bad.is_empty(), "{:?}", bad);
+        let imp = plan_import(&tree.roots[0], &rows);
+        assert_eq!(
+            imp.skipped,
+            ["20-29 Admin: exists", "21 Taxes: exists as 21 Tax"]
+        );
+        let ops = batch::plan_batch(&tree, &imp.steps).unwrap();
+        let names: Vec<&str> = ops
+            .iter()
+            .map(|o| match o {
+                BatchOp::Create(p) => p.final_name.as_str(),
+                _ => unreachable!(),
+            })
+            .collect();
+        assert_eq!(names, ["22_Bills", "21.01_Returns", "21.02_Receipts.pdf"]);
+        batch::execute_batch(&dest, &ops).unwrap();
+        write_entries(&ops, &imp.entries).unwrap();
+        let meta =
+            fs::read_to_string(dest[0].join("20-29_Admin/21_Tax/21.01_Returns/.jdmeta")).unwrap();
+        assert_eq!(
+            meta,
+            "LOCATION=drawer 2, left\nLINK=https://example.org/tax Portal\n"
+        );
+    }
+
+    #[test]
+    fn outliner_opml_needs_only_text() {
+        let opml = r#"<?xml version="1.0"?>
+<opml version="2.0"><head><title>Plan</title></head><body>
+  <outline text="New area">
+    <outline text="40-49 Garden">
+      <outline text="41 Beds">
+        <outline text="41.01 Tomatoes"/>
+        <outline text="Ideas to sort"/>
+        <outline text="41.02 Seed shop" type="link" url="https://seeds.example"/>
+      </outline>
+    </outline>
+  </outline>
+</body></opml>"#;
+        let (rows, skipped) = read_opml(opml).unwrap();
+        assert_eq!(skipped, ["Ideas to sort: no code"]);
+        let codes: Vec<&str> = rows.iter().map(|r| r.code.as_str()).collect();
+        assert_eq!(codes, ["40-49", "41", "41.01", "41.02"]);
+        assert_eq!(rows[3].node_type, Some(NodeType::Link));
+        assert_eq!(rows[3].links, ["https://seeds.example"]);
+
+        let td = tempfile::tempdir().unwrap();
+        let roots = [td.path().join("R")];
+        fs::create_dir_all(&roots[0]).unwrap();
+        let tree = fs_walk::scan_roots(&roots).unwrap();
+        let imp = plan_import(&tree.roots[0], &rows);
+        let ops = batch::plan_batch(&tree, &imp.steps).unwrap();
+        batch::execute_batch(&roots, &ops).unwrap();
+        let beds = roots[0].join("40-49_Garden/41_Beds");
+        assert!(beds.join("41.01_Tomatoes").is_dir());
+        assert!(beds.join("41.02_Seed_shop.webloc").is_file());
+    }
+}
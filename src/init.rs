@@ -0,0 +1,47 @@
+//! `jd-helper init SHELL`: the `jd` function for a shell's rc file. It runs
+//! the TUI with `--format nul` and dispatches the action, so any path
+//! survives the trip; `jd CODE` resolves instead, and TAB completes codes.
+//! The roots passed to `init` are the defaults, `$JD_ROOTS` wins over them.
+
+use crate::complete::Shell;
+use std::path::PathBuf;
+
+const ZSH: &str = include_str!("init/jd.zsh");
+const BASH: &str = include_str!("init/jd.bash");
+const FISH: &str = include_str!("init/jd.fish");
+
+pub fn script(shell: Shell, roots: &[PathBuf]) -> String {
+    let template = match shell {
+        Shell::Zsh => ZSH,
+        Shell::Bash => BASH,
+        Shell::Fish => FISH,
+    };
+    let roots: Vec<String> = roots
+        .iter()
+        .map(|r| quote(shell, &r.to_string_lossy()))
+        .collect();
+    template.replace("@ROOTS@", &roots.join(" "))
+}
+
+/// A single-quoted word. fish takes `\'` inside quotes; the others close,
+/// escape and reopen.
+fn quote(shell: Shell, s: &str) -> String {
+    match shell {
+        Shell::Fish => format!("'{}'", s.replace('\\', "\\\\").replace('\'', "\\'")),
+        Shell::Zsh | Shell::Bash => format!("'{}'", s.replace('\'', "'\\''")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn roots_are_quoted_per_shell() {
+        let roots = [PathBuf::from("/x/it's here")];
+        assert!(script(Shell::Bash, &roots).contains("__jd_r=('/x/it'\\''s here')"));
+        assert!(script(Shell::Zsh, &roots).contains("reply=('/x/it'\\''s here')"));
+        assert!(script(Shell::Fish, &roots).contains("string join \\n -- '/x/it\\'s here'"));
+        assert!(!script(Shell::Zsh, &[]).contains("@ROOTS@"));
+    }
+}
@@ -0,0 +1,54 @@
+# jd: the jd-helper TUI, or `jd CODE` to cd straight to a code.
+# Printed by `jd-helper init bash`; roots: $JD_ROOTS, else the ones given there.
+
+__jd_roots() {
+  if [[ -n "${JD_ROOTS:-}" ]]; then
+    read -r -a __jd_r <<< "$JD_ROOTS"
+  else
+    __jd_r=(@ROOTS@)
+  fi
+  (( ${#__jd_r[@]} )) || { echo "jd: set JD_ROOTS or pass roots to 'jd-helper init bash'" >&2; return 1; }
+}
+
+jd() {
+  local -a __jd_r; __jd_roots || return
+  if [[ -n "${1:-}" ]]; then
+    local target rc
+    target=$(jd-helper resolve "$*" "${__jd_r[@]}"); rc=$?
+    if (( rc == 3 )); then
+      # several fit equally well: one "PATH<TAB>CODE Title" line each
+      local -a lines; mapfile -t lines <<< "$target"
+      if command -v fzf >/dev/null 2>&1; then
+        target=$(printf '%s\n' "${lines[@]}" | fzf --delimiter=$'\t' --with-nth=2 | cut -f1)
+      else
+        target=
+        select pick in "${lines[@]#*$'\t'}"; do
+          target=${lines[REPLY-1]%%$'\t'*}
+          break
+        done
+      fi
+    elif (( rc != 0 )); then
+      return 1
+    fi
+    [[ -z "$target" ]] && return 1
+    [[ -f "$target" ]] && target=$(dirname -- "$target")
+    builtin cd -- "$target"
+    return
+  fi
+  # ACTION\0TARGET\0: any byte but NUL survives in the target
+  local action target
+  { IFS= read -r -d '' action && IFS= read -r -d '' target; } < <(jd-helper ui "${__jd_r[@]}" --format nul)
+  [[ -n "$action" ]] || return 0
+  case $action in
+    cd)   builtin cd -- "$target" ;;
+    edit) "${EDITOR:-vim}" -- "$target" ;;
+    open) if command -v open >/dev/null 2>&1; then open "$target"; else xdg-open "$target"; fi ;;
+  esac
+}
+
+_jd() {
+  local -a __jd_r args; __jd_roots 2>/dev/null || return
+  local r; for r in "${__jd_r[@]}"; do args+=(--root "$r"); done
+  mapfile -t COMPREPLY < <(jd-helper complete --shell bash "${args[@]}" -- "${COMP_WORDS[COMP_CWORD]}" 2>/dev/null)
+}
+complete -F _jd jd
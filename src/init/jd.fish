@@ -0,0 +1,66 @@
+# jd: the jd-helper TUI, or `jd CODE` to cd straight to a code.
+# Printed by `jd-helper init fish`; roots: $JD_ROOTS, else the ones given there.
+
+function __jd_roots
+    if set -q JD_ROOTS; and test -n "$JD_ROOTS"
+        string split -n ' ' -- $JD_ROOTS
+    else
+        string join \n -- @ROOTS@
+    end
+end
+
+function jd
+    set -l roots (__jd_roots)
+    if test (count $roots) -eq 0
+        echo "jd: set JD_ROOTS or pass roots to 'jd-helper init fish'" >&2
+        return 1
+    end
+    if test (count $argv) -gt 0
+        set -l target (jd-helper resolve "$argv" $roots)
+        set -l rc $status
+        if test $rc -eq 3
+            # several fit equally well: one "PATH<TAB>CODE Title" line each
+            if command -q fzf
+                set target (printf '%s\n' $target | fzf --delimiter=\t --with-nth=2 | cut -f1)
+            else
+                for i in (seq (count $target))
+                    echo "$i) "(string split -m1 \t -- $target[$i])[2]
+                end
+                read -P '#? ' -l pick
+                string match -qr '^\d+$' -- $pick; or return 1
+                set target (string split -m1 \t -- $target[$pick])[1]
+            end
+        else if test $rc -ne 0
+            return 1
+        end
+        test -n "$target"; or return 1
+        test -f "$target"; and set target (dirname -- $target)
+        builtin cd -- $target
+        return
+    end
+    # ACTION\0TARGET\0: any byte but NUL survives in the target
+    set -l parts (jd-helper ui $roots --format nul | string split0)
+    test (count $parts) -eq 2; or return 0
+    switch $parts[1]
+        case cd
+            builtin cd -- $parts[2]
+        case edit
+            set -q EDITOR; or set -l EDITOR vim
+            $EDITOR -- $parts[2]
+        case open
+            if command -q open
+                open $parts[2]
+            else
+                xdg-open $parts[2]
+            end
+    end
+end
+
+function __jd_complete
+    set -l args
+    for r in (__jd_roots)
+        set -a args --root $r
+    end
+    jd-helper complete --shell fish $args -- (commandline -ct) 2>/dev/null
+end
+complete -c jd -f -a "(__jd_complete)"
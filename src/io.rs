@@ -1,8 +1,10 @@
 use crate::model::Tree;
+use crate::schema::{self, Index};
+use crate::{config, date, snapshot};
 use anyhow::Result;
 use std::fs;
 use std::io::Write;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 #[derive(Default)]
 pub struct IndexIo;
@@ -30,15 +32,40 @@ impl IndexIo {
         let tmp = out_path.with_extension("tmp");
         {
             let mut f = fs::File::create(&tmp)?;
-            let data = serde_json::to_vec_pretty(tree)?;
+            let data = serde_json::to_vec_pretty(&Index::new(tree.clone()))?;
             f.write_all(&data)?;
             f.sync_all()?;
         }
         fs::rename(&tmp, out_path)?;
+        snapshot::rotate(out_path, &config::get().snapshots, date::now())?;
         Ok(out_path.clone())
     }
 }
 
+/// Where `write_index` keeps the index for these roots.
+pub fn index_path(roots: &[PathBuf]) -> PathBuf {
+    match roots {
+        [root] => root.join(".jd_index.json"),
+        _ => default_index_path(),
+    }
+}
+
+/// The last index written for exactly these roots, if it can be read
+/// (older schema versions are migrated). Paths are compared as given first
+/// so a slow root isn't touched.
+pub fn read_cached(roots: &[PathBuf]) -> Option<Tree> {
+    let tree = schema::read(&index_path(roots)).ok()?.tree;
+    same_roots(&tree, roots).then_some(tree)
+}
+
+/// `tree` is a scan of exactly `roots`, in that order.
+pub fn same_roots(tree: &Tree, roots: &[PathBuf]) -> bool {
+    tree.roots.len() == roots.len()
+        && tree.roots.iter().zip(roots).all(|(n, r)| {
+            Path::new(&n.path) == r || r.canonicalize().is_ok_and(|c| Path::new(&n.path) == c)
+        })
+}
+
 pub fn default_index_path() -> PathBuf {
     let cfg = home::home_dir().unwrap_or_else(|| PathBuf::from("."));
     cfg.join(".config").join("jd").join(".jd_index.json")
@@ -1,6 +1,17 @@
+pub mod batch;
+pub mod complete;
+pub mod config;
+pub mod date;
+pub mod diff;
+pub mod export;
 pub mod fs_walk;
+pub mod highlight;
+pub mod hooks;
 pub mod ignore;
+pub mod import;
+pub mod init;
 pub mod io;
+pub mod lock;
 pub mod meta;
 pub mod md;
 pub mod model;
@@ -8,6 +19,11 @@ pub mod mutate;
 pub mod plan;
 pub mod preview;
 pub mod resolve;
+pub mod schema;
+pub mod skeleton;
+pub mod snapshot;
 pub mod state;
+pub mod template;
+pub mod trash;
 pub mod tsv;
 pub mod ui;
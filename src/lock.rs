@@ -0,0 +1,141 @@
+//! Advisory per-root locks around mutations. Each root gets a `.jd_lock`
+//! file; a mutation holds an exclusive `flock` on it (for every root it was
+//! given, in sorted order) from revalidating its plan until the index is
+//! rewritten. Two TUI sessions, or a TUI and a script, then take turns
+//! instead of both writing the same "next free" code.
+//!
+//! Locks are reentrant within a thread, so a locked operation may call
+//! others that lock the same roots.
+
+use anyhow::{bail, Context, Result};
+use std::cell::RefCell;
+use std::fs::{self, File, OpenOptions, TryLockError};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+pub const LOCK_FILE: &str = ".jd_lock";
+
+/// How long to wait for another process before giving up.
+pub const WAIT: Duration = Duration::from_secs(10);
+
+thread_local! {
+    static HELD: RefCell<Vec<PathBuf>> = const { RefCell::new(Vec::new()) };
+}
+
+/// Held locks; released on drop.
+pub struct RootLock {
+    held: Vec<(PathBuf, File)>,
+}
+
+impl Drop for RootLock {
+    fn drop(&mut self) {
+        HELD.with(|h| {
+            h.borrow_mut()
+                .retain(|r| !self.held.iter().any(|(p, _)| p == r))
+        });
+        for (_, f) in &self.held {
+            let _ = f.unlock();
+        }
+    }
+}
+
+/// Lock every root, waiting up to `WAIT` for other processes.
+pub fn lock_roots(roots: &[PathBuf]) -> Result<RootLock> {
+    lock_roots_within(roots, WAIT)
+}
+
+pub fn lock_roots_within(roots: &[PathBuf], wait: Duration) -> Result<RootLock> {
+    let mut sorted: Vec<&PathBuf> = roots.iter().collect();
+    sorted.sort();
+    sorted.dedup();
+    let mut lock = RootLock { held: Vec::new() };
+    for root in sorted {
+        if HELD.with(|h| h.borrow().contains(root)) {
+            continue;
+        }
+        let f = acquire(root, wait)?;
+        HELD.with(|h| h.borrow_mut().push(root.clone()));
+        lock.held.push((root.clone(), f));
+    }
+    Ok(lock)
+}
+
+fn acquire(root: &Path, wait: Duration) -> Result<File> {
+    let path = root.join(LOCK_FILE);
+    let mut f = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .create(true)
+        .truncate(false)
+        .open(&path)
+        .with_context(|| format!("cannot open lock file {}", path.display()))?;
+    let start = Instant::now();
+    loop {
+        match f.try_lock() {
+            Ok(()) => break,
+            Err(TryLockError::WouldBlock) if start.elapsed() < wait => {
+                std::thread::sleep(Duration::from_millis(50));
+            }
+            Err(TryLockError::WouldBlock) => {
+                let holder = fs::read_to_string(&path).unwrap_or_default();
+                let holder = holder.trim();
+                bail!(
+                    "{} is being changed by another jd-helper{}; try again",
+                    root.display(),
+                    if holder.is_empty() {
+                        String::new()
+                    } else {
+                        format!(" (pid {})", holder)
+                    }
+                );
+            }
+            Err(TryLockError::Error(e)) => {
+                return Err(e).with_context(|| format!("cannot lock {}", path.display()))
+            }
+        }
+    }
+    // Who holds it, for the message above; best effort.
+    let _ = f
+        .set_len(0)
+        .and_then(|_| writeln!(f, "{}", std::process::id()));
+    Ok(f)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn second_holder_waits_then_fails_and_same_thread_reenters() {
+        let td = tempfile::tempdir().unwrap();
+        let roots = vec![td.path().join("A"), td.path().join("B")];
+        for r in &roots {
+            fs::create_dir(r).unwrap();
+        }
+        let outer = lock_roots(&roots).unwrap();
+        // nested locking in the same thread doesn't deadlock
+        drop(lock_roots(&roots[..1]).unwrap());
+
+        let other = roots.clone();
+        let blocked = std::thread::spawn(move || {
+            lock_roots_within(&other, Duration::from_millis(100)).map(|_| ())
+        })
+        .join()
+        .unwrap();
+        let msg = blocked.unwrap_err().to_string();
+        assert!(msg.contains("another jd-helper"), "{}", msg);
+        assert!(
+            msg.contains(&format!("pid {}", std::process::id())),
+            "{}",
+            msg
+        );
+
+        drop(outer);
+        let other = roots.clone();
+        std::thread::spawn(move || lock_roots_within(&other, Duration::ZERO).map(|_| ()))
+            .join()
+            .unwrap()
+            .unwrap();
+    }
+}
@@ -2,7 +2,10 @@ use anyhow::Result;
 use clap::{Args, Parser, Subcommand, ValueEnum};
 use std::path::PathBuf;
 
-use jd_helper::{fs_walk, io, meta, model, mutate, preview, resolve, state, tsv, ui};
+use jd_helper::{
+    batch, complete, export, fs_walk, import, io, meta, model, mutate, preview, resolve, skeleton,
+    state, tsv, ui,
+};
 
 #[derive(Parser, Debug)]
 #[command(name = "jd-helper", version, about = "Filesystem-first JD helper")]
@@ -17,6 +20,8 @@ enum Commands {
     Tree(TreeCmd),
     Preview(PreviewCmd),
     Resolve(ResolveCmd),
+    /// Resolve a code and do what Enter in the TUI would (print the action, or --exec it)
+    Open(OpenCmd),
     Parent(ParentCmd),
     Codes(CodesCmd),
     New(NewCmd),
@@ -36,8 +41,305 @@ enum Commands {
     Duplicates(DuplicatesCmd),
     /// Give a node the next free code under its parent (children recoded)
     Renumber(RenumberCmd),
+    /// Give a node a chosen code, recoding everything inside
+    Recode(RecodeCmd),
     /// Merge a duplicate into the folder with the same number
     Merge(MergeCmd),
+    /// Apply a JSON list of operations as one transaction
+    Apply(ApplyCmd),
+    /// List, restore or purge soft-deleted entries
+    Trash(TrashCmd),
+    /// File outside files or folders into a category or item
+    File(FileCmd),
+    /// List names that break the configured name policy
+    CheckNames(CheckNamesCmd),
+    /// Turn a file item into a folder item with the file inside
+    Wrap(WrapCmd),
+    /// Turn a folder item holding a single file back into a file item
+    Unwrap(WrapCmd),
+    /// Move a finished node into the archive under a new code, noting the old one
+    Archive(ArchiveCmd),
+    /// Move an archived node back to its former code
+    Unarchive(WrapCmd),
+    /// Print the JSON Schema of .jd_index.json and scan output
+    Schema,
+    /// Check an index file against the schema and the filesystem
+    Index(IndexCmd),
+    /// Print the index as a Markdown or HTML outline, or a CSV or OPML skeleton
+    Export(ExportCmd),
+    /// Create the ranges, categories and items of a CSV or OPML file a root lacks
+    Import(ImportCmd),
+    /// Dump a root's structure as a D/F manifest, or recreate one
+    Skeleton(SkeletonCmd),
+    /// What changed between an index snapshot and another, or a fresh scan
+    Diff(DiffCmd),
+    /// What changed since a dated snapshot of the index (see "snapshots" in the config)
+    Changes(ChangesCmd),
+    /// Print a completion script for bash, zsh or fish
+    Completions(CompletionsCmd),
+    /// Codes (or ids) starting with PREFIX, for completion scripts
+    Complete(CompleteCmd),
+    /// Print the `jd` shell function for zsh, bash or fish
+    Init(InitCmd),
+}
+
+#[derive(Args, Debug)]
+struct InitCmd {
+    #[arg(value_enum)]
+    shell: Shell,
+    #[arg(help = "Roots jd uses when $JD_ROOTS is unset")]
+    roots: Vec<PathBuf>,
+}
+
+#[derive(Args, Debug)]
+struct CompletionsCmd {
+    #[arg(value_enum)]
+    shell: Shell,
+}
+
+#[derive(Copy, Clone, Eq, PartialEq, Debug, ValueEnum)]
+enum Shell {
+    Bash,
+    Zsh,
+    Fish,
+}
+
+impl From<Shell> for complete::Shell {
+    fn from(shell: Shell) -> Self {
+        match shell {
+            Shell::Bash => complete::Shell::Bash,
+            Shell::Zsh => complete::Shell::Zsh,
+            Shell::Fish => complete::Shell::Fish,
+        }
+    }
+}
+
+#[derive(Args, Debug)]
+struct CompleteCmd {
+    #[arg(long, value_enum)]
+    shell: Shell,
+    #[arg(
+        long,
+        help = "Complete node ids (matched on id, code or title) instead of codes"
+    )]
+    ids: bool,
+    #[arg(default_value = "")]
+    prefix: String,
+    #[arg(
+        long = "root",
+        value_name = "ROOT",
+        help = "Default: $JD_ROOTS, else the combined index"
+    )]
+    roots: Vec<PathBuf>,
+}
+
+#[derive(Args, Debug)]
+struct ChangesCmd {
+    #[arg(
+        long,
+        value_name = "AGE",
+        default_value = "7d",
+        help = "How far back, e.g. 12h, 7d, 2w"
+    )]
+    since: String,
+    #[arg(long, help = "Print the snapshot used and the changes as JSON")]
+    json: bool,
+    #[arg(required = true)]
+    roots: Vec<PathBuf>,
+}
+
+#[derive(Args, Debug)]
+struct DiffCmd {
+    #[arg(help = "Older index or scan output")]
+    old: PathBuf,
+    #[arg(help = "Newer index, or roots to scan (default: the old index's roots)")]
+    new: Vec<PathBuf>,
+    #[arg(long, help = "Print the changes as a JSON array")]
+    json: bool,
+}
+
+#[derive(Args, Debug)]
+struct SkeletonCmd {
+    #[command(subcommand)]
+    action: SkeletonAction,
+}
+
+#[derive(Subcommand, Debug)]
+enum SkeletonAction {
+    /// Print ranges, categories, items and .jdmeta files as D/F lines
+    Dump {
+        root: PathBuf,
+        #[arg(
+            long,
+            help = "Include the text of .jdmeta files, pointer files and links"
+        )]
+        contents: bool,
+    },
+    /// Create a manifest's folders and files under DEST, skipping what exists
+    Apply {
+        #[arg(help = "Manifest file ('-' reads stdin)")]
+        manifest: PathBuf,
+        dest: PathBuf,
+        #[arg(long, help = "Print the entries without touching disk")]
+        dry_run: bool,
+    },
+}
+
+#[derive(Args, Debug)]
+struct ImportCmd {
+    #[arg(help = "CSV or OPML file, as 'export --format csv|opml' writes")]
+    file: PathBuf,
+    #[arg(long, value_name = "ROOT")]
+    root: PathBuf,
+    #[arg(long, value_enum, help = "Default: from the file extension")]
+    format: Option<ImportFormat>,
+    #[arg(long, help = "Print the plan without touching disk")]
+    dry_run: bool,
+}
+
+#[derive(Copy, Clone, Eq, PartialEq, Debug, ValueEnum)]
+enum ImportFormat {
+    Csv,
+    Opml,
+}
+
+#[derive(Copy, Clone, Eq, PartialEq, Debug, ValueEnum)]
+enum ExportFormat {
+    Markdown,
+    Html,
+    Csv,
+    Opml,
+}
+
+#[derive(Args, Debug)]
+struct ExportCmd {
+    #[arg(long, value_enum, default_value = "markdown")]
+    format: ExportFormat,
+    #[arg(
+        long,
+        value_name = "CODE",
+        help = "Only this range, category or item (also an id or path)"
+    )]
+    under: Option<String>,
+    #[arg(long, help = "Leave out filesystem paths")]
+    no_paths: bool,
+    #[arg(required = true)]
+    roots: Vec<PathBuf>,
+}
+
+#[derive(Args, Debug)]
+struct IndexCmd {
+    #[command(subcommand)]
+    action: IndexAction,
+}
+
+#[derive(Subcommand, Debug)]
+enum IndexAction {
+    /// Report schema problems and whether the index is stale
+    Verify {
+        #[arg(
+            long,
+            help = "Index file; default: where write-index puts it for ROOTS"
+        )]
+        file: Option<PathBuf>,
+        #[arg(required = true)]
+        roots: Vec<PathBuf>,
+    },
+}
+
+#[derive(Args, Debug)]
+struct ArchiveCmd {
+    #[arg(long)]
+    id: String,
+    #[arg(
+        long,
+        value_name = "CODE",
+        help = "Archive category (or range); default: \"archive\" in config.json"
+    )]
+    to: Option<String>,
+    #[arg(long, help = "Print what would change without touching disk")]
+    dry_run: bool,
+    #[arg(required = true)]
+    roots: Vec<PathBuf>,
+}
+
+#[derive(Args, Debug)]
+struct WrapCmd {
+    #[arg(long)]
+    id: String,
+    #[arg(long, help = "Print what would change without touching disk")]
+    dry_run: bool,
+    #[arg(required = true)]
+    roots: Vec<PathBuf>,
+}
+
+#[derive(Args, Debug)]
+struct CheckNamesCmd {
+    #[arg(required = true)]
+    roots: Vec<PathBuf>,
+}
+
+#[derive(Args, Debug)]
+struct FileCmd {
+    #[arg(required = true, help = "Files or folders to file")]
+    paths: Vec<PathBuf>,
+    #[arg(
+        long,
+        value_name = "CODE",
+        help = "Category (next free codes) or item folder (stamped with its code); also an id or path"
+    )]
+    into: String,
+    #[arg(long, help = "Copy instead of moving")]
+    copy: bool,
+    #[arg(long, help = "Print the final names without touching disk")]
+    dry_run: bool,
+    #[arg(long = "root", value_name = "ROOT", required = true)]
+    roots: Vec<PathBuf>,
+}
+
+#[derive(Args, Debug)]
+struct TrashCmd {
+    #[command(subcommand)]
+    action: TrashAction,
+}
+
+#[derive(Subcommand, Debug)]
+enum TrashAction {
+    /// Print name, code, deletion time and original path per entry
+    List {
+        #[arg(required = true)]
+        roots: Vec<PathBuf>,
+    },
+    /// Move an entry back to where it was deleted from
+    Restore {
+        #[arg(long, help = "Entry name as printed by 'trash list'")]
+        name: String,
+        #[arg(required = true)]
+        roots: Vec<PathBuf>,
+    },
+    /// Delete entries for good
+    Purge {
+        #[arg(
+            long,
+            value_name = "AGE",
+            help = "Only entries deleted at least this long ago (30d, 12h, 2w)"
+        )]
+        older_than: Option<String>,
+        #[arg(long, conflicts_with = "older_than", help = "Everything in the trash")]
+        all: bool,
+        #[arg(required = true)]
+        roots: Vec<PathBuf>,
+    },
+}
+
+#[derive(Args, Debug)]
+struct ApplyCmd {
+    #[arg(help = "Plan file ('-' reads stdin)")]
+    plan: PathBuf,
+    #[arg(long, help = "Validate and print the steps without touching disk")]
+    dry_run: bool,
+    #[arg(required = true)]
+    roots: Vec<PathBuf>,
 }
 
 #[derive(Args, Debug)]
@@ -64,6 +366,27 @@ struct RenumberCmd {
     roots: Vec<PathBuf>,
 }
 
+#[derive(Args, Debug)]
+struct RecodeCmd {
+    #[arg(long)]
+    id: String,
+    #[arg(
+        long,
+        value_name = "NEW_CODE",
+        help = "New code (21.10 for an item, 43 for a category, 50-59 for a range)"
+    )]
+    code: String,
+    #[arg(long, help = "Print every rename without touching disk")]
+    dry_run: bool,
+    #[arg(
+        long,
+        help = "Also rewrite codes in .jdmeta.md notes (prose, where dates and prices look like codes)"
+    )]
+    rewrite_notes: bool,
+    #[arg(required = true)]
+    roots: Vec<PathBuf>,
+}
+
 #[derive(Args, Debug)]
 struct MetaCmd {
     #[command(subcommand)]
@@ -104,6 +427,30 @@ struct UiCmd {
     roots: Vec<PathBuf>,
     #[arg(long)]
     state: Option<PathBuf>,
+    #[arg(
+        long,
+        value_enum,
+        default_value = "line",
+        help = "How to print the action: ACTION<TAB>TARGET, NUL-terminated fields, or JSON"
+    )]
+    format: ActionFormat,
+}
+
+#[derive(Copy, Clone, Eq, PartialEq, Debug, ValueEnum)]
+enum ActionFormat {
+    Line,
+    Nul,
+    Json,
+}
+
+impl From<ActionFormat> for ui::actions::Format {
+    fn from(format: ActionFormat) -> Self {
+        match format {
+            ActionFormat::Line => ui::actions::Format::Line,
+            ActionFormat::Nul => ui::actions::Format::Nul,
+            ActionFormat::Json => ui::actions::Format::Json,
+        }
+    }
 }
 
 #[derive(Args, Debug)]
@@ -116,9 +463,12 @@ struct ScanCmd {
 struct TreeCmd {
     #[arg(required = true)]
     roots: Vec<PathBuf>,
-    #[arg(long)]
+    #[arg(long, help = "Like --search, but within the fold state")]
     filter: Option<String>,
-    #[arg(long, help = "Full-tree fuzzy search; ignores fold state while active")]
+    #[arg(
+        long,
+        help = "Full-tree fuzzy search, as the TUI's; ignores fold state while active"
+    )]
     search: Option<String>,
     #[arg(long)]
     state: Option<PathBuf>,
@@ -126,6 +476,17 @@ struct TreeCmd {
     all: bool,
     #[arg(long, help = "Do not auto-expand root level")]
     collapse_root: bool,
+    #[arg(long, help = "Order matches by score, best first (no context rows)")]
+    rank: bool,
+    #[arg(long, value_enum, default_value = "tsv")]
+    format: TreeFormat,
+}
+
+#[derive(Copy, Clone, Eq, PartialEq, Debug, ValueEnum)]
+enum TreeFormat {
+    Tsv,
+    Json,
+    Ndjson,
 }
 
 #[derive(Copy, Clone, Eq, PartialEq, Debug, ValueEnum)]
@@ -145,11 +506,37 @@ struct PreviewCmd {
 
 #[derive(Args, Debug)]
 struct ResolveCmd {
+    #[arg(help = "A code, a short one (21.4), the start of one, a title fragment or an alias")]
     code: String,
+    #[arg(long, value_enum, default_value = "path")]
+    format: ResolveFormat,
     #[arg(required = true)]
     roots: Vec<PathBuf>,
 }
 
+#[derive(Args, Debug)]
+struct OpenCmd {
+    #[arg(help = "Anything resolve takes: a code, short code, title fragment or alias")]
+    code: String,
+    #[arg(
+        long,
+        help = "Run it: the editor for text files on a terminal, the opener for the rest"
+    )]
+    exec: bool,
+    #[arg(long, value_enum, default_value = "line", conflicts_with = "exec")]
+    format: ActionFormat,
+    #[arg(required = true)]
+    roots: Vec<PathBuf>,
+}
+
+#[derive(Copy, Clone, Eq, PartialEq, Debug, ValueEnum)]
+enum ResolveFormat {
+    Path,
+    Json,
+    Id,
+    Url,
+}
+
 #[derive(Args, Debug)]
 struct ParentCmd {
     id: String,
@@ -186,6 +573,12 @@ struct NewCmd {
     url: Option<String>,
     #[arg(long)]
     location: Option<String>,
+    #[arg(
+        long,
+        value_name = "NAME",
+        help = "Fill a new dir from .jdtemplates/NAME ('none' skips the default item/category/range template)"
+    )]
+    template: Option<String>,
     #[arg(required = true)]
     roots: Vec<PathBuf>,
 }
@@ -232,7 +625,7 @@ struct DeleteCmd {
 
 #[derive(Args, Debug)]
 struct SuggestCmd {
-    #[arg(long)]
+    #[arg(long, value_name = "CODE")]
     parent: String,
     #[arg(required = true)]
     roots: Vec<PathBuf>,
@@ -273,7 +666,8 @@ fn main() -> Result<()> {
     match cli.command {
         Commands::Scan(cmd) => {
             let tree = fs_walk::scan_roots(&cmd.roots)?;
-            println!("{}", serde_json::to_string_pretty(&tree)?);
+            let index = jd_helper::schema::Index::new(tree);
+            println!("{}", serde_json::to_string_pretty(&index)?);
         }
         Commands::Tree(cmd) => {
             let tree = fs_walk::scan_roots(&cmd.roots)?;
@@ -281,15 +675,26 @@ fn main() -> Result<()> {
             let expanded = state::load_state_or_default(state_path.as_ref())?;
             // When --search is provided, traverse all nodes and apply fuzzy to entire tree;
             // treat empty search string as no search
-            let search_opt =
-                cmd.search
-                    .as_ref()
-                    .and_then(|s| if s.is_empty() { None } else { Some(s.as_str()) });
-            let query = search_opt.or(cmd.filter.as_deref());
-            let show_all = cmd.all || search_opt.is_some();
-            let lines = tsv::flatten_to_tsv(&tree, query, &expanded, show_all, cmd.collapse_root);
-            for l in lines {
-                println!("{}", l);
+            let search_opt = cmd.search.as_deref().filter(|s| !s.is_empty());
+            let opts = tsv::Options {
+                query: search_opt.or(cmd.filter.as_deref()),
+                show_all: cmd.all || search_opt.is_some(),
+                collapse_root: cmd.collapse_root,
+                rank: cmd.rank,
+            };
+            let lines = tsv::list(&tree, &expanded, &opts);
+            match cmd.format {
+                TreeFormat::Tsv => {
+                    for l in &lines {
+                        println!("{}", tsv::tsv_line(l));
+                    }
+                }
+                TreeFormat::Json => println!("{}", serde_json::to_string_pretty(&lines)?),
+                TreeFormat::Ndjson => {
+                    for l in &lines {
+                        println!("{}", serde_json::to_string(l)?);
+                    }
+                }
             }
         }
         Commands::Preview(cmd) => {
@@ -302,8 +707,62 @@ fn main() -> Result<()> {
         }
         Commands::Resolve(cmd) => {
             let tree = fs_walk::scan_roots(&cmd.roots)?;
-            let p = resolve::resolve_code_to_path(&tree, &cmd.code)?;
-            println!("{}", p.display());
+            let (found, ambiguous) = candidates(&tree, &cmd.code)?;
+            // ambiguous: every candidate, best first, and exit 3
+            let shown = if ambiguous { &found[..] } else { &found[..1] };
+            let value = |c: &resolve::Candidate| match cmd.format {
+                ResolveFormat::Path | ResolveFormat::Json => c.node.path.clone(),
+                ResolveFormat::Id => c.node.id.clone(),
+                ResolveFormat::Url => resolve::url(c.node),
+            };
+            if cmd.format == ResolveFormat::Json {
+                let out: Vec<serde_json::Value> = shown.iter().map(candidate_json).collect();
+                println!("{}", serde_json::to_string_pretty(&out)?);
+            } else if ambiguous {
+                for c in shown {
+                    println!("{}\t{}", value(c), c.display());
+                }
+            } else {
+                println!("{}", value(&found[0]));
+            }
+            if ambiguous {
+                std::process::exit(3);
+            }
+        }
+        Commands::Open(cmd) => {
+            let tree = fs_walk::scan_roots(&cmd.roots)?;
+            let (found, ambiguous) = candidates(&tree, &cmd.code)?;
+            if ambiguous {
+                // as resolve does, in the asked-for format, then exit 3
+                match cmd.format {
+                    ActionFormat::Json => {
+                        let out: Vec<serde_json::Value> =
+                            found.iter().map(candidate_json).collect();
+                        println!("{}", serde_json::to_string_pretty(&out)?);
+                    }
+                    ActionFormat::Nul => {
+                        use std::io::Write;
+                        let mut out = std::io::stdout().lock();
+                        for c in &found {
+                            write!(out, "{}\0{}\0", c.node.path, c.display())?;
+                        }
+                    }
+                    ActionFormat::Line => {
+                        for c in &found {
+                            println!("{}\t{}", c.node.path, c.display());
+                        }
+                    }
+                }
+                std::process::exit(3);
+            }
+            let n = found[0].node;
+            let action = ui::FinalAction::for_node(&n.node_type, &n.path, n.url.as_deref());
+            if cmd.exec {
+                ui::execute(&action)?;
+            } else {
+                use std::io::Write;
+                std::io::stdout().write_all(&action.encode(cmd.format.into())?)?;
+            }
         }
         Commands::Parent(cmd) => {
             let tree = fs_walk::scan_roots(&cmd.roots)?;
@@ -360,6 +819,7 @@ fn main() -> Result<()> {
                 &cmd.name,
                 cmd.url.as_deref(),
                 cmd.location.as_deref(),
+                cmd.template.as_deref(),
             )?;
         }
         Commands::NewInteractive(cmd) => {
@@ -374,7 +834,25 @@ fn main() -> Result<()> {
             mutate::rename(&cmd.roots, &cmd.id, &cmd.name)?;
         }
         Commands::Move(cmd) => {
-            mutate::move_node(&cmd.roots, &cmd.id, &cmd.parent)?;
+            let tree = fs_walk::scan_roots(&cmd.roots)?;
+            let plan = jd_helper::plan::plan_move(&tree, &cmd.id, &cmd.parent)?;
+            // Only cross-device moves copy; report once there is enough to wait for.
+            let mut shown = false;
+            mutate::execute_move(&cmd.roots, &plan, &mut |p| {
+                if p.total_files >= 200 || p.total_bytes >= 64 << 20 {
+                    eprint!(
+                        "\rcopying {}/{} files, {}/{} MiB",
+                        p.files,
+                        p.total_files,
+                        p.bytes >> 20,
+                        p.total_bytes >> 20
+                    );
+                    shown = true;
+                }
+            })?;
+            if shown {
+                eprintln!();
+            }
         }
         Commands::Delete(cmd) => {
             mutate::delete_node(&cmd.roots, &cmd.id)?;
@@ -391,7 +869,7 @@ fn main() -> Result<()> {
         }
         Commands::WriteIndex(cmd) => {
             let tree = fs_walk::scan_roots(&cmd.roots)?;
-            let out = io::IndexIo::default().write_index(cmd.out.as_ref(), &tree)?;
+            let out = io::IndexIo.write_index(cmd.out.as_ref(), &tree)?;
             println!("{}", out.display());
         }
         Commands::ResetState(cmd) => {
@@ -428,7 +906,8 @@ fn main() -> Result<()> {
         Commands::Ui(cmd) => {
             let state = cmd.state.unwrap_or_else(state::default_state_path);
             if let Some(action) = ui::run(&cmd.roots, &state)? {
-                println!("{}", action);
+                use std::io::Write;
+                std::io::stdout().write_all(&action.encode(cmd.format.into())?)?;
             }
         }
         Commands::Duplicates(cmd) => {
@@ -452,12 +931,341 @@ fn main() -> Result<()> {
             let dest = mutate::execute_renumber(&cmd.roots, &plan)?;
             println!("{}\t{}\t{}", plan.old_code, plan.new_code, dest.display());
         }
+        Commands::Recode(cmd) => {
+            let tree = fs_walk::scan_roots(&cmd.roots)?;
+            let n = model::find_node(&tree, &cmd.id).ok_or_else(|| anyhow::anyhow!("not found"))?;
+            if matches!(
+                n.node_type,
+                model::NodeType::Range | model::NodeType::Category
+            ) {
+                let plan =
+                    jd_helper::plan::plan_recode(&tree, &cmd.id, &cmd.code, cmd.rewrite_notes)?;
+                println!("{}", jd_helper::plan::recode_summary(&plan));
+                if !cmd.dry_run {
+                    mutate::execute_recode(&cmd.roots, &plan)?;
+                }
+            } else {
+                let plan = jd_helper::plan::plan_recode_item(&tree, &cmd.id, &cmd.code)?;
+                println!("{}", jd_helper::plan::renumber_summary(&plan));
+                if !cmd.dry_run {
+                    mutate::execute_renumber(&cmd.roots, &plan)?;
+                }
+            }
+        }
+        Commands::File(cmd) => {
+            let tree = fs_walk::scan_roots(&cmd.roots)?;
+            let into = batch::resolve_ref(&tree, &cmd.into)?;
+            let mut plans = jd_helper::plan::plan_file_in(&tree, &cmd.paths, &into.id)?;
+            if !cmd.dry_run {
+                plans = mutate::execute_file(&cmd.roots, &plans, cmd.copy)?;
+            }
+            for f in &plans {
+                println!("{}\t{}", f.src.display(), f.dest_path.display());
+            }
+        }
+        Commands::Wrap(cmd) => wrap(&cmd, false)?,
+        Commands::Unwrap(cmd) => wrap(&cmd, true)?,
+        Commands::Archive(cmd) => {
+            let Some(to) = cmd
+                .to
+                .clone()
+                .or_else(|| jd_helper::config::get().archive.clone())
+            else {
+                anyhow::bail!(
+                    "no archive configured: pass --to or set \"archive\" in {}",
+                    jd_helper::config::config_path().display()
+                );
+            };
+            let tree = fs_walk::scan_roots(&cmd.roots)?;
+            let plan = jd_helper::plan::plan_archive(&tree, &cmd.id, &to)?;
+            println!("{}", jd_helper::plan::archive_summary(&plan));
+            if !cmd.dry_run {
+                let dest = mutate::execute_archive(&cmd.roots, &plan)?;
+                println!("{}", dest.display());
+            }
+        }
+        Commands::Unarchive(cmd) => {
+            let tree = fs_walk::scan_roots(&cmd.roots)?;
+            let plan = jd_helper::plan::plan_unarchive(&tree, &cmd.id)?;
+            println!("{}", jd_helper::plan::archive_summary(&plan));
+            if !cmd.dry_run {
+                let dest = mutate::execute_archive(&cmd.roots, &plan)?;
+                println!("{}", dest.display());
+            }
+        }
+        Commands::Schema => {
+            let schema = jd_helper::schema::json_schema();
+            println!("{}", serde_json::to_string_pretty(&schema)?);
+        }
+        Commands::Index(IndexCmd {
+            action: IndexAction::Verify { file, roots },
+        }) => {
+            let file = file.unwrap_or_else(|| io::index_path(&roots));
+            let v = jd_helper::schema::verify(&file, &roots)?;
+            for p in &v.problems {
+                println!("{}\t{}", file.display(), p);
+            }
+            if !v.problems.is_empty() {
+                anyhow::bail!("{} problem(s) with {}", v.problems.len(), file.display());
+            }
+            println!(
+                "{}\tschema {}, generated {}, current",
+                file.display(),
+                v.schema_version,
+                v.generated_at.as_deref().unwrap_or("?")
+            );
+        }
+        Commands::Export(cmd) => {
+            let tree = fs_walk::scan_roots(&cmd.roots)?;
+            let nodes: Vec<&model::Node> = match &cmd.under {
+                Some(r) => vec![batch::resolve_ref(&tree, r)?],
+                None => tree.roots.iter().collect(),
+            };
+            let format = match cmd.format {
+                ExportFormat::Markdown => export::Format::Markdown,
+                ExportFormat::Html => export::Format::Html,
+                ExportFormat::Csv => export::Format::Csv,
+                ExportFormat::Opml => export::Format::Opml,
+            };
+            let opts = export::Options {
+                paths: !cmd.no_paths,
+            };
+            print!("{}", export::export(&nodes, format, &opts));
+        }
+        Commands::Import(cmd) => {
+            let text = std::fs::read_to_string(&cmd.file)?;
+            let ext = cmd.file.extension().and_then(|e| e.to_str());
+            let format = match (cmd.format, ext) {
+                (Some(f), _) => f,
+                (None, Some("opml" | "xml")) => ImportFormat::Opml,
+                (None, Some("csv")) => ImportFormat::Csv,
+                _ => anyhow::bail!("pass --format csv|opml for {}", cmd.file.display()),
+            };
+            let (rows, mut skipped) = match format {
+                ImportFormat::Csv => import::read_csv(&text)?,
+                ImportFormat::Opml => import::read_opml(&text)?,
+            };
+            let roots = [cmd.root.clone()];
+            let _lock = jd_helper::lock::lock_roots(&roots)?;
+            let tree = fs_walk::scan_roots(&roots)?;
+            let imp = import::plan_import(&tree.roots[0], &rows);
+            skipped.extend(imp.skipped);
+            for s in &skipped {
+                println!("skip\t{}", s);
+            }
+            let ops = batch::plan_batch(&tree, &imp.steps)?;
+            for (i, op) in ops.iter().enumerate() {
+                println!("{}\t{}", i + 1, op.summary());
+            }
+            if !cmd.dry_run && !ops.is_empty() {
+                batch::execute_batch(&roots, &ops)?;
+                import::write_entries(&ops, &imp.entries)?;
+                mutate::index(&roots)?;
+            }
+        }
+        Commands::Skeleton(cmd) => match cmd.action {
+            SkeletonAction::Dump { root, contents } => {
+                let tree = fs_walk::scan_roots(&[root])?;
+                let (manifest, warnings) = skeleton::dump(&tree.roots[0], contents);
+                for w in warnings {
+                    eprintln!("warning: {}", w);
+                }
+                print!("{}", manifest);
+            }
+            SkeletonAction::Apply {
+                manifest,
+                dest,
+                dry_run,
+            } => {
+                let text = if manifest.as_os_str() == "-" {
+                    std::io::read_to_string(std::io::stdin())?
+                } else {
+                    std::fs::read_to_string(&manifest)?
+                };
+                let entries = skeleton::parse(&text)?;
+                if dry_run {
+                    for e in &entries {
+                        println!("{}", dest.join(e.path()).display());
+                    }
+                } else {
+                    let applied = skeleton::apply(&entries, &dest)?;
+                    for p in &applied.existing {
+                        println!("exists\t{}", p.display());
+                    }
+                    println!(
+                        "created {} entries in {}",
+                        applied.created.len(),
+                        dest.display()
+                    );
+                }
+            }
+        },
+        Commands::Diff(cmd) => {
+            let old = jd_helper::schema::read(&cmd.old)?.tree;
+            let new = match cmd.new.as_slice() {
+                [file] if file.is_file() => jd_helper::schema::read(file)?.tree,
+                [] => {
+                    let roots: Vec<PathBuf> =
+                        old.roots.iter().map(|r| PathBuf::from(&r.path)).collect();
+                    fs_walk::scan_roots(&roots)?
+                }
+                roots => fs_walk::scan_roots(roots)?,
+            };
+            let changes = jd_helper::diff::differences(&old, &new);
+            if cmd.json {
+                println!("{}", serde_json::to_string_pretty(&changes)?);
+            } else if changes.is_empty() {
+                println!("no changes");
+            } else {
+                for c in &changes {
+                    println!("{}", c.describe());
+                }
+            }
+        }
+        Commands::Completions(cmd) => {
+            use clap::CommandFactory;
+            print!(
+                "{}",
+                complete::script(cmd.shell.into(), &mut Cli::command(), "jd-helper")
+            );
+        }
+        Commands::Init(cmd) => {
+            print!("{}", jd_helper::init::script(cmd.shell.into(), &cmd.roots));
+        }
+        Commands::Complete(cmd) => {
+            let mut roots = cmd.roots;
+            if roots.is_empty() {
+                let env = std::env::var("JD_ROOTS").unwrap_or_default();
+                roots = env.split_whitespace().map(PathBuf::from).collect();
+            }
+            // completion runs on every TAB: take the cached index when there is one
+            let tree = if roots.is_empty() {
+                jd_helper::schema::read(&io::default_index_path())?.tree
+            } else {
+                match io::read_cached(&roots) {
+                    Some(tree) => tree,
+                    None => fs_walk::scan_roots(&roots)?,
+                }
+            };
+            let values = if cmd.ids {
+                complete::Values::Ids
+            } else {
+                complete::Values::Codes
+            };
+            let items = complete::candidates(&tree, &cmd.prefix, values);
+            print!("{}", complete::render(cmd.shell.into(), &items));
+        }
+        Commands::Changes(cmd) => {
+            let cutoff =
+                jd_helper::date::now().saturating_sub(jd_helper::date::parse_age(&cmd.since)?);
+            let index = io::index_path(&cmd.roots);
+            let Some(snap) = jd_helper::snapshot::since(&index, cutoff) else {
+                anyhow::bail!(
+                    "no snapshots of {}; set \"snapshots\" in {} to keep them",
+                    index.display(),
+                    jd_helper::config::config_path().display()
+                );
+            };
+            let old = jd_helper::schema::read(&snap.path)?.tree;
+            let new = fs_walk::scan_roots(&cmd.roots)?;
+            let changes = jd_helper::diff::differences(&old, &new);
+            let since = jd_helper::date::date_time(snap.taken);
+            if cmd.json {
+                let out = serde_json::json!({
+                    "since": jd_helper::date::rfc3339(snap.taken),
+                    "snapshot": snap.path,
+                    "changes": changes,
+                });
+                println!("{}", serde_json::to_string_pretty(&out)?);
+            } else {
+                if snap.taken > cutoff {
+                    eprintln!("the oldest snapshot is from {}", since);
+                }
+                println!("since {}: {}", since, jd_helper::diff::summary(&changes));
+                for c in &changes {
+                    println!("{}", c.describe());
+                }
+            }
+        }
+        Commands::CheckNames(cmd) => {
+            // read it again so a broken config is an error here, not a fallback
+            let cfg = jd_helper::config::load(&jd_helper::config::config_path())?;
+            let tree = fs_walk::scan_roots(&cmd.roots)?;
+            let bad = jd_helper::plan::check_names(&tree, &cfg.names);
+            for (p, problems) in &bad {
+                println!("{}\t{}", p.display(), problems.join("; "));
+            }
+            if !bad.is_empty() {
+                anyhow::bail!("{} name(s) break the name policy", bad.len());
+            }
+        }
         Commands::Merge(cmd) => {
             let tree = fs_walk::scan_roots(&cmd.roots)?;
             let plan = jd_helper::plan::plan_merge(&tree, &cmd.id, &cmd.into)?;
             println!("{}", jd_helper::plan::merge_summary(&plan));
             mutate::execute_merge(&cmd.roots, &plan)?;
         }
+        Commands::Apply(cmd) => {
+            let json = if cmd.plan.as_os_str() == "-" {
+                std::io::read_to_string(std::io::stdin())?
+            } else {
+                std::fs::read_to_string(&cmd.plan)?
+            };
+            let steps = batch::parse_steps(&json)?;
+            // plan against a scan nobody else can change until we're done
+            let _lock = jd_helper::lock::lock_roots(&cmd.roots)?;
+            let tree = fs_walk::scan_roots(&cmd.roots)?;
+            let ops = batch::plan_batch(&tree, &steps)?;
+            for (i, op) in ops.iter().enumerate() {
+                println!("{}\t{}", i + 1, op.summary());
+            }
+            if !cmd.dry_run {
+                batch::execute_batch(&cmd.roots, &ops)?;
+            }
+        }
+        Commands::Trash(cmd) => match cmd.action {
+            TrashAction::List { roots } => {
+                for root in &roots {
+                    for e in jd_helper::trash::list(root)? {
+                        println!(
+                            "{}\t{}\t{}\t{}",
+                            e.name,
+                            e.code.as_deref().unwrap_or(""),
+                            jd_helper::date::date_time(e.deleted),
+                            root.join(&e.original).display()
+                        );
+                    }
+                }
+            }
+            TrashAction::Restore { name, roots } => {
+                let root = roots
+                    .iter()
+                    .find(|r| {
+                        jd_helper::trash::list(r)
+                            .map(|l| l.iter().any(|e| e.name == name))
+                            .unwrap_or(false)
+                    })
+                    .ok_or_else(|| anyhow::anyhow!("no trash entry named {}", name))?;
+                let restored = mutate::restore_trashed(&roots, root, &name)?;
+                println!("{}", restored.display());
+            }
+            TrashAction::Purge {
+                older_than,
+                all,
+                roots,
+            } => {
+                let age = match (older_than, all) {
+                    (Some(a), _) => Some(jd_helper::date::parse_age(&a)?),
+                    (None, true) => None,
+                    (None, false) => anyhow::bail!("pass --older-than AGE or --all"),
+                };
+                for root in &roots {
+                    for e in mutate::purge_trash(root, age)? {
+                        println!("{}\t{}", e.name, root.join(&e.original).display());
+                    }
+                }
+            }
+        },
         Commands::Meta(cmd) => {
             fn node_dir(roots: &[PathBuf], id: &str) -> Result<PathBuf> {
                 let tree = fs_walk::scan_roots(roots)?;
@@ -491,7 +1299,52 @@ fn main() -> Result<()> {
             }
         }
     }
+    // the change went through; say what the post-hooks made of it
+    for f in jd_helper::hooks::take_failures() {
+        eprintln!("warning: {}", f);
+    }
     Ok(())
 }
 
 // interactive helpers moved into mutate module
+
+/// Every node `code` could mean, best first, and whether more than one
+/// fits equally well; an error when nothing matches.
+fn candidates<'a>(
+    tree: &'a model::Tree,
+    code: &str,
+) -> Result<(Vec<resolve::Candidate<'a>>, bool)> {
+    let found = resolve::candidates(tree, code, &jd_helper::config::get().aliases);
+    if found.is_empty() {
+        anyhow::bail!("Nothing matches {}", code);
+    }
+    let ambiguous = resolve::ambiguous(&found);
+    Ok((found, ambiguous))
+}
+
+fn candidate_json(c: &resolve::Candidate) -> serde_json::Value {
+    serde_json::json!({
+        "match": c.how,
+        "code": c.node.code,
+        "title": c.node.title,
+        "node_type": c.node.node_type,
+        "id": c.node.id,
+        "path": c.node.path,
+        "url": resolve::url(c.node),
+    })
+}
+
+fn wrap(cmd: &WrapCmd, unwrap: bool) -> Result<()> {
+    let tree = fs_walk::scan_roots(&cmd.roots)?;
+    let plan = if unwrap {
+        jd_helper::plan::plan_unwrap(&tree, &cmd.id)?
+    } else {
+        jd_helper::plan::plan_wrap(&tree, &cmd.id)?
+    };
+    println!("{}", jd_helper::plan::wrap_summary(&plan));
+    if !cmd.dry_run {
+        let out = mutate::execute_wrap(&cmd.roots, &plan)?;
+        println!("{}", out.display());
+    }
+    Ok(())
+}
@@ -13,7 +13,8 @@
 //! ```
 //!
 //! Repeated keys mean multiple values. `LINK` is URL-first with an optional
-//! label after the first space (URLs contain no spaces).
+//! label after the first space (URLs contain no spaces). `archive` adds
+//! single-valued `ARCHIVED_FROM=21.04` and `ARCHIVED=2026-10-19` lines.
 
 use anyhow::Result;
 use serde::{Deserialize, Serialize};
@@ -22,6 +23,8 @@ use std::path::{Path, PathBuf};
 
 pub const META_FILE: &str = ".jdmeta";
 pub const NOTES_FILE: &str = ".jdmeta.md";
+pub const ARCHIVED_FROM: &str = "ARCHIVED_FROM";
+pub const ARCHIVED_ON: &str = "ARCHIVED";
 
 pub fn notes_path(dir: &Path) -> PathBuf {
     dir.join(NOTES_FILE)
@@ -39,14 +42,15 @@ pub fn ensure_notes(dir: &Path, title: &str) -> Result<PathBuf> {
     Ok(path)
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, schemars::JsonSchema)]
 pub struct MetaLink {
     pub url: String,
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub label: Option<String>,
 }
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
 pub enum Entry {
     Location(String),
     Link(MetaLink),
@@ -119,11 +123,99 @@ impl Entry {
 
 /// Ordered entries of a directory's `.jdmeta` (empty if absent/unreadable).
 pub fn entries(dir: &Path) -> Vec<Entry> {
-    fs::read_to_string(dir.join(META_FILE))
+    read_entries(&dir.join(META_FILE))
+}
+
+fn read_entries(path: &Path) -> Vec<Entry> {
+    fs::read_to_string(path)
         .map(|s| s.lines().filter_map(Entry::parse_line).collect())
         .unwrap_or_default()
 }
 
+/// A file item keeps what would be its folder's `.jdmeta` and `.jdmeta.md`
+/// beside it as `.NAME.jdmeta` and `.NAME.jdmeta.md` (written by unwrap,
+/// taken back by wrap).
+pub fn sidecars(file: &Path) -> [PathBuf; 2] {
+    let name = file.file_name().unwrap_or_default().to_string_lossy();
+    [META_FILE, NOTES_FILE].map(|suffix| file.with_file_name(format!(".{}{}", name, suffix)))
+}
+
+/// Entries of a file item's `.NAME.jdmeta` sidecar.
+pub fn file_entries(file: &Path) -> Vec<Entry> {
+    let [meta, _] = sidecars(file);
+    read_entries(&meta)
+}
+
+/// Move `src`'s sidecars (if any) to go with `dest`, after `src` itself was
+/// renamed or moved there.
+pub fn carry_sidecars(src: &Path, dest: &Path) -> Result<()> {
+    for (from, to) in sidecars(src).into_iter().zip(sidecars(dest)) {
+        if fs::symlink_metadata(&from).is_ok() {
+            if let Err(e) = fs::rename(&from, &to) {
+                if e.kind() != std::io::ErrorKind::CrossesDevices {
+                    return Err(e.into());
+                }
+                fs::copy(&from, &to)?;
+                fs::remove_file(&from)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// The `.jdmeta` describing an item: inside a folder, beside a file.
+pub fn meta_path(item: &Path) -> PathBuf {
+    if item.is_dir() {
+        item.join(META_FILE)
+    } else {
+        let [meta, _] = sidecars(item);
+        meta
+    }
+}
+
+/// The value of the first `KEY=` line of a `.jdmeta` file.
+pub fn field(meta: &Path, key: &str) -> Option<String> {
+    let content = fs::read_to_string(meta).ok()?;
+    content.lines().find_map(|l| {
+        l.strip_prefix(key)
+            .and_then(|r| r.strip_prefix('='))
+            .map(|v| v.trim().to_string())
+            .filter(|v| !v.is_empty())
+    })
+}
+
+/// Set `KEY=value` in place of any existing `KEY=` lines, or drop them for
+/// `None`; every other line is kept. Removes a file left empty.
+pub fn set_field(meta: &Path, key: &str, value: Option<&str>) -> Result<()> {
+    let content = fs::read_to_string(meta).unwrap_or_default();
+    let prefix = format!("{}=", key);
+    let mut out = String::new();
+    let mut pending = value.map(|v| format!("{}{}", prefix, v));
+    for line in content.lines() {
+        if line.starts_with(&prefix) {
+            if let Some(l) = pending.take() {
+                out.push_str(&l);
+                out.push('\n');
+            }
+            continue;
+        }
+        out.push_str(line);
+        out.push('\n');
+    }
+    if let Some(l) = pending {
+        out.push_str(&l);
+        out.push('\n');
+    }
+    if out.trim().is_empty() {
+        if meta.exists() {
+            fs::remove_file(meta)?;
+        }
+        Ok(())
+    } else {
+        atomic_write(meta, &out)
+    }
+}
+
 /// Append an entry, creating the file if needed.
 pub fn add_entry(dir: &Path, entry: &Entry) -> Result<()> {
     let path = dir.join(META_FILE);
@@ -1,10 +1,11 @@
 use anyhow::{bail, Result};
 use regex::Regex;
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use std::collections::BTreeSet;
 use std::path::Path;
 
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, JsonSchema)]
 pub enum NodeType {
     Range,
     Category,
@@ -14,29 +15,38 @@ pub enum NodeType {
     Other,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct Node {
-    pub id: String,           // stable id derived from path
-    pub code: Option<String>, // JD code parsed from name
+    /// Stable id: the inode where there is one, else derived from the path.
+    pub id: String,
+    /// JD code parsed from the name.
+    pub code: Option<String>,
     pub title: String,
-    pub path: String, // absolute filesystem path for all node types
+    /// Absolute filesystem path, for every node type.
+    pub path: String,
     pub node_type: NodeType,
-    pub location: Option<String>, // parsed from file contents (LOCATION=...)
-    pub url: Option<String>,      // for link nodes only
-    // From this directory's .jdmeta: where else this number lives
+    /// Parsed from a file's contents (LOCATION=...).
+    pub location: Option<String>,
+    /// For link nodes only.
+    pub url: Option<String>,
+    /// From the node's .jdmeta: where else this number lives.
     #[serde(default, skip_serializing_if = "Vec::is_empty")]
     pub locations: Vec<String>,
     #[serde(default, skip_serializing_if = "Vec::is_empty")]
     pub links: Vec<crate::meta::MetaLink>,
+    /// A .jdmeta.md notes file sits in the folder.
     #[serde(default)]
     pub has_notes: bool,
+    /// The code this node had before `archive` moved it (ARCHIVED_FROM=).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub archived_from: Option<String>,
     pub children: Vec<Node>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[derive(Debug, Clone, Serialize, Deserialize, Default, JsonSchema)]
 pub struct Tree {
     pub roots: Vec<Node>,
-    // Non-fatal scan findings, e.g. duplicate sibling codes
+    /// Non-fatal scan findings, e.g. duplicate sibling codes.
     #[serde(default, skip_serializing_if = "Vec::is_empty")]
     pub warnings: Vec<String>,
 }
@@ -323,6 +333,7 @@ mod tests {
             locations: vec![],
             links: vec![],
             has_notes: false,
+            archived_from: None,
             children,
         }
     }
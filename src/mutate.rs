@@ -1,8 +1,9 @@
 use crate::{
-    fs_walk,
+    fs_walk, hooks,
     io::IndexIo,
-    model,
+    lock, model,
     plan::{self, CreatePlan, MovePlan, PlanKind, RenamePlan},
+    template, trash,
 };
 use anyhow::Result;
 use std::{
@@ -12,10 +13,21 @@ use std::{
 };
 
 pub use crate::plan::PlanKind as NewKind;
-fn index(roots: &[PathBuf]) -> Result<()> {
-    IndexIo::default().write_index(None, &fs_walk::scan_roots(roots)?)?;
+/// Rescan and rewrite the index mirror after a disk mutation.
+pub fn index(roots: &[PathBuf]) -> Result<()> {
+    IndexIo.write_index(None, &fs_walk::scan_roots(roots)?)?;
     Ok(())
 }
+
+/// Lock the roots and scan them afresh, so a plan made from an older scan
+/// can be checked before it runs. Hold the lock until the index is written.
+fn fresh(roots: &[PathBuf]) -> Result<(lock::RootLock, model::Tree)> {
+    let lock = lock::lock_roots(roots)?;
+    Ok((lock, fs_walk::scan_roots(roots)?))
+}
+/// `template` names a `.jdtemplates/` folder for new directories (files and
+/// links take none); None uses the default for the code's kind, `"none"`
+/// skips templates.
 pub fn create(
     roots: &[PathBuf],
     kind: NewKind,
@@ -23,17 +35,55 @@ pub fn create(
     name: &str,
     url: Option<&str>,
     location: Option<&str>,
+    template: Option<&str>,
 ) -> Result<()> {
-    let tree = fs_walk::scan_roots(roots)?;
+    let (_lock, tree) = fresh(roots)?;
     let parent =
         model::find_node(&tree, parent_id).ok_or_else(|| anyhow::anyhow!("parent not found"))?;
-    let p = PathBuf::from(&parent.path).join(name);
+    let dir = Path::new(&parent.path);
+    let name = &plan::sanitize_name(name)?;
+    let p = dir.join(name);
+    let root = trash::root_of(roots, &p)
+        .map(PathBuf::as_path)
+        .unwrap_or(dir);
+    let template = match (kind, template) {
+        (_, Some("none")) | (PlanKind::File | PlanKind::Link, None) => None,
+        (PlanKind::File | PlanKind::Link, Some(_)) => {
+            anyhow::bail!("templates apply to folders only")
+        }
+        (PlanKind::Dir, Some(t)) => Some(template::find(root, dir, t)?),
+        (PlanKind::Dir, None) => {
+            let code = template::Vars::for_name(name).code;
+            template::default_for(root, dir, Some(code.as_str()).filter(|c| !c.is_empty()))
+        }
+    };
+    let plan = serde_json::json!({
+        "kind": kind, "parent_id": parent_id, "final_name": name, "dest_path": p,
+        "url": url, "location": location, "template": template,
+    });
+    let ev = hooks::Event::new("create", &plan)
+        .paths(None, Some(&p))
+        .code(plan::name_code(name).as_deref());
+    hooks::around(ev, || {
+        write_new(kind, &p, url, location.unwrap_or(&parent.path))?;
+        if let Some(t) = template {
+            fill_template(&t, &p)?;
+        }
+        index(roots)
+    })
+}
+
+/// Fill a freshly created directory from a template (no rescan).
+pub fn fill_template(template: &Path, dir: &Path) -> Result<()> {
+    let vars = template::Vars::for_name(&dir.file_name().unwrap().to_string_lossy());
+    template::instantiate(template, dir, &vars)
+}
+
+/// Put a new directory, LOCATION= file or link file at `p` (no rescan).
+pub fn write_new(kind: NewKind, p: &Path, url: Option<&str>, location: &str) -> Result<()> {
     match kind {
-        PlanKind::Dir => fs::create_dir(&p)?,
-        PlanKind::File => fs::write(
-            &p,
-            format!("LOCATION={}\n", location.unwrap_or(&parent.path)),
-        )?,
+        PlanKind::Dir => fs::create_dir(p)?,
+        PlanKind::File => fs::write(p, format!("LOCATION={}\n", location))?,
         PlanKind::Link => {
             let u = url.ok_or_else(|| anyhow::anyhow!("link requires --url"))?;
             if p.extension()
@@ -42,7 +92,7 @@ pub fn create(
                 .unwrap_or(false)
             {
                 fs::write(
-                    &p,
+                    p,
                     format!(
                         r#"<?xml version="1.0" encoding="UTF-8"?>
 <!DOCTYPE plist PUBLIC "-//Apple//DTD PLIST 1.0//EN" "http://www.apple.com/DTDs/PropertyList-1.0.dtd">
@@ -51,60 +101,316 @@ pub fn create(
                     ),
                 )?
             } else {
-                fs::write(&p, u)?
+                fs::write(p, u)?
             }
         }
     };
-    index(roots)
+    Ok(())
 }
-pub fn execute_create(roots: &[PathBuf], p: &CreatePlan) -> Result<()> {
-    create(
-        roots,
-        p.kind,
-        &p.parent_id,
-        &p.final_name,
-        p.url.as_deref(),
-        p.location.as_deref(),
-    )
+/// Run a create under the roots' lock. Returns the plan as executed: its
+/// suggested code is replaced if another process took it meanwhile.
+pub fn execute_create(roots: &[PathBuf], p: &CreatePlan) -> Result<CreatePlan> {
+    let (_lock, tree) = fresh(roots)?;
+    let p = plan::revalidate_create(&tree, p)?;
+    let ev = hooks::Event::new("create", &p)
+        .paths(None, Some(&p.dest_path))
+        .code(plan::name_code(&p.final_name).as_deref());
+    hooks::around(ev, || {
+        let parent = p.dest_path.parent().unwrap().to_string_lossy().to_string();
+        write_new(
+            p.kind,
+            &p.dest_path,
+            p.url.as_deref(),
+            p.location.as_deref().unwrap_or(&parent),
+        )?;
+        if let Some(t) = &p.template {
+            fill_template(t, &p.dest_path)?;
+        }
+        index(roots)
+    })?;
+    Ok(p)
 }
 pub fn execute_rename(roots: &[PathBuf], p: &RenamePlan) -> Result<()> {
-    let tree = fs_walk::scan_roots(roots)?;
-    let n = model::find_node(&tree, &p.id).ok_or_else(|| anyhow::anyhow!("not found"))?;
-    fs::rename(&n.path, &p.dest_path)?;
-    index(roots)
+    let (_lock, tree) = fresh(roots)?;
+    plan::revalidate(&tree, &p.id, &p.src_path, &p.dest_path, None)?;
+    let ev = hooks::Event::new("rename", p)
+        .paths(Some(&p.src_path), Some(&p.dest_path))
+        .code(plan::name_code(&p.new_name).as_deref());
+    hooks::around(ev, || {
+        fs::rename(&p.src_path, &p.dest_path)?;
+        crate::meta::carry_sidecars(&p.src_path, &p.dest_path)?;
+        index(roots)
+    })
 }
 pub fn rename(roots: &[PathBuf], id: &str, name: &str) -> Result<()> {
-    let t = fs_walk::scan_roots(roots)?;
+    let (_lock, t) = fresh(roots)?;
     execute_rename(roots, &plan::plan_rename(&t, id, name)?)
 }
-pub fn execute_move(roots: &[PathBuf], p: &MovePlan) -> Result<()> {
-    fs::rename(&p.src_path, &p.dest_path)?;
-    index(roots)
+pub fn execute_move(
+    roots: &[PathBuf],
+    p: &MovePlan,
+    progress: &mut dyn FnMut(Progress),
+) -> Result<()> {
+    let (_lock, tree) = fresh(roots)?;
+    let code = model::parse_item(&p.final_name).map(|(c, _, _)| c);
+    plan::revalidate(&tree, &p.id, &p.src_path, &p.dest_path, code.as_deref())?;
+    let ev = hooks::Event::new("move", p)
+        .paths(Some(&p.src_path), Some(&p.dest_path))
+        .code(plan::name_code(&p.final_name).as_deref());
+    hooks::around(ev, || {
+        move_path(&p.src_path, &p.dest_path, progress)?;
+        index(roots)
+    })
 }
 pub fn move_node(roots: &[PathBuf], id: &str, parent: &str) -> Result<()> {
-    let t = fs_walk::scan_roots(roots)?;
-    execute_move(roots, &plan::plan_move(&t, id, parent)?)
+    let (_lock, t) = fresh(roots)?;
+    execute_move(roots, &plan::plan_move(&t, id, parent)?, &mut |_| {})
+}
+
+/// Files and bytes copied so far during a cross-device move.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Progress {
+    pub files: u64,
+    pub total_files: u64,
+    pub bytes: u64,
+    pub total_bytes: u64,
+}
+
+/// Move `src` to `dest`: a plain rename on one filesystem, copy-verify-remove
+/// across devices. `progress` is called after every copied file.
+pub fn move_path(src: &Path, dest: &Path, progress: &mut dyn FnMut(Progress)) -> Result<()> {
+    match fs::rename(src, dest) {
+        Err(e) if e.kind() == io::ErrorKind::CrossesDevices => copy_across(src, dest, progress)?,
+        r => r?,
+    }
+    crate::meta::carry_sidecars(src, dest)
+}
+
+/// Copy `src` to `dest` keeping permissions and timestamps, check every file
+/// against its sha1, and only then remove `src`. If the copy fails the partial
+/// copy is removed and `src` is left untouched; if removing `src` fails the
+/// error says the copy is complete and lists what is still at `src`.
+pub fn copy_across(src: &Path, dest: &Path, progress: &mut dyn FnMut(Progress)) -> Result<()> {
+    copy_tree(src, dest, progress)?;
+    remove_any(src).map_err(|e| {
+        e.context(format!(
+            "copied to {} in full, but could not remove the source; still at {}: {}",
+            dest.display(),
+            src.display(),
+            leftovers(src)
+        ))
+    })
+}
+
+/// What remains under `src` after a failed removal: up to a few paths,
+/// relative to it.
+fn leftovers(src: &Path) -> String {
+    const SHOWN: usize = 5;
+    fn walk(dir: &Path, base: &Path, out: &mut Vec<String>) {
+        let Ok(entries) = fs::read_dir(dir) else {
+            return;
+        };
+        for e in entries.flatten() {
+            let p = e.path();
+            let is_dir = e.file_type().is_ok_and(|t| t.is_dir());
+            let before = out.len();
+            if is_dir {
+                walk(&p, base, out);
+            }
+            // list a directory only when nothing under it was listed
+            if !is_dir || out.len() == before {
+                let rel = p.strip_prefix(base).unwrap_or(&p);
+                out.push(rel.to_string_lossy().to_string());
+            }
+        }
+    }
+    if !fs::symlink_metadata(src).is_ok_and(|m| m.is_dir()) {
+        return "the file itself".into();
+    }
+    let mut left = Vec::new();
+    walk(src, src, &mut left);
+    left.sort();
+    let more = left.len().saturating_sub(SHOWN);
+    left.truncate(SHOWN);
+    let mut s = left.join(", ");
+    if more > 0 {
+        s.push_str(&format!(" and {} more", more));
+    }
+    if s.is_empty() {
+        s = "the empty directory".into();
+    }
+    s
+}
+
+/// The copy half of `copy_across`: a verified copy of `src` at `dest`, with
+/// nothing left behind at `dest` if it fails.
+pub fn copy_tree(src: &Path, dest: &Path, progress: &mut dyn FnMut(Progress)) -> Result<()> {
+    if fs::symlink_metadata(dest).is_ok() {
+        anyhow::bail!("destination already exists: {}", dest.display());
+    }
+    let mut p = Progress::default();
+    tally(src, &mut p)?;
+    if let Err(e) = copy_verified(src, dest, &mut p, progress) {
+        let _ = remove_any(dest);
+        return Err(e.context(format!(
+            "copy of {} failed; source left in place",
+            src.display()
+        )));
+    }
+    Ok(())
+}
+
+fn tally(p: &Path, acc: &mut Progress) -> Result<()> {
+    let meta = fs::symlink_metadata(p)?;
+    if meta.is_dir() {
+        for entry in fs::read_dir(p)? {
+            tally(&entry?.path(), acc)?;
+        }
+    } else {
+        acc.total_files += 1;
+        acc.total_bytes += meta.len();
+    }
+    Ok(())
+}
+
+fn copy_verified(
+    src: &Path,
+    dest: &Path,
+    p: &mut Progress,
+    progress: &mut dyn FnMut(Progress),
+) -> Result<()> {
+    let meta = fs::symlink_metadata(src)?;
+    if meta.file_type().is_symlink() {
+        #[cfg(unix)]
+        std::os::unix::fs::symlink(fs::read_link(src)?, dest)?;
+        #[cfg(not(unix))]
+        anyhow::bail!("cannot copy symlink {}", src.display());
+        p.files += 1;
+        progress(*p);
+        return Ok(());
+    }
+    if meta.is_dir() {
+        fs::create_dir(dest)?;
+        for entry in fs::read_dir(src)? {
+            let entry = entry?;
+            copy_verified(&entry.path(), &dest.join(entry.file_name()), p, progress)?;
+        }
+    } else {
+        fs::copy(src, dest)?;
+        if sha1_file(src)? != sha1_file(dest)? {
+            anyhow::bail!("checksum mismatch copying {}", src.display());
+        }
+        p.files += 1;
+        p.bytes += meta.len();
+        progress(*p);
+    }
+    // Permissions and times last: a read-only directory must be filled first,
+    // and filling it bumps its mtime.
+    fs::set_permissions(dest, meta.permissions())?;
+    let times = fs::FileTimes::new()
+        .set_accessed(meta.accessed()?)
+        .set_modified(meta.modified()?);
+    fs::File::open(dest)?.set_times(times)?;
+    Ok(())
+}
+
+fn sha1_file(p: &Path) -> Result<Vec<u8>> {
+    use sha1::{Digest, Sha1};
+    let mut hasher = Sha1::new();
+    io::copy(&mut fs::File::open(p)?, &mut hasher)?;
+    Ok(hasher.finalize().to_vec())
+}
+
+fn remove_any(p: &Path) -> Result<()> {
+    if fs::symlink_metadata(p)?.is_dir() {
+        fs::remove_dir_all(p)?;
+    } else {
+        fs::remove_file(p)?;
+    }
+    Ok(())
 }
 pub fn delete_node(roots: &[PathBuf], id: &str) -> Result<PathBuf> {
-    let t = fs_walk::scan_roots(roots)?;
+    let (_lock, t) = fresh(roots)?;
     let n = model::find_node(&t, id).ok_or_else(|| anyhow::anyhow!("not found"))?;
-    let p = PathBuf::from(&n.path);
-    let trash = p.parent().unwrap().join(".jd_trash");
-    fs::create_dir_all(&trash)?;
-    let target = trash.join(p.file_name().unwrap());
-    if target.exists() {
-        anyhow::bail!("trash destination already exists")
-    };
-    fs::rename(&p, &target)?;
-    index(roots)?;
-    Ok(target)
+    let plan = serde_json::json!({"id": id, "src_path": n.path});
+    let ev = hooks::Event::new("delete", &plan)
+        .paths(Some(Path::new(&n.path)), None)
+        .code(n.code.as_deref());
+    hooks::around(ev, || {
+        let target = trash(roots, Path::new(&n.path))?;
+        index(roots)?;
+        Ok(target)
+    })
 }
-pub fn undo_delete(roots: &[PathBuf], trash: &Path, original: &Path) -> Result<()> {
-    if original.exists() {
-        anyhow::bail!("original path is occupied")
-    };
-    fs::rename(trash, original)?;
-    index(roots)
+
+/// Soft-delete `p` into its root's `.jd_trash/` (no rescan); returns the
+/// trashed path.
+pub fn trash(roots: &[PathBuf], p: &Path) -> Result<PathBuf> {
+    let root = trash::root_of(roots, p)
+        .ok_or_else(|| anyhow::anyhow!("{} is not under a root", p.display()))?;
+    trash::put(root, p)
+}
+
+/// Put a trashed entry back where it came from (no rescan); returns the
+/// restored path.
+pub fn untrash(trashed: &Path) -> Result<PathBuf> {
+    let root = trashed
+        .parent()
+        .and_then(Path::parent)
+        .ok_or_else(|| anyhow::anyhow!("not a trash entry: {}", trashed.display()))?;
+    trash::restore(root, &trashed.file_name().unwrap().to_string_lossy())
+}
+
+pub fn undo_delete(roots: &[PathBuf], trashed: &Path) -> Result<PathBuf> {
+    let _lock = lock::lock_roots(roots)?;
+    let plan = serde_json::json!({"trash_path": trashed});
+    let name = trashed.file_name().unwrap_or_default().to_string_lossy();
+    let ev = hooks::Event::new("restore", &plan)
+        .paths(Some(trashed), None)
+        .code(plan::name_code(&name).as_deref());
+    hooks::around(ev, || {
+        let restored = untrash(trashed)?;
+        index(roots)?;
+        Ok(restored)
+    })
+}
+
+/// Restore the trash entry stored as `name` under `root` and reindex;
+/// returns the restored path.
+pub fn restore_trashed(roots: &[PathBuf], root: &Path, name: &str) -> Result<PathBuf> {
+    let entry = trash::list(root)?.into_iter().find(|e| e.name == name);
+    let trashed = trash::trash_dir(root).join(name);
+    let plan = serde_json::json!({"trash_path": trashed});
+    let ev = hooks::Event::new("restore", &plan)
+        .paths(
+            Some(&trashed),
+            entry.as_ref().map(|e| root.join(&e.original)).as_deref(),
+        )
+        .code(entry.as_ref().and_then(|e| e.code.as_deref()));
+    hooks::around(ev, || {
+        let restored = trash::restore(root, name)?;
+        index(roots)?;
+        Ok(restored)
+    })
+}
+
+/// Permanently remove trash entries under `root` deleted at least
+/// `older_than` seconds ago (everything when None).
+pub fn purge_trash(root: &Path, older_than: Option<u64>) -> Result<Vec<trash::TrashEntry>> {
+    let plan = serde_json::json!({"root": root, "older_than": older_than});
+    let ev = hooks::Event::new("purge", &plan);
+    hooks::around(ev, || trash::purge(root, older_than))
+}
+
+/// Permanently remove the single trash entry stored as `name`.
+pub fn purge_trashed(root: &Path, name: &str) -> Result<()> {
+    let entry = trash::list(root)?.into_iter().find(|e| e.name == name);
+    let trashed = trash::trash_dir(root).join(name);
+    let plan = serde_json::json!({"trash_path": trashed});
+    let ev = hooks::Event::new("purge", &plan)
+        .paths(Some(&trashed), None)
+        .code(entry.as_ref().and_then(|e| e.code.as_deref()));
+    hooks::around(ev, || trash::purge_entry(root, name))
 }
 
 /// Execute a merge. Absorbing a pointer returns the (trash, original) pair
@@ -114,7 +420,14 @@ pub fn execute_merge(
     roots: &[PathBuf],
     p: &crate::plan::MergePlan,
 ) -> Result<Option<(PathBuf, PathBuf)>> {
-    match &p.action {
+    let (_lock, tree) = fresh(roots)?;
+    plan::revalidate(&tree, &p.source_id, &p.src_path, &p.src_path, None)?;
+    plan::revalidate(&tree, &p.target_id, &p.target_path, &p.target_path, None)?;
+    let target = model::find_node(&tree, &p.target_id).and_then(|n| n.code.clone());
+    let ev = hooks::Event::new("merge", p)
+        .paths(Some(&p.src_path), Some(&p.target_path))
+        .code(target.as_deref());
+    hooks::around(ev, || match &p.action {
         crate::plan::MergeAction::AbsorbPointer { entries } => {
             for e in entries {
                 crate::meta::add_entry(&p.target_path, e)?;
@@ -127,32 +440,189 @@ pub fn execute_merge(
             index(roots)?;
             Ok(None)
         }
-    }
+    })
 }
 
 /// Execute a renumber: rename the entry, cascade-rename descendants whose
 /// filenames embed the old code, and rewrite the old code inside the entry's
 /// own .jdmeta. Returns the new path.
 pub fn execute_renumber(roots: &[PathBuf], p: &crate::plan::RenumberPlan) -> Result<PathBuf> {
-    fs::rename(&p.src_path, &p.dest_path)?;
-    if p.dest_path.is_dir() {
-        recode_children(&p.dest_path, &p.old_code, &p.new_code)?;
-        rewrite_meta_code(&p.dest_path, &p.old_code, &p.new_code)?;
+    let (_lock, tree) = fresh(roots)?;
+    plan::revalidate(&tree, &p.id, &p.src_path, &p.dest_path, Some(&p.new_code))?;
+    let ev = hooks::Event::new("renumber", p)
+        .paths(Some(&p.src_path), Some(&p.dest_path))
+        .code(Some(&p.new_code));
+    hooks::around(ev, || {
+        recode(&p.src_path, &p.dest_path, &p.old_code, &p.new_code)?;
+        index(roots)
+    })?;
+    Ok(p.dest_path.clone())
+}
+
+/// Reverse an executed renumber/recode. Refused when something has since
+/// taken the original path.
+pub fn undo_renumber(roots: &[PathBuf], p: &crate::plan::RenumberPlan) -> Result<()> {
+    let _lock = lock::lock_roots(roots)?;
+    if p.src_path.exists() {
+        anyhow::bail!("{} is occupied", p.src_path.display());
+    }
+    let ev = hooks::Event::new("renumber", p)
+        .paths(Some(&p.dest_path), Some(&p.src_path))
+        .code(Some(&p.old_code));
+    hooks::around(ev, || {
+        recode(&p.dest_path, &p.src_path, &p.new_code, &p.old_code)?;
+        index(roots)
+    })
+}
+
+/// Wrap a file item into a folder item or unwrap one, moving `.jdmeta` and
+/// `.jdmeta.md` between the folder and the file's sidecars. Returns the new
+/// path of the item (the folder, or the file).
+pub fn execute_wrap(roots: &[PathBuf], p: &crate::plan::WrapPlan) -> Result<PathBuf> {
+    let (_lock, tree) = fresh(roots)?;
+    let (src, out) = if p.unwrap {
+        (&p.dir_path, &p.dest_path)
+    } else {
+        (&p.file_path, &p.dir_path)
+    };
+    plan::revalidate(&tree, &p.id, src, out, None)?;
+    let code = model::find_node(&tree, &p.id).and_then(|n| n.code.clone());
+    let ev = hooks::Event::new(if p.unwrap { "unwrap" } else { "wrap" }, p)
+        .paths(Some(src), Some(out))
+        .code(code.as_deref());
+    hooks::around(ev, || {
+        if p.unwrap {
+            unwrap_dir(p)?;
+        } else {
+            wrap_file(p)?;
+        }
+        index(roots)
+    })?;
+    Ok(out.clone())
+}
+
+fn wrap_file(p: &crate::plan::WrapPlan) -> Result<()> {
+    let name = p.file_path.file_name().unwrap().to_string_lossy();
+    // without an extension the folder takes the file's own name
+    let parked = p.file_path.with_file_name(format!(".{}.wrap", name));
+    fs::rename(&p.file_path, &parked)?;
+    let moved = (|| -> Result<()> {
+        fs::create_dir(&p.dir_path)?;
+        fs::rename(&parked, &p.dest_path)?;
+        let [meta, notes] = crate::meta::sidecars(&p.file_path);
+        for (from, to) in [
+            (meta, crate::meta::META_FILE),
+            (notes, crate::meta::NOTES_FILE),
+        ] {
+            if from.exists() {
+                fs::rename(&from, p.dir_path.join(to))?;
+            }
+        }
+        Ok(())
+    })();
+    if let Err(e) = moved {
+        let _ = fs::rename(&p.dest_path, &parked);
+        let _ = fs::remove_dir(&p.dir_path);
+        let _ = fs::rename(&parked, &p.file_path);
+        return Err(e.context("wrap failed; the file is back in place"));
+    }
+    Ok(())
+}
+
+fn unwrap_dir(p: &crate::plan::WrapPlan) -> Result<()> {
+    let name = p.dest_path.file_name().unwrap().to_string_lossy();
+    let parked = p.dest_path.with_file_name(format!(".{}.unwrap", name));
+    fs::rename(&p.file_path, &parked)?;
+    let moved = (|| -> Result<()> {
+        let [meta, notes] = crate::meta::sidecars(&p.dest_path);
+        for (from, to) in [
+            (crate::meta::META_FILE, meta),
+            (crate::meta::NOTES_FILE, notes),
+        ] {
+            let from = p.dir_path.join(from);
+            if from.exists() {
+                fs::rename(&from, &to)?;
+            }
+        }
+        // empty by now; if something appeared since the plan, fail, don't wipe it
+        fs::remove_dir(&p.dir_path)?;
+        fs::rename(&parked, &p.dest_path)?;
+        Ok(())
+    })();
+    if let Err(e) = moved {
+        let _ = fs::create_dir_all(&p.dir_path);
+        let [meta, notes] = crate::meta::sidecars(&p.dest_path);
+        let _ = fs::rename(meta, p.dir_path.join(crate::meta::META_FILE));
+        let _ = fs::rename(notes, p.dir_path.join(crate::meta::NOTES_FILE));
+        let _ = fs::rename(&parked, &p.file_path);
+        return Err(e.context("unwrap failed; the folder is back in place"));
+    }
+    Ok(())
+}
+
+/// Rename `src` to `dest` and carry a code change from `old` to `new` into
+/// its descendants and its .jdmeta (no rescan). Calling it again with the
+/// arguments swapped undoes it.
+pub fn recode(src: &Path, dest: &Path, old: &str, new: &str) -> Result<()> {
+    fs::rename(src, dest)?;
+    crate::meta::carry_sidecars(src, dest)?;
+    recode_inside(dest, old, new)
+}
+
+/// The part of a recode after `dest` got its new name: stamped children and
+/// the folder's own .jdmeta follow.
+fn recode_inside(dest: &Path, old: &str, new: &str) -> Result<()> {
+    if dest.is_dir() {
+        recode_children(dest, old, new)?;
+        rewrite_meta_code(dest, old, new)?;
     }
-    index(roots)?;
+    Ok(())
+}
+
+/// Archive or unarchive a node: move it under its new parent with the new
+/// code (children recoded), then record or clear ARCHIVED_FROM/ARCHIVED.
+pub fn execute_archive(roots: &[PathBuf], p: &crate::plan::ArchivePlan) -> Result<PathBuf> {
+    let (_lock, tree) = fresh(roots)?;
+    plan::revalidate(&tree, &p.id, &p.src_path, &p.dest_path, Some(&p.new_code))?;
+    let ev = hooks::Event::new(if p.unarchive { "unarchive" } else { "archive" }, p)
+        .paths(Some(&p.src_path), Some(&p.dest_path))
+        .code(Some(&p.new_code));
+    hooks::around(ev, || {
+        move_path(&p.src_path, &p.dest_path, &mut |_| {})?;
+        if let Err(e) = recode_inside(&p.dest_path, &p.old_code, &p.new_code) {
+            let _ = recode_inside(&p.dest_path, &p.new_code, &p.old_code);
+            let _ = move_path(&p.dest_path, &p.src_path, &mut |_| {});
+            return Err(e);
+        }
+        use crate::meta::{set_field, ARCHIVED_FROM, ARCHIVED_ON};
+        let meta = crate::meta::meta_path(&p.dest_path);
+        if p.unarchive {
+            set_field(&meta, ARCHIVED_FROM, None)?;
+            set_field(&meta, ARCHIVED_ON, None)?;
+        } else {
+            set_field(&meta, ARCHIVED_FROM, Some(&p.old_code))?;
+            let today = crate::date::date(crate::date::now());
+            set_field(&meta, ARCHIVED_ON, Some(&today))?;
+        }
+        index(roots)
+    })?;
     Ok(p.dest_path.clone())
 }
 
-/// Rename every descendant whose filename starts with "{old}." to start with
-/// "{new}." instead (item codes embed their ancestors' codes).
+/// Rename every descendant whose filename starts with "{old}." (item codes
+/// embed their ancestors' codes) or "{old}_" (stamped files) to start with
+/// the new code instead.
 fn recode_children(dir: &Path, old: &str, new: &str) -> Result<()> {
-    let prefix = format!("{}.", old);
     for entry in fs::read_dir(dir)?.filter_map(|e| e.ok()) {
         let name = entry.file_name().to_string_lossy().to_string();
         let path = entry.path();
-        let path = if let Some(rest) = name.strip_prefix(&prefix) {
-            let renamed = dir.join(format!("{}.{}", new, rest));
+        let rest = name
+            .strip_prefix(old)
+            .filter(|r| r.starts_with('.') || r.starts_with('_'));
+        let path = if let Some(rest) = rest {
+            let renamed = dir.join(format!("{}{}", new, rest));
             fs::rename(&path, &renamed)?;
+            crate::meta::carry_sidecars(&path, &renamed)?;
             renamed
         } else {
             path
@@ -179,6 +649,103 @@ fn rewrite_meta_code(dir: &Path, old: &str, new: &str) -> Result<()> {
     }
     Ok(())
 }
+
+/// Execute a range/category recode: rewrite references in notes first, then
+/// rename deepest entries first so planned paths stay valid. Any failure
+/// restores what was already changed. Returns the new path.
+pub fn execute_recode(roots: &[PathBuf], p: &crate::plan::RecodePlan) -> Result<PathBuf> {
+    let (_lock, tree) = fresh(roots)?;
+    let now = plan::plan_recode(&tree, &p.id, &p.new_code, p.rewrite_notes)?;
+    if now.renames != p.renames || now.rewrites != p.rewrites {
+        anyhow::bail!(
+            "{} changed since the recode was planned; rescan and try again",
+            p.src_path.display()
+        );
+    }
+    let ev = hooks::Event::new("recode", p)
+        .paths(Some(&p.src_path), Some(&p.dest_path))
+        .code(Some(&p.new_code));
+    hooks::around(ev, || recode_tree(roots, p))?;
+    Ok(p.dest_path.clone())
+}
+
+fn recode_tree(roots: &[PathBuf], p: &crate::plan::RecodePlan) -> Result<()> {
+    let mut texts = Vec::new();
+    let mut renamed = Vec::new();
+    let result = (|| -> Result<()> {
+        for f in &p.rewrites {
+            let before = fs::read_to_string(&f.path)?;
+            fs::write(&f.path, p.rewrite_text(&before))?;
+            texts.push((&f.path, before));
+        }
+        for (src, name) in p.renames.iter().rev() {
+            let dest = src.with_file_name(name);
+            fs::rename(src, &dest)?;
+            renamed.push((dest, src.clone()));
+        }
+        Ok(())
+    })();
+    if let Err(e) = result {
+        for (now, was) in renamed.iter().rev() {
+            let _ = fs::rename(now, was);
+        }
+        for (f, before) in &texts {
+            let _ = fs::write(f, before);
+        }
+        return Err(e.context("recode failed; changes rolled back"));
+    }
+    index(roots)
+}
+
+/// File outside paths into the tree, moving them (or copying with `copy`).
+/// The names are re-planned under the roots' lock, so codes taken meanwhile
+/// are skipped; returns what was filed. On failure, entries already filed
+/// are put back (or their copies removed).
+pub fn execute_file(
+    roots: &[PathBuf],
+    plans: &[plan::FilePlan],
+    copy: bool,
+) -> Result<Vec<plan::FilePlan>> {
+    let Some(first) = plans.first() else {
+        return Ok(Vec::new());
+    };
+    let (_lock, tree) = fresh(roots)?;
+    let sources: Vec<PathBuf> = plans.iter().map(|f| f.src.clone()).collect();
+    let plans = plan::plan_file_in(&tree, &sources, &first.into_id)?;
+    let into = model::find_node(&tree, &first.into_id);
+    let ev = hooks::Event::new("file", &plans)
+        .paths(None, into.map(|n| Path::new(&n.path)))
+        .code(into.and_then(|n| n.code.as_deref()));
+    hooks::around(ev, || file_all(roots, &plans, copy))?;
+    Ok(plans)
+}
+
+fn file_all(roots: &[PathBuf], plans: &[plan::FilePlan], copy: bool) -> Result<()> {
+    let mut done: Vec<&plan::FilePlan> = Vec::new();
+    let result = (|| -> Result<()> {
+        for f in plans {
+            if copy {
+                copy_tree(&f.src, &f.dest_path, &mut |_| {})?;
+            } else {
+                move_path(&f.src, &f.dest_path, &mut |_| {})?;
+            }
+            done.push(f);
+        }
+        Ok(())
+    })();
+    if let Err(e) = result {
+        for f in done.iter().rev() {
+            let _ = if copy {
+                remove_any(&f.dest_path)
+            } else {
+                move_path(&f.dest_path, &f.src, &mut |_| {})
+            };
+        }
+        return Err(e.context("filing failed; changes rolled back"));
+    }
+    index(roots)
+}
+
 pub fn new_interactive_any(
     roots: &[PathBuf],
     parent_id: &str,
@@ -215,7 +782,85 @@ pub fn new_interactive_any(
     let mut y = String::new();
     io::stdin().read_line(&mut y)?;
     if matches!(y.trim(), "y" | "Y") {
-        execute_create(roots, &p)?
+        let done = execute_create(roots, &p)?;
+        if done.final_name != p.final_name {
+            println!("created {} instead", done.final_name);
+        }
     }
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn copy_across_keeps_metadata_and_removes_source() {
+        let td = tempfile::tempdir().unwrap();
+        let src = td.path().join("31.04_Container");
+        fs::create_dir_all(src.join("sub")).unwrap();
+        fs::write(src.join("sub/a.txt"), b"hello").unwrap();
+        fs::write(src.join("b.bin"), vec![7u8; 10_000]).unwrap();
+        let old = std::time::SystemTime::UNIX_EPOCH + std::time::Duration::from_secs(1_000_000);
+        let times = fs::FileTimes::new().set_modified(old);
+        fs::File::options()
+            .write(true)
+            .open(src.join("b.bin"))
+            .unwrap()
+            .set_times(times)
+            .unwrap();
+        fs::File::open(&src).unwrap().set_times(times).unwrap();
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            fs::set_permissions(src.join("sub/a.txt"), fs::Permissions::from_mode(0o600)).unwrap();
+        }
+
+        let dest = td.path().join("moved");
+        let mut seen = Vec::new();
+        copy_across(&src, &dest, &mut |p| seen.push(p)).unwrap();
+        assert!(!src.exists());
+        assert_eq!(fs::read(dest.join("sub/a.txt")).unwrap(), b"hello");
+        assert_eq!(
+            fs::metadata(dest.join("b.bin"))
+                .unwrap()
+                .modified()
+                .unwrap(),
+            old
+        );
+        assert_eq!(fs::metadata(&dest).unwrap().modified().unwrap(), old);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = fs::metadata(dest.join("sub/a.txt"))
+                .unwrap()
+                .permissions()
+                .mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+        let last = seen.last().unwrap();
+        assert_eq!((last.files, last.total_files), (2, 2));
+        assert_eq!(last.bytes, 10_005);
+
+        // an occupied destination is refused before anything is copied
+        fs::create_dir(&src).unwrap();
+        assert!(copy_across(&src, &dest, &mut |_| {}).is_err());
+        assert!(src.is_dir());
+    }
+
+    #[test]
+    fn leftovers_lists_what_a_failed_removal_left() {
+        let td = tempfile::tempdir().unwrap();
+        let src = td.path().join("31.04_Container");
+        fs::create_dir_all(src.join("sub/empty")).unwrap();
+        fs::write(src.join("sub/a.txt"), b"a").unwrap();
+        assert_eq!(leftovers(&src), "sub/a.txt, sub/empty");
+        for i in 0..6 {
+            fs::write(src.join(format!("f{}", i)), b"").unwrap();
+        }
+        assert_eq!(leftovers(&src), "f0, f1, f2, f3, f4 and 3 more");
+        fs::remove_dir_all(&src).unwrap();
+        fs::create_dir(&src).unwrap();
+        assert_eq!(leftovers(&src), "the empty directory");
+    }
+}
@@ -2,9 +2,12 @@ use crate::model::{self, Node, NodeType, Tree};
 use anyhow::{bail, Result};
 use once_cell::sync::Lazy;
 use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
 use std::path::{Path, PathBuf};
 
-#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
 pub enum PlanKind {
     Dir,
     File,
@@ -14,7 +17,7 @@ pub struct CreateContext<'a> {
     pub tree: &'a Tree,
     pub selected: &'a Node,
 }
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize)]
 pub struct CreatePlan {
     pub kind: PlanKind,
     pub parent_id: String,
@@ -24,15 +27,21 @@ pub struct CreatePlan {
     pub url: Option<String>,
     pub location: Option<String>,
     pub warnings: Vec<String>,
+    /// Template folder whose contents fill the new directory.
+    pub template: Option<PathBuf>,
+    /// The code was the next free one rather than typed, so it may be
+    /// swapped for another if it's taken by the time the plan runs.
+    pub suggested: bool,
 }
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize)]
 pub struct RenamePlan {
     pub id: String,
+    pub src_path: PathBuf,
     pub old_name: String,
     pub new_name: String,
     pub dest_path: PathBuf,
 }
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize)]
 pub struct MovePlan {
     pub id: String,
     pub src_path: PathBuf,
@@ -41,8 +50,53 @@ pub struct MovePlan {
     pub dest_path: PathBuf,
 }
 
+/// A title made safe for a file name under the configured name policy
+/// (`config::NamePolicy`).
 pub fn sanitize_title(s: &str) -> String {
-    s.trim().replace(' ', "_").replace('/', "")
+    crate::config::get().names.sanitize(s)
+}
+/// `sanitize_title` for a title about to be written: refused when the
+/// policy leaves nothing (`???`), as `21.04_` no longer parses as an item.
+pub fn clean_title(s: &str) -> Result<String> {
+    let title = sanitize_title(s);
+    if title.is_empty() {
+        bail!("no title left in {}", s);
+    }
+    Ok(title)
+}
+/// A full entry name with its title sanitised; the code and extension stay.
+pub fn sanitize_name(name: &str) -> Result<String> {
+    if let Some((c, t, ext)) = model::parse_item(name) {
+        let ext = ext.map(|e| format!(".{}", e)).unwrap_or_default();
+        return Ok(format!("{}_{}{}", c, clean_title(&t)?, ext));
+    }
+    if let Some((c, t)) = model::parse_range(name).or_else(|| model::parse_category(name)) {
+        return Ok(format!("{}_{}", c, clean_title(&t)?));
+    }
+    Ok(match name.rsplit_once('.') {
+        Some((stem, ext)) if !stem.is_empty() => format!("{}.{}", clean_title(stem)?, ext),
+        _ => clean_title(name)?,
+    })
+}
+
+/// Every name in the tree (roots excluded) that breaks `policy`, with what's
+/// wrong with it, in tree order.
+pub fn check_names(tree: &Tree, policy: &crate::config::NamePolicy) -> Vec<(PathBuf, Vec<String>)> {
+    fn walk(n: &Node, policy: &crate::config::NamePolicy, out: &mut Vec<(PathBuf, Vec<String>)>) {
+        for c in &n.children {
+            let p = PathBuf::from(&c.path);
+            let problems = policy.problems(&p.file_name().unwrap().to_string_lossy());
+            if !problems.is_empty() {
+                out.push((p, problems));
+            }
+            walk(c, policy, out);
+        }
+    }
+    let mut out = Vec::new();
+    for r in &tree.roots {
+        walk(r, policy, &mut out);
+    }
+    out
 }
 fn display(n: &Node) -> String {
     n.code
@@ -109,6 +163,7 @@ pub fn parse_new_input_forced(
         warnings.push("title derived from URL".into());
     }
     let mut parent = anchor(ctx.tree, ctx.selected)?;
+    let mut suggested = false;
     let (code, mut title) = if let Some(c) = RANGE.captures(&rest) {
         (Some(c[1].to_string()), c[2].to_string())
     } else if let Some(c) = ITEM.captures(&rest) {
@@ -126,6 +181,7 @@ pub fn parse_new_input_forced(
         (Some(c[1].to_string()), c[2].to_string())
     } else {
         let c = model::suggest_child_code(ctx.tree, parent)?;
+        suggested = !c.is_empty();
         (if c.is_empty() { None } else { Some(c) }, rest)
     };
     let inferred = if url.is_some() {
@@ -156,8 +212,8 @@ pub fn parse_new_input_forced(
         PlanKind::Link => {}
     }
     let base = match &code {
-        Some(c) => format!("{}_{}", c, sanitize_title(&title)),
-        None => sanitize_title(&title),
+        Some(c) => format!("{}_{}", c, clean_title(&title)?),
+        None => clean_title(&title)?,
     };
     let final_name = match kind {
         PlanKind::File => format!("{}.{}", base, ext.unwrap()),
@@ -178,6 +234,16 @@ pub fn parse_new_input_forced(
     if dest_path.exists() {
         bail!("destination already exists: {}", dest_path.display())
     }
+    let template = match kind {
+        PlanKind::Dir => root_of(ctx.tree, &parent.path).and_then(|r| {
+            crate::template::default_for(
+                Path::new(&r.path),
+                Path::new(&parent.path),
+                code.as_deref(),
+            )
+        }),
+        _ => None,
+    };
     Ok(CreatePlan {
         kind,
         parent_id: parent.id.clone(),
@@ -187,13 +253,106 @@ pub fn parse_new_input_forced(
         url,
         location: None,
         warnings,
+        template,
+        suggested,
     })
 }
+
+/// Check a create planned against an older scan against `fresh`. A suggested
+/// code that has been taken since is replaced by the next free one (noted in
+/// the warnings); anything else that changed is an error.
+pub fn revalidate_create(fresh: &Tree, p: &CreatePlan) -> Result<CreatePlan> {
+    let parent = model::find_node(fresh, &p.parent_id)
+        .filter(|n| Some(Path::new(&n.path)) == p.dest_path.parent())
+        .ok_or_else(|| stale(&p.parent_display))?;
+    let mut p = p.clone();
+    if let Some(code) = name_code(&p.final_name) {
+        let warned = p
+            .warnings
+            .contains(&format!("code {} already in use", code));
+        if !warned && code_taken(fresh, &code, None) {
+            if !p.suggested {
+                bail!(
+                    "code {} was taken since it was planned; rescan and try again",
+                    code
+                );
+            }
+            let next = model::suggest_child_code(fresh, parent)?;
+            p.final_name = format!("{}{}", next, &p.final_name[code.len()..]);
+            p.dest_path = p.dest_path.with_file_name(&p.final_name);
+            p.warnings
+                .push(format!("{} was taken meanwhile; using {}", code, next));
+        }
+    }
+    if p.dest_path.exists() {
+        bail!("destination already exists: {}", p.dest_path.display());
+    }
+    Ok(p)
+}
+
+/// Check that node `id` is still at `src`, that `dest` is free and that
+/// `new_code` (if any) isn't used by another node in the tree since the
+/// plan was made.
+pub fn revalidate(
+    fresh: &Tree,
+    id: &str,
+    src: &Path,
+    dest: &Path,
+    new_code: Option<&str>,
+) -> Result<()> {
+    let n = model::find_node(fresh, id)
+        .filter(|n| Path::new(&n.path) == src)
+        .ok_or_else(|| stale(&src.display().to_string()))?;
+    if dest != src && dest.exists() {
+        bail!("destination already exists: {}", dest.display());
+    }
+    if let Some(code) = new_code.filter(|c| n.code.as_deref() != Some(*c)) {
+        if code_taken(fresh, code, Some(id)) {
+            bail!(
+                "code {} was taken since it was planned; rescan and try again",
+                code
+            );
+        }
+    }
+    Ok(())
+}
+
+fn stale(what: &str) -> anyhow::Error {
+    anyhow::anyhow!(
+        "{} changed since it was planned; rescan and try again",
+        what
+    )
+}
+
+/// The JD code at the front of a file or folder name.
+pub fn name_code(name: &str) -> Option<String> {
+    model::parse_range(name)
+        .or_else(|| model::parse_category(name))
+        .map(|(c, _)| c)
+        .or_else(|| model::parse_item(name).map(|(c, _, _)| c))
+}
+
+/// Whether some node other than `except` (and what's inside it) has `code`.
+fn code_taken(tree: &Tree, code: &str, except: Option<&str>) -> bool {
+    fn walk(n: &Node, code: &str, except: Option<&str>) -> bool {
+        if Some(n.id.as_str()) == except {
+            return false;
+        }
+        n.code.as_deref() == Some(code) || n.children.iter().any(|c| walk(c, code, except))
+    }
+    tree.roots.iter().any(|r| walk(r, code, except))
+}
+
+fn root_of<'a>(tree: &'a Tree, path: &str) -> Option<&'a Node> {
+    tree.roots
+        .iter()
+        .find(|r| format!("{}/", path).starts_with(&format!("{}/", r.path)))
+}
 pub fn plan_rename(tree: &Tree, id: &str, new_title: &str) -> Result<RenamePlan> {
     let n = model::find_node(tree, id).ok_or_else(|| anyhow::anyhow!("not found"))?;
     let p = PathBuf::from(&n.path);
     let old = p.file_name().unwrap().to_string_lossy().to_string();
-    let title = sanitize_title(new_title);
+    let title = clean_title(new_title)?;
     let new = if let Some((c, _, e)) = model::parse_item(&old) {
         e.map(|e| format!("{}_{}.{}", c, title, e))
             .unwrap_or_else(|| format!("{}_{}", c, title))
@@ -210,6 +369,7 @@ pub fn plan_rename(tree: &Tree, id: &str, new_title: &str) -> Result<RenamePlan>
     };
     Ok(RenamePlan {
         id: id.into(),
+        src_path: p.clone(),
         old_name: old,
         new_name: new,
         dest_path: dest,
@@ -224,17 +384,14 @@ pub fn plan_move(tree: &Tree, id: &str, new_parent_id: &str) -> Result<MovePlan>
     if id == new_parent_id || pp.starts_with(&src) {
         bail!("cannot move into self or descendant")
     }
-    let root_for = |p: &PathBuf| tree.roots.iter().position(|r| p.starts_with(&r.path));
-    if root_for(&src) != root_for(&pp) {
-        bail!("move across roots is not allowed")
-    }
     let mut name = src.file_name().unwrap().to_string_lossy().to_string();
     if matches!(parent.node_type, NodeType::Category) {
         if let Some((_, t, e)) = model::parse_item(&name) {
             let c = model::suggest_next_code(tree, parent.code.as_deref().unwrap())?;
+            let t = clean_title(&t)?;
             name = e
-                .map(|e| format!("{}_{}.{}", c, sanitize_title(&t), e))
-                .unwrap_or_else(|| format!("{}_{}", c, sanitize_title(&t)));
+                .map(|e| format!("{}_{}.{}", c, t, e))
+                .unwrap_or_else(|| format!("{}_{}", c, t));
         }
     }
     let dest = pp.join(&name);
@@ -249,7 +406,7 @@ pub fn plan_move(tree: &Tree, id: &str, new_parent_id: &str) -> Result<MovePlan>
         dest_path: dest,
     })
 }
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize)]
 pub struct RenumberPlan {
     pub id: String,
     pub old_code: String,
@@ -285,6 +442,56 @@ pub fn plan_renumber(tree: &Tree, id: &str) -> Result<RenumberPlan> {
             parent.title
         );
     }
+    renumber_to(n, old_code, new_code)
+}
+
+/// Plan giving an item a chosen code under its current parent (`21.04 →
+/// 21.10`). Same cascade as a renumber; ranges and categories go through
+/// `plan_recode`.
+pub fn plan_recode_item(tree: &Tree, id: &str, new_code: &str) -> Result<RenumberPlan> {
+    let n = model::find_node(tree, id).ok_or_else(|| anyhow::anyhow!("not found"))?;
+    let old_code = n
+        .code
+        .clone()
+        .ok_or_else(|| anyhow::anyhow!("node has no JD code"))?;
+    if matches!(n.node_type, NodeType::Range | NodeType::Category) {
+        bail!("{} is not an item — use a range/category recode", old_code);
+    }
+    let new_code = new_code.trim().to_string();
+    if new_code == old_code {
+        bail!("{} already has code {}", n.title, old_code);
+    }
+    let parent = model::find_parent_id(tree, id)
+        .and_then(|p| model::find_node(tree, &p))
+        .ok_or_else(|| anyhow::anyhow!("cannot recode a root"))?;
+    let parent_code = parent.code.as_deref().unwrap_or("");
+    let parsed = model::parse_item(&format!("{}_x", new_code)).map(|(c, _, _)| c);
+    let under_parent = new_code
+        .rsplit_once('.')
+        .is_some_and(|(head, _)| head == parent_code);
+    if parsed.as_deref() != Some(new_code.as_str()) || !under_parent {
+        bail!(
+            "{} is not an item code under {} (expected {}.NN)",
+            new_code,
+            parent.title,
+            parent_code
+        );
+    }
+    let root =
+        root_of(tree, &n.path).ok_or_else(|| anyhow::anyhow!("node is outside the roots"))?;
+    fn user<'a>(n: &'a Node, code: &str) -> Option<&'a Node> {
+        if n.code.as_deref() == Some(code) {
+            return Some(n);
+        }
+        n.children.iter().find_map(|c| user(c, code))
+    }
+    if let Some(other) = user(root, &new_code) {
+        bail!("{} is already in use by {}", new_code, other.path);
+    }
+    renumber_to(n, old_code, new_code)
+}
+
+fn renumber_to(n: &Node, old_code: String, new_code: String) -> Result<RenumberPlan> {
     let src_path = PathBuf::from(&n.path);
     let name = src_path
         .file_name()
@@ -296,21 +503,25 @@ pub fn plan_renumber(tree: &Tree, id: &str) -> Result<RenumberPlan> {
     if dest_path.exists() {
         bail!("destination already exists: {}", dest_path.display());
     }
-    fn count_embedded(n: &Node, prefix: &str) -> usize {
+    fn count_embedded(n: &Node, code: &str) -> usize {
         n.children
             .iter()
             .map(|c| {
                 let hit = Path::new(&c.path)
                     .file_name()
-                    .map(|f| f.to_string_lossy().starts_with(prefix))
+                    .and_then(|f| {
+                        let f = f.to_string_lossy();
+                        let rest = f.strip_prefix(code)?;
+                        Some(rest.starts_with('.') || rest.starts_with('_'))
+                    })
                     .unwrap_or(false) as usize;
-                hit + count_embedded(c, prefix)
+                hit + count_embedded(c, code)
             })
             .sum()
     }
-    let child_renames = count_embedded(n, &format!("{}.", old_code));
+    let child_renames = count_embedded(n, &old_code);
     Ok(RenumberPlan {
-        id: id.into(),
+        id: n.id.clone(),
         old_code,
         new_code,
         src_path,
@@ -320,7 +531,390 @@ pub fn plan_renumber(tree: &Tree, id: &str) -> Result<RenumberPlan> {
     })
 }
 
-#[derive(Clone, Debug)]
+/// How a recode maps codes: a prefix swap (a category and every item under
+/// it, `42 → 43` makes `42.07.01 → 43.07.01`) or a range shift (categories
+/// move by the same offset as the range, `40-49 → 50-59` makes
+/// `42.07 → 52.07`).
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+#[serde(tag = "kind", rename_all = "lowercase")]
+pub enum CodeShift {
+    Prefix {
+        old: String,
+        new: String,
+    },
+    Range {
+        old: String,
+        new: String,
+        old_span: (u32, u32),
+        new_start: u32,
+    },
+}
+
+impl CodeShift {
+    /// The new code for `code`, or None when the shift leaves it alone.
+    pub fn map(&self, code: &str) -> Option<String> {
+        match self {
+            CodeShift::Prefix { old, new } => {
+                if code == old {
+                    Some(new.clone())
+                } else {
+                    code.strip_prefix(&format!("{}.", old))
+                        .map(|rest| format!("{}.{}", new, rest))
+                }
+            }
+            CodeShift::Range {
+                old,
+                new,
+                old_span,
+                new_start,
+            } => {
+                if code == old {
+                    return Some(new.clone());
+                }
+                let (head, rest) = (code.get(..2)?, &code[2..]);
+                let cat: u32 = head.parse().ok()?;
+                let is_cat_or_item = rest.is_empty() || rest.starts_with('.');
+                (is_cat_or_item && (old_span.0..=old_span.1).contains(&cat))
+                    .then(|| format!("{:02}{}", cat - old_span.0 + new_start, rest))
+            }
+        }
+    }
+}
+
+/// Rewrite the range and item codes in `text` that are keys of `codes`.
+/// Anything else that looks like one stays: a code that isn't in the
+/// recoded subtree, bare two-digit categories ("42" in prose is rarely a JD
+/// number), and a token running on into more digits (`31.12.2024`, `1.31.5`).
+fn rewrite_codes(text: &str, codes: &BTreeMap<String, String>) -> String {
+    static CODE: Lazy<Regex> =
+        Lazy::new(|| Regex::new(r"\b(\d{2}-\d{2}|\d{2}\.\d{2,4}(?:\.\d{2})*)\b").unwrap());
+    let runs_on = |s: &str| {
+        let mut c = s.chars();
+        matches!(c.next(), Some('.' | '-')) && c.next().is_some_and(|d| d.is_ascii_digit())
+    };
+    CODE.replace_all(text, |c: &regex::Captures| {
+        let m = c.get(0).unwrap();
+        let before: String = text[..m.start()].chars().rev().take(2).collect();
+        match codes.get(m.as_str()) {
+            Some(new) if !runs_on(&text[m.end()..]) && !runs_on(&before) => new.clone(),
+            _ => m.as_str().to_string(),
+        }
+    })
+    .into_owned()
+}
+
+/// A `.jdmeta` (or, on request, `.jdmeta.md`) file a recode rewrites.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct NoteRewrite {
+    pub path: PathBuf,
+    /// (line number from 1, before, after) for each changed line.
+    pub lines: Vec<(usize, String, String)>,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct RecodePlan {
+    pub id: String,
+    pub old_code: String,
+    pub new_code: String,
+    pub shift: CodeShift,
+    pub src_path: PathBuf,
+    pub dest_path: PathBuf,
+    /// (current path, new file name) for the node and every descendant whose
+    /// name carries a shifted code, parents before children.
+    pub renames: Vec<(PathBuf, String)>,
+    /// Old → new for every code in the recoded subtree.
+    pub codes: BTreeMap<String, String>,
+    /// Whether `.jdmeta.md` notes are rewritten as well as `.jdmeta`.
+    pub rewrite_notes: bool,
+    /// Files anywhere in the root that mention one of `codes`.
+    pub rewrites: Vec<NoteRewrite>,
+}
+
+impl RecodePlan {
+    /// `text` with the subtree's codes swapped for their new ones.
+    pub fn rewrite_text(&self, text: &str) -> String {
+        rewrite_codes(text, &self.codes)
+    }
+}
+
+/// Plan giving a range or category an explicit new code, cascading into
+/// every descendant (`42.07 → 43.07`, stamped files included) and into
+/// `.jdmeta` references to those codes across the root; `.jdmeta.md` notes
+/// are prose, where dates and prices look like codes, so only with
+/// `rewrite_notes`. Refused when any resulting code is already used outside
+/// the recoded subtree.
+pub fn plan_recode(
+    tree: &Tree,
+    id: &str,
+    new_code: &str,
+    rewrite_notes: bool,
+) -> Result<RecodePlan> {
+    static CAT: Lazy<Regex> = Lazy::new(|| Regex::new(r"^\d{2}$").unwrap());
+    static RANGE: Lazy<Regex> = Lazy::new(|| Regex::new(r"^(\d{2})-(\d{2})$").unwrap());
+    let n = model::find_node(tree, id).ok_or_else(|| anyhow::anyhow!("not found"))?;
+    let old_code = n
+        .code
+        .clone()
+        .ok_or_else(|| anyhow::anyhow!("node has no JD code"))?;
+    let new_code = new_code.trim().to_string();
+    if new_code == old_code {
+        bail!("{} already has code {}", n.title, old_code);
+    }
+    let root =
+        root_of(tree, &n.path).ok_or_else(|| anyhow::anyhow!("node is outside the roots"))?;
+    let span = |c: &str| -> Option<(u32, u32)> {
+        let m = RANGE.captures(c)?;
+        Some((m[1].parse().ok()?, m[2].parse().ok()?))
+    };
+    let shift = match n.node_type {
+        NodeType::Category => {
+            if !CAT.is_match(&new_code) {
+                bail!("a category code is two digits, got {}", new_code);
+            }
+            let parent = model::find_parent_id(tree, id).and_then(|p| model::find_node(tree, &p));
+            if let Some((lo, hi)) = parent
+                .filter(|p| p.node_type == NodeType::Range)
+                .and_then(|p| span(p.code.as_deref()?))
+            {
+                let c: u32 = new_code.parse()?;
+                if !(lo..=hi).contains(&c) {
+                    bail!(
+                        "{} is outside its range {:02}-{:02} — move the category instead",
+                        new_code,
+                        lo,
+                        hi
+                    );
+                }
+            }
+            CodeShift::Prefix {
+                old: old_code.clone(),
+                new: new_code.clone(),
+            }
+        }
+        NodeType::Range => {
+            let old_span = span(&old_code).ok_or_else(|| anyhow::anyhow!("bad range code"))?;
+            let new_span = span(&new_code).filter(|(a, b)| a <= b).ok_or_else(|| {
+                anyhow::anyhow!("a range code looks like 40-49, got {}", new_code)
+            })?;
+            fn ranges<'a>(n: &'a Node, out: &mut Vec<&'a Node>) {
+                if n.node_type == NodeType::Range {
+                    out.push(n);
+                }
+                n.children.iter().for_each(|c| ranges(c, out));
+            }
+            let mut others = Vec::new();
+            ranges(root, &mut others);
+            for o in others.into_iter().filter(|o| o.id != n.id) {
+                if let Some((a, b)) = o.code.as_deref().and_then(span) {
+                    if a <= new_span.1 && new_span.0 <= b {
+                        bail!("{} overlaps range {}", new_code, o.code.as_deref().unwrap());
+                    }
+                }
+            }
+            CodeShift::Range {
+                old: old_code.clone(),
+                new: new_code.clone(),
+                old_span,
+                new_start: new_span.0,
+            }
+        }
+        _ => bail!("only ranges and categories can be recoded"),
+    };
+
+    // Every node in the subtree whose name starts with a shifted code.
+    let mut renames = Vec::new();
+    let mut inside = BTreeSet::new();
+    let mut codes = BTreeMap::new();
+    fn collect(
+        n: &Node,
+        shift: &CodeShift,
+        renames: &mut Vec<(PathBuf, String)>,
+        inside: &mut BTreeSet<String>,
+        codes: &mut BTreeMap<String, String>,
+    ) {
+        inside.insert(n.id.clone());
+        let path = PathBuf::from(&n.path);
+        let name = path.file_name().unwrap().to_string_lossy().to_string();
+        if let Some((code, new)) = n.code.as_ref().and_then(|c| Some((c, shift.map(c)?))) {
+            if let Some(rest) = name.strip_prefix(code.as_str()) {
+                renames.push((path.clone(), format!("{}{}", new, rest)));
+            }
+            codes.insert(code.clone(), new);
+        }
+        for c in &n.children {
+            collect(c, shift, renames, inside, codes);
+        }
+    }
+    collect(n, &shift, &mut renames, &mut inside, &mut codes);
+    if let CodeShift::Range { new, .. } = &shift {
+        let (lo, hi) = span(new).unwrap();
+        for c in n
+            .children
+            .iter()
+            .filter(|c| c.node_type == NodeType::Category)
+        {
+            let moved: u32 = shift.map(c.code.as_deref().unwrap()).unwrap().parse()?;
+            if !(lo..=hi).contains(&moved) {
+                bail!(
+                    "category {} would land outside {}",
+                    c.code.as_deref().unwrap(),
+                    new
+                );
+            }
+        }
+    }
+
+    // Refuse codes used anywhere else in the root; collect notes to rewrite.
+    let mut taken = BTreeSet::new();
+    let mut rewrites = Vec::new();
+    let files: &[&str] = if rewrite_notes {
+        &[crate::meta::META_FILE, crate::meta::NOTES_FILE]
+    } else {
+        &[crate::meta::META_FILE]
+    };
+    fn scan(
+        n: &Node,
+        inside: &BTreeSet<String>,
+        codes: &BTreeMap<String, String>,
+        files: &[&str],
+        taken: &mut BTreeSet<String>,
+        rewrites: &mut Vec<NoteRewrite>,
+    ) {
+        if !inside.contains(&n.id) {
+            if let Some(c) = n.code.as_ref().filter(|c| codes.values().any(|v| v == *c)) {
+                taken.insert(c.clone());
+            }
+        }
+        for f in files {
+            let path = Path::new(&n.path).join(f);
+            let Ok(s) = std::fs::read_to_string(&path) else {
+                continue;
+            };
+            let lines: Vec<(usize, String, String)> = s
+                .lines()
+                .enumerate()
+                .filter_map(|(i, l)| {
+                    let new = rewrite_codes(l, codes);
+                    (new != l).then(|| (i + 1, l.to_string(), new))
+                })
+                .collect();
+            if !lines.is_empty() {
+                rewrites.push(NoteRewrite { path, lines });
+            }
+        }
+        for c in &n.children {
+            scan(c, inside, codes, files, taken, rewrites);
+        }
+    }
+    scan(root, &inside, &codes, files, &mut taken, &mut rewrites);
+    if !taken.is_empty() {
+        bail!(
+            "target code(s) already in use: {}",
+            taken.into_iter().collect::<Vec<_>>().join(", ")
+        );
+    }
+    for (src, name) in &renames {
+        let dest = src.with_file_name(name);
+        if dest.exists() {
+            bail!("destination already exists: {}", dest.display());
+        }
+    }
+    let src_path = PathBuf::from(&n.path);
+    let dest_path = src_path.with_file_name(&renames[0].1);
+    Ok(RecodePlan {
+        id: id.into(),
+        old_code,
+        new_code,
+        shift,
+        src_path,
+        dest_path,
+        renames,
+        codes,
+        rewrite_notes,
+        rewrites,
+    })
+}
+
+/// One outside file or folder on its way into the tree.
+#[derive(Clone, Debug, Serialize)]
+pub struct FilePlan {
+    pub into_id: String,
+    pub src: PathBuf,
+    pub final_name: String,
+    pub dest_path: PathBuf,
+}
+
+/// Plan filing outside paths into a category (each gets the next free item
+/// code) or an item folder (each is stamped with the item's own code). A
+/// code already at the front of a source name is replaced, the rest becomes
+/// the sanitised title.
+pub fn plan_file_in(tree: &Tree, sources: &[PathBuf], into_id: &str) -> Result<Vec<FilePlan>> {
+    let target = model::find_node(tree, into_id).ok_or_else(|| anyhow::anyhow!("not found"))?;
+    let code = match (&target.node_type, &target.code) {
+        (NodeType::Category | NodeType::ItemDir, Some(c)) => c.clone(),
+        _ => bail!(
+            "file into a category or an item folder, not {}",
+            display(target)
+        ),
+    };
+    let dir = PathBuf::from(&target.path);
+    let mut used: BTreeSet<String> = model::all_codes(tree).into_iter().collect();
+    let mut claimed: BTreeSet<PathBuf> = BTreeSet::new();
+    let mut out = Vec::new();
+    for src in sources {
+        let meta =
+            std::fs::metadata(src).map_err(|e| anyhow::anyhow!("{}: {}", src.display(), e))?;
+        if dir.starts_with(src) {
+            bail!("cannot file {} into itself", src.display());
+        }
+        let name = src
+            .file_name()
+            .ok_or_else(|| anyhow::anyhow!("no file name: {}", src.display()))?
+            .to_string_lossy()
+            .to_string();
+        let (stem, ext) = match name.rsplit_once('.') {
+            Some((s, e)) if meta.is_file() && !s.is_empty() => (s.to_string(), Some(e)),
+            _ => (name.clone(), None),
+        };
+        // "21.04_Old_title" or "21.04 Old title": keep only the title
+        let title = model::parse_item(&stem)
+            .map(|(_, t, _)| t)
+            .or_else(|| model::parse_item(&stem.replacen(' ', "_", 1)).map(|(_, t, _)| t))
+            .unwrap_or(stem);
+        let title = sanitize_title(&title);
+        if title.is_empty() {
+            bail!("no title left in {}", name);
+        }
+        let item_code = if target.node_type == NodeType::ItemDir {
+            code.clone()
+        } else {
+            let next = (1..=99u32)
+                .map(|i| format!("{}.{:02}", code, i))
+                .find(|c| !used.contains(c))
+                .ok_or_else(|| anyhow::anyhow!("no free item code under {}", code))?;
+            used.insert(next.clone());
+            next
+        };
+        let final_name = match ext {
+            Some(e) => format!("{}_{}.{}", item_code, title, e),
+            None => format!("{}_{}", item_code, title),
+        };
+        let dest_path = dir.join(&final_name);
+        if dest_path.exists() || !claimed.insert(dest_path.clone()) {
+            bail!("destination already exists: {}", dest_path.display());
+        }
+        out.push(FilePlan {
+            into_id: into_id.to_string(),
+            src: src.clone(),
+            final_name,
+            dest_path,
+        });
+    }
+    Ok(out)
+}
+
+#[derive(Clone, Debug, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
 pub enum MergeAction {
     /// Source is a pure pointer: its location/URL becomes a .jdmeta entry on
     /// the target folder and the source file is trashed.
@@ -330,7 +924,7 @@ pub enum MergeAction {
     MoveInside,
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize)]
 pub struct MergePlan {
     pub source_id: String,
     pub source_name: String,
@@ -438,6 +1032,279 @@ pub fn merge_summary(p: &MergePlan) -> String {
     }
 }
 
+/// A file item turned into a folder item around it (wrap), or a folder item
+/// holding a single file collapsed back into that file (unwrap).
+#[derive(Clone, Debug, Serialize)]
+pub struct WrapPlan {
+    pub id: String,
+    pub unwrap: bool,
+    /// The file that moves: the item itself (wrap) or the one inside.
+    pub file_path: PathBuf,
+    /// The folder that's created (wrap) or removed (unwrap).
+    pub dir_path: PathBuf,
+    /// Where the file ends up.
+    pub dest_path: PathBuf,
+}
+
+/// Plan wrapping a file item `21.04_Draft.md` into `21.04_Draft/`, the file
+/// keeping its stamped name inside.
+pub fn plan_wrap(tree: &Tree, id: &str) -> Result<WrapPlan> {
+    let n = model::find_node(tree, id).ok_or_else(|| anyhow::anyhow!("not found"))?;
+    if !matches!(n.node_type, NodeType::File | NodeType::Link) {
+        bail!("only file items can be wrapped, not {}", display(n));
+    }
+    let file = PathBuf::from(&n.path);
+    let name = file.file_name().unwrap().to_string_lossy().to_string();
+    let Some((_, _, ext)) = model::parse_item(&name) else {
+        bail!("{} has no item code", name);
+    };
+    let dir_name = match &ext {
+        Some(e) => &name[..name.len() - e.len() - 1],
+        None => name.as_str(),
+    };
+    let dir_path = file.with_file_name(dir_name);
+    if dir_path != file && dir_path.exists() {
+        bail!("destination already exists: {}", dir_path.display());
+    }
+    Ok(WrapPlan {
+        id: n.id.clone(),
+        unwrap: false,
+        dest_path: dir_path.join(&name),
+        file_path: file,
+        dir_path,
+    })
+}
+
+/// Plan collapsing a folder item that holds exactly one file (besides its
+/// `.jdmeta`/`.jdmeta.md`) into a file item named after the folder.
+pub fn plan_unwrap(tree: &Tree, id: &str) -> Result<WrapPlan> {
+    let n = model::find_node(tree, id).ok_or_else(|| anyhow::anyhow!("not found"))?;
+    if n.node_type != NodeType::ItemDir {
+        bail!("only folder items can be unwrapped, not {}", display(n));
+    }
+    let dir = PathBuf::from(&n.path);
+    // everything counts, ignored entries too (.git, *.bak): the folder goes
+    let mut inside: Vec<PathBuf> = std::fs::read_dir(&dir)?
+        .filter_map(|e| e.ok())
+        .map(|e| e.path())
+        .filter(|p| {
+            let name = p.file_name().unwrap().to_string_lossy();
+            name != crate::meta::META_FILE && name != crate::meta::NOTES_FILE
+        })
+        .collect();
+    inside.sort();
+    let file = match inside.as_slice() {
+        [f] if f.is_file() => f.clone(),
+        [_] => bail!("{} holds a folder, not a file", display(n)),
+        _ => bail!(
+            "{} holds {} entries ({}); unwrap needs exactly one file",
+            display(n),
+            inside.len(),
+            inside
+                .iter()
+                .map(|p| p.file_name().unwrap().to_string_lossy())
+                .collect::<Vec<_>>()
+                .join(", ")
+        ),
+    };
+    let inner = file.file_name().unwrap().to_string_lossy().to_string();
+    // a stamped name knows its extension ("tar.gz"); otherwise the last one
+    let ext = match model::parse_item(&inner) {
+        Some((_, _, ext)) => ext,
+        None => inner
+            .rsplit_once('.')
+            .filter(|(s, _)| !s.is_empty())
+            .map(|(_, e)| e.to_string()),
+    };
+    let dir_name = dir.file_name().unwrap().to_string_lossy();
+    let dest_path = dir.with_file_name(match ext {
+        Some(e) => format!("{}.{}", dir_name, e),
+        None => dir_name.to_string(),
+    });
+    if dest_path != dir && dest_path.exists() {
+        bail!("destination already exists: {}", dest_path.display());
+    }
+    Ok(WrapPlan {
+        id: n.id.clone(),
+        unwrap: true,
+        file_path: file,
+        dir_path: dir,
+        dest_path,
+    })
+}
+
+pub fn wrap_summary(p: &WrapPlan) -> String {
+    let name = |p: &Path| p.file_name().unwrap().to_string_lossy().to_string();
+    if p.unwrap {
+        let keeps = [crate::meta::META_FILE, crate::meta::NOTES_FILE]
+            .iter()
+            .any(|f| p.dir_path.join(f).exists());
+        format!(
+            "will unwrap {}/ into {}{}",
+            name(&p.dir_path),
+            name(&p.dest_path),
+            if keeps {
+                " (.jdmeta kept beside it)"
+            } else {
+                ""
+            }
+        )
+    } else {
+        let brings = crate::meta::sidecars(&p.file_path)
+            .iter()
+            .any(|s| s.exists());
+        format!(
+            "will wrap {} into {}/{}",
+            name(&p.file_path),
+            name(&p.dir_path),
+            if brings {
+                " (its .jdmeta moves in)"
+            } else {
+                ""
+            }
+        )
+    }
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct ArchivePlan {
+    pub id: String,
+    pub unarchive: bool,
+    pub old_code: String,
+    pub new_code: String,
+    pub src_path: PathBuf,
+    pub dest_parent_id: String,
+    pub dest_path: PathBuf,
+    pub warnings: Vec<String>,
+}
+
+/// Plan moving a finished item into the archive category `to` (or a
+/// category into the archive range) under the next free code there. `to`
+/// is a code, looked up in the node's own root first, or an id.
+pub fn plan_archive(tree: &Tree, id: &str, to: &str) -> Result<ArchivePlan> {
+    let n = model::find_node(tree, id).ok_or_else(|| anyhow::anyhow!("not found"))?;
+    let old_code = n
+        .code
+        .clone()
+        .ok_or_else(|| anyhow::anyhow!("node has no JD code"))?;
+    if let Some(from) = &n.archived_from {
+        bail!("{} is already archived (from {})", display(n), from);
+    }
+    let target = root_of(tree, &n.path)
+        .and_then(|r| nearest(r, &|c| c.code.as_deref() == Some(to)))
+        .or_else(|| model::find_by_code(tree, to))
+        .or_else(|| model::find_node(tree, to))
+        .ok_or_else(|| anyhow::anyhow!("archive {} not found", to))?;
+    let fits = match n.node_type {
+        NodeType::ItemDir | NodeType::File | NodeType::Link => {
+            target.node_type == NodeType::Category
+        }
+        NodeType::Category => target.node_type == NodeType::Range,
+        _ => false,
+    };
+    if !fits {
+        bail!(
+            "archive takes items into a category and categories into a range, not {} into {}",
+            display(n),
+            display(target)
+        );
+    }
+    let src = PathBuf::from(&n.path);
+    if src.parent() == Some(Path::new(&target.path)) {
+        bail!("{} is already in {}", display(n), display(target));
+    }
+    let new_code = model::suggest_child_code(tree, target)?;
+    recoded(n, old_code, new_code, target, false, Vec::new())
+}
+
+/// Plan moving an archived node back under the category (or range) of its
+/// former code, taking that code again if it is still free.
+pub fn plan_unarchive(tree: &Tree, id: &str) -> Result<ArchivePlan> {
+    let n = model::find_node(tree, id).ok_or_else(|| anyhow::anyhow!("not found"))?;
+    let (Some(code), Some(from)) = (n.code.clone(), n.archived_from.clone()) else {
+        bail!("{} was not archived", display(n));
+    };
+    let home = |c: &Node| match from.split_once('.') {
+        Some((cat, _)) => c.node_type == NodeType::Category && c.code.as_deref() == Some(cat),
+        None => {
+            c.node_type == NodeType::Range
+                && c.code
+                    .as_deref()
+                    .and_then(|r| r.split_once('-'))
+                    .is_some_and(|(a, b)| a <= from.as_str() && from.as_str() <= b)
+        }
+    };
+    let parent = root_of(tree, &n.path)
+        .and_then(|r| nearest(r, &home))
+        .or_else(|| tree.roots.iter().find_map(|r| nearest(r, &home)))
+        .ok_or_else(|| {
+            anyhow::anyhow!(
+                "nothing left to hold {} — recreate its parent or move it by hand",
+                from
+            )
+        })?;
+    let mut warnings = Vec::new();
+    let new_code = if code_taken(tree, &from, Some(id)) {
+        let next = model::suggest_child_code(tree, parent)?;
+        warnings.push(format!("{} is taken now; using {}", from, next));
+        next
+    } else {
+        from
+    };
+    recoded(n, code, new_code, parent, true, warnings)
+}
+
+fn nearest<'a>(n: &'a Node, want: &dyn Fn(&Node) -> bool) -> Option<&'a Node> {
+    if want(n) {
+        return Some(n);
+    }
+    n.children.iter().find_map(|c| nearest(c, want))
+}
+
+fn recoded(
+    n: &Node,
+    old_code: String,
+    new_code: String,
+    parent: &Node,
+    unarchive: bool,
+    warnings: Vec<String>,
+) -> Result<ArchivePlan> {
+    let src = PathBuf::from(&n.path);
+    let name = src.file_name().unwrap().to_string_lossy().to_string();
+    let rest = name
+        .strip_prefix(old_code.as_str())
+        .ok_or_else(|| anyhow::anyhow!("{} does not start with its code", name))?;
+    let dest_path = Path::new(&parent.path).join(format!("{}{}", new_code, rest));
+    if dest_path.exists() {
+        bail!("destination already exists: {}", dest_path.display());
+    }
+    Ok(ArchivePlan {
+        id: n.id.clone(),
+        unarchive,
+        old_code,
+        new_code,
+        src_path: src,
+        dest_parent_id: parent.id.clone(),
+        dest_path,
+        warnings,
+    })
+}
+
+pub fn archive_summary(p: &ArchivePlan) -> String {
+    let parent = p.dest_path.parent().and_then(|d| d.file_name());
+    let mut s = format!(
+        "will {} {} as {} in {}",
+        if p.unarchive { "unarchive" } else { "archive" },
+        p.src_path.file_name().unwrap_or_default().to_string_lossy(),
+        p.new_code,
+        parent.unwrap_or_default().to_string_lossy()
+    );
+    for w in &p.warnings {
+        s.push_str(&format!(" ⚠ {}", w));
+    }
+    s
+}
+
 pub fn renumber_summary(p: &RenumberPlan) -> String {
     let cascade = match p.child_renames {
         0 => String::new(),
@@ -449,15 +1316,62 @@ pub fn renumber_summary(p: &RenumberPlan) -> String {
     )
 }
 
+/// One line per rename and rewritten notes file, headed by a count.
+pub fn recode_summary(p: &RecodePlan) -> String {
+    let mut out = format!(
+        "will recode {} → {}: {} rename(s), {} notes file(s) rewritten",
+        p.old_code,
+        p.new_code,
+        p.renames.len(),
+        p.rewrites.len()
+    );
+    for (src, name) in &p.renames {
+        let old = src.file_name().unwrap().to_string_lossy();
+        out.push_str(&format!("\n  {} → {}", old, name));
+    }
+    for f in &p.rewrites {
+        for (line, before, after) in &f.lines {
+            out.push_str(&format!(
+                "\n  ~ {}:{}: {} → {}",
+                f.path.display(),
+                line,
+                before,
+                after
+            ));
+        }
+    }
+    out
+}
+
+pub fn move_summary(p: &MovePlan) -> String {
+    format!(
+        "will move {} → {} as {}",
+        p.src_path
+            .file_name()
+            .map(|n| n.to_string_lossy().into_owned())
+            .unwrap_or_default(),
+        p.dest_path
+            .parent()
+            .map(|n| n.display().to_string())
+            .unwrap_or_default(),
+        p.final_name
+    )
+}
+
 pub fn create_summary(p: &CreatePlan) -> String {
     format!(
-        "will create {} {} under {}{}",
+        "will create {} {} under {}{}{}",
         format!("{:?}", p.kind).to_uppercase(),
         p.final_name,
         p.parent_display,
         p.url
             .as_ref()
             .map(|u| format!(" -> {}", u))
+            .unwrap_or_default(),
+        p.template
+            .as_ref()
+            .and_then(|t| t.file_name())
+            .map(|t| format!(" from template {}", t.to_string_lossy()))
             .unwrap_or_default()
     )
 }
@@ -589,6 +1503,23 @@ mod tests {
         assert!(plan_at(&tree, "31_Papers", "31.01 Existing").is_err());
     }
 
+    #[test]
+    fn titles_the_policy_empties_are_refused() {
+        let (_td, tree) = fixture();
+        let err = plan_at(&tree, "31_Papers", "31.05 ???").unwrap_err();
+        assert!(err.to_string().contains("no title left"), "{}", err);
+        assert!(sanitize_name("32.05_???").is_err());
+        let item = node_by_suffix(&tree, "31.01_Existing").id.clone();
+        assert!(plan_rename(&tree, &item, "???").is_err());
+        assert!(plan_rename(&tree, &item, "...").is_err());
+        let vars = crate::template::Vars::for_name("31.05_???");
+        assert!(vars.fill_name("{{code}}_{{title}}.md").is_err());
+        assert_eq!(
+            vars.fill_name("{{code}}_refs.bib").unwrap(),
+            "31.05_refs.bib"
+        );
+    }
+
     #[test]
     fn forced_kinds() {
         let (_td, tree) = fixture();
@@ -725,4 +1656,243 @@ mod tests {
         let p = plan_move(&tree, &src, &existing).unwrap();
         assert_eq!(p.final_name, "31.03_Two_Word_Note.txt");
     }
+
+    #[test]
+    fn recode_planning() {
+        let (td, tree) = fixture();
+        let papers = node_by_suffix(&tree, "31_Papers").id.clone();
+        let p = plan_recode(&tree, &papers, "33", false).unwrap();
+        assert!(p.dest_path.ends_with("30-39_Research/33_Papers"));
+        let names: Vec<&str> = p.renames.iter().map(|(_, n)| n.as_str()).collect();
+        assert_eq!(names[0], "33_Papers");
+        assert!(names.contains(&"33.03_Two_Word_Note.txt"));
+        assert_eq!(names.len(), 4);
+        // taken by a sibling, outside the range, malformed
+        assert!(plan_recode(&tree, &papers, "32", false).is_err());
+        assert!(plan_recode(&tree, &papers, "41", false).is_err());
+        assert!(plan_recode(&tree, &papers, "3", false).is_err());
+        // items are not recoded this way
+        let item = node_by_suffix(&tree, "31.01_Existing").id.clone();
+        assert!(plan_recode(&tree, &item, "31.02", false).is_err());
+
+        // ranges shift their categories by the same offset
+        let stuff = td.path().join("R/90-99_Stuff");
+        fs::write(stuff.join(".jdmeta"), "LINK=31.04 https://x.io\n").unwrap();
+        fs::write(stuff.join(".jdmeta.md"), "See 31.04.\nPaid 31.12.2024\n").unwrap();
+        let tree = fs_walk::scan_roots(&[td.path().join("R")]).unwrap();
+        let range = node_by_suffix(&tree, "30-39_Research").id.clone();
+        let p = plan_recode(&tree, &range, "50-59", false).unwrap();
+        assert_eq!(p.renames[0].1, "50-59_Research");
+        assert!(p.renames.iter().any(|(_, n)| n == "51.04_Container"));
+        assert_eq!(p.rewrites.len(), 1);
+        assert_eq!(
+            p.rewrites[0].lines,
+            [(
+                1,
+                "LINK=31.04 https://x.io".to_string(),
+                "LINK=51.04 https://x.io".to_string()
+            )]
+        );
+        // only codes in the subtree, and not inside a longer number
+        assert_eq!(
+            p.rewrite_text("31.04, 31.041, 31.50, 31.04.2024 and 91.04"),
+            "51.04, 31.041, 31.50, 31.04.2024 and 91.04"
+        );
+        // notes only when asked, and only their code
+        let p = plan_recode(&tree, &range, "50-59", true).unwrap();
+        assert_eq!(p.rewrites.len(), 2);
+        assert_eq!(p.rewrites[1].lines.len(), 1);
+        assert_eq!(p.rewrites[1].lines[0].2, "See 51.04.");
+        // overlapping another range, or too narrow for its categories
+        assert!(plan_recode(&tree, &range, "90-99", false).is_err());
+        assert!(plan_recode(&tree, &range, "50-51", false).is_err());
+    }
+
+    #[test]
+    fn item_recode_planning() {
+        let (td, _) = fixture();
+        let r = td.path().join("R/30-39_Research/31_Papers/31.04_Container");
+        fs::write(r.join("31.04_scan.pdf"), b"x").unwrap();
+        fs::create_dir(r.join("31.04.01_Sub")).unwrap();
+        let tree = fs_walk::scan_roots(&[td.path().join("R")]).unwrap();
+        let item = node_by_suffix(&tree, "31.04_Container").id.clone();
+        let p = plan_recode_item(&tree, &item, "31.10").unwrap();
+        assert_eq!(p.new_name, "31.10_Container");
+        // the stamped file and the sub-item follow
+        assert_eq!(p.child_renames, 2);
+        // in use, wrong category, malformed, categories refused
+        assert!(plan_recode_item(&tree, &item, "31.01").is_err());
+        assert!(plan_recode_item(&tree, &item, "32.04").is_err());
+        assert!(plan_recode_item(&tree, &item, "31.x").is_err());
+        let papers = node_by_suffix(&tree, "31_Papers").id.clone();
+        assert!(plan_recode_item(&tree, &papers, "33").is_err());
+        // extended codes are fine
+        assert!(plan_recode_item(&tree, &item, "31.104").is_ok());
+    }
+
+    #[test]
+    fn stale_plans_are_resuggested_or_refused() {
+        let (td, tree) = fixture();
+        let cat = td.path().join("R/30-39_Research/31_Papers");
+        let auto = plan_at(&tree, "31_Papers", "Reading list").unwrap();
+        assert!(auto.suggested);
+        let code = name_code(&auto.final_name).unwrap();
+        let typed = plan_at(&tree, "31_Papers", "31.09 Typed").unwrap();
+        assert!(!typed.suggested);
+        let rename =
+            plan_rename(&tree, &node_by_suffix(&tree, "31.04_Container").id, "Box").unwrap();
+
+        // another session creates both codes and renames the container
+        fs::create_dir(cat.join(format!("{}_Elsewhere", code))).unwrap();
+        fs::create_dir(cat.join("31.09_Race")).unwrap();
+        fs::rename(cat.join("31.04_Container"), cat.join("31.04_Crate")).unwrap();
+        let fresh = fs_walk::scan_roots(&[td.path().join("R")]).unwrap();
+
+        let p = revalidate_create(&fresh, &auto).unwrap();
+        assert!(!p.final_name.starts_with(&code));
+        assert!(p.final_name.ends_with("_Reading_list"));
+        assert_eq!(p.dest_path, cat.join(&p.final_name));
+        assert!(p.warnings.last().unwrap().contains("was taken meanwhile"));
+        let err = revalidate_create(&fresh, &typed).unwrap_err().to_string();
+        assert!(err.contains("31.09 was taken"), "{}", err);
+        let err = revalidate(
+            &fresh,
+            &rename.id,
+            &rename.src_path,
+            &rename.dest_path,
+            None,
+        )
+        .unwrap_err()
+        .to_string();
+        assert!(err.contains("changed since it was planned"), "{}", err);
+    }
+
+    #[test]
+    fn filing_picks_free_codes_or_stamps() {
+        let (td, tree) = fixture();
+        let inbox = td.path().join("inbox");
+        fs::create_dir_all(inbox.join("Old folder")).unwrap();
+        fs::write(inbox.join("my scan (1).pdf"), b"x").unwrap();
+        fs::write(inbox.join("12.07 Invoice.txt"), b"x").unwrap();
+        let srcs = [
+            inbox.join("my scan (1).pdf"),
+            inbox.join("12.07 Invoice.txt"),
+            inbox.join("Old folder"),
+        ];
+        let papers = node_by_suffix(&tree, "31_Papers").id.clone();
+        let names: Vec<String> = plan_file_in(&tree, &srcs, &papers)
+            .unwrap()
+            .into_iter()
+            .map(|f| f.final_name)
+            .collect();
+        // 31.01, 31.03 and 31.04 are taken; a foreign code prefix is dropped
+        assert_eq!(
+            names,
+            [
+                "31.02_my_scan_(1).pdf",
+                "31.05_Invoice.txt",
+                "31.06_Old_folder"
+            ]
+        );
+
+        let item = node_by_suffix(&tree, "31.04_Container").id.clone();
+        let p = plan_file_in(&tree, &srcs[..1], &item).unwrap();
+        assert_eq!(p[0].final_name, "31.04_my_scan_(1).pdf");
+        // same name twice in one batch, missing source, ranges refused
+        assert!(plan_file_in(&tree, &[srcs[0].clone(), srcs[0].clone()], &item).is_err());
+        assert!(plan_file_in(&tree, &[inbox.join("nope")], &item).is_err());
+        let range = node_by_suffix(&tree, "30-39_Research").id.clone();
+        assert!(plan_file_in(&tree, &srcs, &range).is_err());
+    }
+
+    #[test]
+    fn wrap_and_unwrap_plan_paths_or_refuse() {
+        let (td, tree) = fixture();
+        let papers = td.path().join("R/30-39_Research/31_Papers");
+        let note = node_by_suffix(&tree, "31.03_Two_Word_Note.txt");
+        let w = plan_wrap(&tree, &note.id).unwrap();
+        assert_eq!(w.dir_path, papers.join("31.03_Two_Word_Note"));
+        assert_eq!(w.dest_path, w.dir_path.join("31.03_Two_Word_Note.txt"));
+        assert_eq!(
+            wrap_summary(&w),
+            "will wrap 31.03_Two_Word_Note.txt into 31.03_Two_Word_Note/"
+        );
+        let folder = node_by_suffix(&tree, "31.01_Existing").id.clone();
+        assert!(plan_wrap(&tree, &folder).is_err());
+
+        // empty, then one file plus .jdmeta, then two files
+        assert!(plan_unwrap(&tree, &folder).is_err());
+        let dir = papers.join("31.01_Existing");
+        fs::write(dir.join("31.01_Existing.tar.gz"), b"x").unwrap();
+        fs::write(dir.join(crate::meta::META_FILE), "drawer 2\n").unwrap();
+        let tree = fs_walk::scan_roots(&[td.path().join("R")]).unwrap();
+        let u = plan_unwrap(&tree, &folder).unwrap();
+        assert_eq!(u.file_path, dir.join("31.01_Existing.tar.gz"));
+        assert_eq!(u.dest_path, papers.join("31.01_Existing.tar.gz"));
+        assert!(wrap_summary(&u).ends_with("(.jdmeta kept beside it)"));
+        fs::write(dir.join("scan.pdf"), b"x").unwrap();
+        let tree = fs_walk::scan_roots(&[td.path().join("R")]).unwrap();
+        let err = plan_unwrap(&tree, &folder).unwrap_err().to_string();
+        assert!(err.contains("holds 2 entries"), "{}", err);
+
+        // entries the scan ignores count too: unwrapping would delete them
+        fs::remove_file(dir.join("scan.pdf")).unwrap();
+        fs::create_dir(dir.join(".git")).unwrap();
+        fs::write(dir.join("draft.bak"), b"x").unwrap();
+        let tree = fs_walk::scan_roots(&[td.path().join("R")]).unwrap();
+        let err = plan_unwrap(&tree, &folder).unwrap_err().to_string();
+        assert!(
+            err.contains("holds 3 entries (.git, 31.01_Existing.tar.gz, draft.bak)"),
+            "{}",
+            err
+        );
+    }
+
+    #[test]
+    fn archive_takes_next_free_code_and_unarchive_goes_home() {
+        let (td, _) = fixture();
+        let roots = [td.path().join("R")];
+        let r = &roots[0];
+        fs::create_dir_all(r.join("90-99_Stuff/91_Archive/91.01_Old")).unwrap();
+        let tree = fs_walk::scan_roots(&roots).unwrap();
+        let item = node_by_suffix(&tree, "31.04_Container").id.clone();
+        let p = plan_archive(&tree, &item, "91").unwrap();
+        assert_eq!(p.new_code, "91.02");
+        assert_eq!(
+            p.dest_path,
+            r.join("90-99_Stuff/91_Archive/91.02_Container")
+        );
+        assert_eq!(
+            archive_summary(&p),
+            "will archive 31.04_Container as 91.02 in 91_Archive"
+        );
+        // categories go into a range; an item can't
+        let cat = node_by_suffix(&tree, "32_Empty").id.clone();
+        assert_eq!(plan_archive(&tree, &cat, "90-99").unwrap().new_code, "90");
+        assert!(plan_archive(&tree, &item, "90-99").is_err());
+
+        // as archive leaves it
+        fs::rename(&p.src_path, &p.dest_path).unwrap();
+        fs::write(
+            p.dest_path.join(crate::meta::META_FILE),
+            "ARCHIVED_FROM=31.04\n",
+        )
+        .unwrap();
+        let tree = fs_walk::scan_roots(&roots).unwrap();
+        let archived = crate::resolve::resolve_code_to_path(&tree, "31.04").unwrap();
+        assert_eq!(archived, p.dest_path);
+        let back = plan_unarchive(&tree, &item).unwrap();
+        assert_eq!(
+            (back.old_code.as_str(), back.new_code.as_str()),
+            ("91.02", "31.04")
+        );
+        assert_eq!(back.dest_path, p.src_path);
+        assert!(plan_archive(&tree, &item, "91").is_err());
+
+        fs::create_dir(r.join("30-39_Research/31_Papers/31.04_Newcomer")).unwrap();
+        let tree = fs_walk::scan_roots(&roots).unwrap();
+        let back = plan_unarchive(&tree, &item).unwrap();
+        assert_eq!(back.new_code, "31.02");
+        assert_eq!(back.warnings, ["31.04 is taken now; using 31.02"]);
+    }
 }
@@ -1,7 +1,11 @@
 use crate::ignore::is_ignored_entry;
 use anyhow::Result;
+use once_cell::sync::Lazy;
 use std::fs;
-use std::path::Path;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::SystemTime;
 
 pub fn preview_dir(path: &Path) -> Result<String> {
     let listing = dir_listing(path, 50)?;
@@ -57,10 +61,268 @@ fn parse_datetime_prefix(name: &str) -> Option<u128> {
     s.parse::<u128>().ok()
 }
 
+/// The summary lines, then the first 200 lines for text.
 pub fn preview_file(path: &Path) -> Result<String> {
-    let content = fs::read_to_string(path).unwrap_or_default();
-    let head = content.lines().take(200).collect::<Vec<_>>().join("\n");
-    Ok(head)
+    let info = inspect(path)?;
+    let mut out = summary(&info).join("\n");
+    out.push('\n');
+    if info.encoding.is_some() {
+        out.push('\n');
+        out.push_str(&text_head(path, 200)?);
+    }
+    Ok(out)
+}
+
+/// Bytes read to tell the type, dimensions, encoding and preview text.
+const HEAD: u64 = 64 * 1024;
+/// Lines are counted this far; a longer file shows "N+ lines".
+const LINE_SCAN: u64 = 1024 * 1024;
+/// A PDF's page count is looked for in files up to this size.
+const PDF_SCAN: u64 = 4 * 1024 * 1024;
+
+/// What `inspect` found out about a file.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct FileInfo {
+    pub size: u64,
+    pub modified: Option<u64>,
+    /// "PNG image", "PDF document", "text", "binary", …
+    pub kind: &'static str,
+    pub dimensions: Option<(u32, u32)>,
+    pub pages: Option<u32>,
+    /// Set for text only.
+    pub encoding: Option<&'static str>,
+    pub lines: Option<usize>,
+    /// `lines` stops at `LINE_SCAN` bytes.
+    pub lines_partial: bool,
+}
+
+/// The TUI previews the selected row on every frame: keep the last answer
+/// while the file's size and mtime hold.
+type Inspected = (PathBuf, u64, Option<SystemTime>, FileInfo);
+static LAST: Lazy<Mutex<Option<Inspected>>> = Lazy::new(|| Mutex::new(None));
+
+/// Size, date and type of a file from its first bytes: magic numbers for
+/// common binary formats (with image dimensions and a PDF's page count where
+/// that's cheap), else text in some encoding, else binary.
+pub fn inspect(path: &Path) -> Result<FileInfo> {
+    let meta = fs::metadata(path)?;
+    let mtime = meta.modified().ok();
+    if let Some((p, size, m, info)) = LAST.lock().unwrap().as_ref() {
+        if p == path && *size == meta.len() && *m == mtime {
+            return Ok(info.clone());
+        }
+    }
+    let head = read_head(path, HEAD)?;
+    let mut info = FileInfo {
+        size: meta.len(),
+        modified: mtime.map(crate::date::secs),
+        ..FileInfo::default()
+    };
+    if let Some(kind) = magic(&head) {
+        info.kind = kind;
+        info.dimensions = dimensions(&head);
+        if kind == "PDF document" && info.size <= PDF_SCAN {
+            info.pages = pdf_pages(&fs::read(path)?);
+        }
+    } else if let Some(encoding) = encoding(&head, info.size > HEAD) {
+        info.kind = "text";
+        info.encoding = Some(encoding);
+        let scanned = read_head(path, LINE_SCAN)?;
+        let mut lines = scanned.iter().filter(|&&b| b == b'\n').count();
+        if scanned.last().is_some_and(|&b| b != b'\n') {
+            lines += 1;
+        }
+        info.lines = Some(lines);
+        info.lines_partial = info.size > LINE_SCAN;
+    } else {
+        info.kind = "binary";
+    }
+    *LAST.lock().unwrap() = Some((path.to_path_buf(), meta.len(), mtime, info.clone()));
+    Ok(info)
+}
+
+/// `12.3 KB · modified 2026-10-19 14:05`, then the type line:
+/// `PNG image · 800×600`, `PDF document · 12 pages`, `text · UTF-8 · 120 lines`.
+pub fn summary(info: &FileInfo) -> Vec<String> {
+    let mut first = human_size(info.size);
+    if let Some(m) = info.modified {
+        first.push_str(&format!(" · modified {}", crate::date::date_time(m)));
+    }
+    let mut second = vec![info.kind.to_string()];
+    if let Some((w, h)) = info.dimensions {
+        second.push(format!("{}×{}", w, h));
+    }
+    if let Some(p) = info.pages {
+        second.push(format!("{} page{}", p, if p == 1 { "" } else { "s" }));
+    }
+    if let Some(e) = info.encoding {
+        second.push(e.to_string());
+    }
+    if let Some(n) = info.lines {
+        let plus = if info.lines_partial { "+" } else { "" };
+        second.push(format!(
+            "{}{} line{}",
+            n,
+            plus,
+            if n == 1 { "" } else { "s" }
+        ));
+    }
+    vec![first, second.join(" · ")]
+}
+
+/// The first `max_lines` of a text file, decoded as `inspect` detected it.
+pub fn text_head(path: &Path, max_lines: usize) -> Result<String> {
+    let head = read_head(path, HEAD)?;
+    let size = fs::metadata(path)?.len();
+    let text = match encoding(&head, size > HEAD) {
+        Some("UTF-16LE") => utf16(&head[2..], u16::from_le_bytes),
+        Some("UTF-16BE") => utf16(&head[2..], u16::from_be_bytes),
+        // Latin-1 maps every byte to the code point of the same number
+        Some("8-bit") => head.iter().map(|&b| b as char).collect(),
+        _ => String::from_utf8_lossy(head.strip_prefix(b"\xef\xbb\xbf").unwrap_or(&head))
+            .into_owned(),
+    };
+    Ok(text.lines().take(max_lines).collect::<Vec<_>>().join("\n"))
+}
+
+/// A text file worth an editor rather than the desktop's opener.
+pub fn looks_like_text(path: &Path) -> bool {
+    inspect(path).is_ok_and(|i| i.encoding.is_some())
+}
+
+fn read_head(path: &Path, n: u64) -> Result<Vec<u8>> {
+    let mut buf = Vec::new();
+    fs::File::open(path)?.take(n).read_to_end(&mut buf)?;
+    Ok(buf)
+}
+
+pub fn human_size(n: u64) -> String {
+    if n < 1000 {
+        return format!("{} bytes", n);
+    }
+    let mut v = n as f64;
+    for unit in ["KB", "MB", "GB"] {
+        v /= 1000.0;
+        if v < 1000.0 {
+            return format!("{:.1} {}", v, unit);
+        }
+    }
+    format!("{:.1} TB", v / 1000.0)
+}
+
+fn magic(b: &[u8]) -> Option<&'static str> {
+    let at = |i: usize, sig: &[u8]| b.get(i..i + sig.len()) == Some(sig);
+    Some(match () {
+        _ if at(0, b"\x89PNG\r\n\x1a\n") => "PNG image",
+        _ if at(0, b"\xff\xd8\xff") => "JPEG image",
+        _ if at(0, b"GIF87a") || at(0, b"GIF89a") => "GIF image",
+        _ if at(0, b"RIFF") && at(8, b"WEBP") => "WebP image",
+        _ if at(4, b"ftypheic") || at(4, b"ftypheix") || at(4, b"ftypmif1") => "HEIC image",
+        _ if at(4, b"ftyp") => "MP4 video",
+        _ if at(0, b"%PDF-") => "PDF document",
+        _ if at(0, b"PK\x03\x04") => "ZIP archive",
+        _ if at(0, b"\x1f\x8b") => "gzip archive",
+        _ if at(0, b"SQLite format 3\0") => "SQLite database",
+        _ if at(0, b"ID3") || at(0, b"\xff\xfb") => "MP3 audio",
+        _ if at(0, b"\x7fELF") => "executable",
+        _ => return None,
+    })
+}
+
+fn dimensions(b: &[u8]) -> Option<(u32, u32)> {
+    let be16 = |i: usize| {
+        b.get(i..i + 2)
+            .map(|s| u16::from_be_bytes([s[0], s[1]]) as u32)
+    };
+    let le16 = |i: usize| {
+        b.get(i..i + 2)
+            .map(|s| u16::from_le_bytes([s[0], s[1]]) as u32)
+    };
+    let be32 = |i: usize| {
+        b.get(i..i + 4)
+            .map(|s| u32::from_be_bytes([s[0], s[1], s[2], s[3]]))
+    };
+    match magic(b)? {
+        // IHDR comes first
+        "PNG image" => Some((be32(16)?, be32(20)?)),
+        "GIF image" => Some((le16(6)?, le16(8)?)),
+        "JPEG image" => {
+            // walk the segments to the first start-of-frame
+            let mut i = 2;
+            while b.get(i) == Some(&0xff) {
+                let marker = *b.get(i + 1)?;
+                let len = be16(i + 2)? as usize;
+                let sof = matches!(marker, 0xc0..=0xcf) && !matches!(marker, 0xc4 | 0xc8 | 0xcc);
+                if sof {
+                    return Some((be16(i + 7)?, be16(i + 5)?));
+                }
+                i += 2 + len;
+            }
+            None
+        }
+        _ => None,
+    }
+}
+
+/// The largest `/Count N` in the file: the page tree root counts every page
+/// below it. None when the page tree sits in a compressed object stream.
+fn pdf_pages(b: &[u8]) -> Option<u32> {
+    let mut best = None;
+    let mut rest = b;
+    while let Some(i) = rest.windows(6).position(|w| w == b"/Count") {
+        rest = &rest[i + 6..];
+        let digits: Vec<u8> = rest
+            .iter()
+            .skip_while(|c| c.is_ascii_whitespace())
+            .take_while(|c| c.is_ascii_digit())
+            .copied()
+            .collect();
+        if let Some(n) = std::str::from_utf8(&digits)
+            .ok()
+            .and_then(|s| s.parse().ok())
+        {
+            best = best.max(Some(n));
+        }
+    }
+    best
+}
+
+/// The encoding of text, or None for binary. `cut` says the sample stops
+/// mid-file, so a character split at its end doesn't count against UTF-8.
+fn encoding(b: &[u8], cut: bool) -> Option<&'static str> {
+    if b.starts_with(b"\xff\xfe") {
+        return Some("UTF-16LE");
+    }
+    if b.starts_with(b"\xfe\xff") {
+        return Some("UTF-16BE");
+    }
+    if b.contains(&0) {
+        return None;
+    }
+    if b.starts_with(b"\xef\xbb\xbf") {
+        return Some("UTF-8 (BOM)");
+    }
+    if b.is_ascii() {
+        return Some("ASCII");
+    }
+    match std::str::from_utf8(b) {
+        Ok(_) => return Some("UTF-8"),
+        Err(e) if cut && e.error_len().is_none() => return Some("UTF-8"),
+        Err(_) => {}
+    }
+    // legacy 8-bit text has few control characters besides whitespace
+    let control = b
+        .iter()
+        .filter(|&&c| c < 0x20 && !matches!(c, b'\t' | b'\n' | b'\r' | 0x0c | 0x1b))
+        .count();
+    (control * 100 < b.len()).then_some("8-bit")
+}
+
+fn utf16(b: &[u8], word: fn([u8; 2]) -> u16) -> String {
+    let words = b.chunks_exact(2).map(|c| word([c[0], c[1]]));
+    char::decode_utf16(words)
+        .map(|c| c.unwrap_or(char::REPLACEMENT_CHARACTER))
+        .collect()
 }
 
 pub fn preview_link(path: &Path) -> Result<String> {
@@ -1,22 +1,269 @@
-use crate::model::{Node, Tree};
-use anyhow::{bail, Result};
+//! Finding nodes from what someone types: a code, a short form of one
+//! (`21.4` for `21.04`), the start of one, a title fragment, or an alias
+//! from the config. `candidates` ranks every node that fits; the input is
+//! ambiguous when more than one fits equally well.
 
-pub fn resolve_code_to_path(tree: &Tree, code: &str) -> Result<std::path::PathBuf> {
-    fn walk<'a>(node: &'a Node, code: &str) -> Option<&'a Node> {
-        if node.code.as_deref() == Some(code) {
-            return Some(node);
+use crate::model::{Node, NodeType, Tree};
+use anyhow::{anyhow, Result};
+use serde::Serialize;
+use std::collections::{BTreeMap, HashSet};
+use std::path::PathBuf;
+
+/// How a candidate fits the input, best first.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Match {
+    /// The code itself.
+    Code,
+    /// An alias from the config naming this code.
+    Alias,
+    /// The code it had before `archive` moved it.
+    Archived,
+    /// The code with its digits padded: `21.4`, `1.04`.
+    Short,
+    /// The code starts with the input: `21.0`.
+    Prefix,
+    /// Every word of the input is in "CODE Title".
+    Title,
+}
+
+pub struct Candidate<'a> {
+    pub node: &'a Node,
+    pub how: Match,
+}
+
+impl Candidate<'_> {
+    pub fn display(&self) -> String {
+        match &self.node.code {
+            Some(c) => format!("{} {}", c, self.node.title),
+            None => self.node.title.clone(),
+        }
+    }
+}
+
+/// Every node `input` could mean, best match first, then in tree order.
+/// `aliases` maps names (compared without case) to codes.
+pub fn candidates<'a>(
+    tree: &'a Tree,
+    input: &str,
+    aliases: &BTreeMap<String, String>,
+) -> Vec<Candidate<'a>> {
+    let input = input.trim();
+    let alias = aliases
+        .iter()
+        .find(|(k, _)| k.eq_ignore_ascii_case(input))
+        .map(|(_, code)| code.as_str());
+    let short = pad(input).filter(|s| s != input);
+    let code_like = !input.is_empty() && input.chars().all(|c| c.is_ascii_digit() || c == '.');
+    let words: Vec<String> = input.split_whitespace().map(str::to_lowercase).collect();
+
+    let mut out = Vec::new();
+    let mut seen = HashSet::new();
+    let mut add = |node: &'a Node, how| {
+        if seen.insert(&node.id) {
+            out.push(Candidate { node, how });
+        }
+    };
+    let all = walk(tree);
+    for n in with_code(&all, input) {
+        add(n, Match::Code);
+    }
+    if let Some(code) = alias {
+        for n in with_code(&all, code) {
+            add(n, Match::Alias);
+        }
+    }
+    for r in &tree.roots {
+        archived(r, input, &mut |n| add(n, Match::Archived));
+        if let Some(code) = alias {
+            archived(r, code, &mut |n| add(n, Match::Alias));
+        }
+    }
+    if let Some(code) = &short {
+        for n in with_code(&all, code) {
+            add(n, Match::Short);
+        }
+    }
+    for (n, stamped) in &all {
+        let code = n.code.as_deref().unwrap_or("");
+        if code_like && !stamped && code.starts_with(input) && code != input {
+            add(n, Match::Prefix);
+        }
+    }
+    for (n, stamped) in &all {
+        let hay = match &n.code {
+            Some(c) => format!("{} {}", c, n.title).to_lowercase(),
+            None => n.title.to_lowercase(),
+        };
+        if n.node_type != NodeType::Other
+            && !stamped
+            && !words.is_empty()
+            && words.iter().all(|w| hay.contains(w.as_str()))
+        {
+            add(n, Match::Title);
         }
-        for ch in &node.children {
-            if let Some(n) = walk(ch, code) {
-                return Some(n);
+    }
+    out.sort_by_key(|c| c.how);
+    out
+}
+
+/// More than one candidate fits as well as the best.
+pub fn ambiguous(candidates: &[Candidate]) -> bool {
+    matches!(candidates, [a, b, ..] if a.how == b.how)
+}
+
+/// The path for an exact code, or one the node had before it was archived;
+/// the first across roots.
+pub fn resolve_code_to_path(tree: &Tree, code: &str) -> Result<PathBuf> {
+    candidates(tree, code, &BTreeMap::new())
+        .into_iter()
+        .find(|c| matches!(c.how, Match::Code | Match::Archived))
+        .map(|c| PathBuf::from(&c.node.path))
+        .ok_or_else(|| anyhow!("Code not found: {}", code))
+}
+
+/// A link's own URL, else a `file://` URL for the path.
+pub fn url(node: &Node) -> String {
+    if let Some(u) = &node.url {
+        return u.clone();
+    }
+    let mut out = String::from("file://");
+    for b in node.path.bytes() {
+        match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'/' | b'-' | b'_' | b'.' | b'~' => {
+                out.push(b as char)
             }
+            _ => out.push_str(&format!("%{:02X}", b)),
         }
-        None
     }
+    out
+}
+
+/// Nodes in tree order, each with whether an ancestor has the same code
+/// (a file stamped with its item's number, which the item stands for).
+fn walk(tree: &Tree) -> Vec<(&Node, bool)> {
+    fn go<'a>(n: &'a Node, codes: &mut Vec<&'a str>, out: &mut Vec<(&'a Node, bool)>) {
+        let code = n.code.as_deref();
+        out.push((n, code.is_some_and(|c| codes.contains(&c))));
+        codes.extend(code);
+        for c in &n.children {
+            go(c, codes, out);
+        }
+        if code.is_some() {
+            codes.pop();
+        }
+    }
+    let mut out = Vec::new();
     for r in &tree.roots {
-        if let Some(n) = walk(r, code) {
-            return Ok(std::path::PathBuf::from(&n.path));
+        go(r, &mut Vec::new(), &mut out);
+    }
+    out
+}
+
+fn with_code<'a>(all: &[(&'a Node, bool)], code: &str) -> Vec<&'a Node> {
+    all.iter()
+        .filter(|(n, stamped)| !stamped && n.code.as_deref() == Some(code))
+        .map(|(n, _)| *n)
+        .collect()
+}
+
+/// An archived node still answers to its former code, and so does what
+/// was recoded along with it (21.04 in a category archived from 21).
+fn archived<'a>(node: &'a Node, code: &str, found: &mut impl FnMut(&'a Node)) {
+    if let (Some(from), Some(now)) = (&node.archived_from, &node.code) {
+        if from == code {
+            found(node);
+        } else if let Some(rest) = code
+            .strip_prefix(from.as_str())
+            .filter(|r| r.starts_with('.'))
+        {
+            let inner = format!("{}{}", now, rest);
+            if let Some(n) = find(node, &inner) {
+                found(n);
+            }
+        }
+    }
+    for c in &node.children {
+        archived(c, code, found);
+    }
+}
+
+fn find<'a>(node: &'a Node, code: &str) -> Option<&'a Node> {
+    if node.code.as_deref() == Some(code) {
+        return Some(node);
+    }
+    node.children.iter().find_map(|c| find(c, code))
+}
+
+/// `21.4` → `21.04`, `1.04.3` → `01.04.03`; None unless it's digits and
+/// dots with nothing longer than the code allows.
+fn pad(input: &str) -> Option<String> {
+    let mut parts = input.split('.');
+    let first = parts.next()?;
+    if first.is_empty() || first.len() > 2 || !first.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    let mut out = format!("{:0>2}", first);
+    for (i, p) in parts.enumerate() {
+        let max = if i == 0 { 4 } else { 2 };
+        if p.is_empty() || p.len() > max || !p.bytes().all(|b| b.is_ascii_digit()) {
+            return None;
         }
+        out.push_str(&format!(".{:0>2}", p));
+    }
+    Some(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fs_walk;
+    use std::fs;
+
+    #[test]
+    fn candidates_are_ranked_and_ambiguity_reported() {
+        let td = tempfile::tempdir().unwrap();
+        let roots = [td.path().join("R")];
+        let cat = roots[0].join("20-29_Admin/21_Taxes");
+        fs::create_dir_all(cat.join("21.04_Returns")).unwrap();
+        fs::write(cat.join("21.04_Returns/21.04_Checklist.md"), "").unwrap();
+        fs::create_dir_all(cat.join("21.05_Tax_letters")).unwrap();
+        fs::create_dir_all(roots[0].join("20-29_Admin/22_Bills")).unwrap();
+        let tree = fs_walk::scan_roots(&roots).unwrap();
+        let none = BTreeMap::new();
+        let found = |input: &str| -> Vec<(String, Match)> {
+            candidates(&tree, input, &none)
+                .iter()
+                .map(|c| (c.display(), c.how))
+                .collect()
+        };
+
+        // the stamped file inside 21.04 isn't a second 21.04
+        assert_eq!(found("21.04"), [("21.04 Returns".into(), Match::Code)]);
+        assert_eq!(found("21.4"), [("21.04 Returns".into(), Match::Short)]);
+        let c = candidates(&tree, "21.0", &none);
+        assert!(ambiguous(&c));
+        assert_eq!(c.len(), 2);
+        // a unique best match wins over weaker ones
+        let c = candidates(&tree, "21", &none);
+        assert!(!ambiguous(&c));
+        assert_eq!(c[0].display(), "21 Taxes");
+        assert_eq!(c.len(), 3); // and the items under it, by prefix
+        assert_eq!(
+            found("tax LET"),
+            [("21.05 Tax letters".into(), Match::Title)]
+        );
+        assert!(ambiguous(&candidates(&tree, "tax", &none)));
+        assert!(found("nothing like it").is_empty());
+
+        let aliases = BTreeMap::from([("bills".to_string(), "22".to_string())]);
+        let c = candidates(&tree, "Bills", &aliases);
+        assert_eq!(c[0].how, Match::Alias);
+        assert!(!ambiguous(&c));
+
+        assert_eq!(pad("1.4.3").as_deref(), Some("01.04.03"));
+        assert_eq!(pad("21.04x"), None);
+        assert!(url(c[0].node).starts_with("file:///"));
+        assert!(url(c[0].node).ends_with("/20-29_Admin/22_Bills"));
     }
-    bail!("Code not found: {}", code)
 }
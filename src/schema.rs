@@ -0,0 +1,300 @@
+//! The versioned shape of `.jd_index.json` (and of `scan` output).
+//!
+//! - 1: the bare `model::Tree`, no header.
+//! - 2: `schema_version` and `generated_at` beside `roots`; nodes may carry
+//!   `archived_from`.
+//!
+//! Older indexes are migrated when read; `jd-helper schema` prints the JSON
+//! Schema generated from these types, and `schema/jd_index.schema.json` is a
+//! checked-in copy for consumers that don't link this crate.
+
+use crate::model::Tree;
+use crate::{date, diff, fs_walk};
+use anyhow::{anyhow, bail, Result};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+pub const SCHEMA_VERSION: u32 = 2;
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[schemars(title = "JdIndex")]
+pub struct Index {
+    /// Bumped whenever a reader of the previous version could misread this one.
+    pub schema_version: u32,
+    /// When the tree was scanned, RFC 3339 UTC.
+    pub generated_at: String,
+    #[serde(flatten)]
+    pub tree: Tree,
+}
+
+impl Index {
+    /// Stamp a freshly scanned tree.
+    pub fn new(tree: Tree) -> Index {
+        Index {
+            schema_version: SCHEMA_VERSION,
+            generated_at: date::rfc3339(date::now()),
+            tree,
+        }
+    }
+}
+
+pub fn json_schema() -> Value {
+    serde_json::to_value(schemars::schema_for!(Index)).expect("schema serializes")
+}
+
+/// The version an index was written with; a missing field means 1.
+pub fn version_of(v: &Value) -> Result<u32> {
+    match v.get("schema_version") {
+        None => Ok(1),
+        Some(n) => n
+            .as_u64()
+            .and_then(|n| u32::try_from(n).ok())
+            .ok_or_else(|| anyhow!("schema_version is not a number: {}", n)),
+    }
+}
+
+/// Read an index of this or any older version. `written` stands in for
+/// `generated_at` where the file predates it (the file's mtime, say).
+pub fn parse(data: &[u8], written: u64) -> Result<Index> {
+    let mut v: Value = serde_json::from_slice(data)?;
+    let version = version_of(&v)?;
+    if version > SCHEMA_VERSION {
+        bail!(
+            "index schema {} is newer than this jd-helper reads (up to {}); upgrade jd-helper",
+            version,
+            SCHEMA_VERSION
+        );
+    }
+    if version < 2 {
+        let obj = v
+            .as_object_mut()
+            .ok_or_else(|| anyhow!("index is not a JSON object"))?;
+        obj.insert("schema_version".into(), SCHEMA_VERSION.into());
+        obj.insert("generated_at".into(), date::rfc3339(written).into());
+    }
+    Ok(serde_json::from_value(v)?)
+}
+
+/// `parse` a file, dating pre-version indexes by their mtime.
+pub fn read(path: &Path) -> Result<Index> {
+    let data = fs::read(path)?;
+    let written = fs::metadata(path)
+        .and_then(|m| m.modified())
+        .map(date::secs)
+        .unwrap_or(0);
+    parse(&data, written)
+}
+
+pub struct Verdict {
+    pub schema_version: u32,
+    pub generated_at: Option<String>,
+    pub problems: Vec<String>,
+}
+
+/// Check an index file against the current schema and against a fresh scan
+/// of `roots`: unknown or missing fields, an old or newer version, other
+/// roots, and nodes added, changed or removed since it was written.
+pub fn verify(path: &Path, roots: &[PathBuf]) -> Result<Verdict> {
+    let v: Value = serde_json::from_slice(&fs::read(path)?)?;
+    let schema_version = version_of(&v)?;
+    let generated_at = v
+        .get("generated_at")
+        .and_then(|g| g.as_str())
+        .map(str::to_string);
+    let mut problems = Vec::new();
+    if schema_version > SCHEMA_VERSION {
+        problems.push(format!(
+            "schema {} is newer than this jd-helper knows ({})",
+            schema_version, SCHEMA_VERSION
+        ));
+        return Ok(Verdict {
+            schema_version,
+            generated_at,
+            problems,
+        });
+    }
+    if schema_version < SCHEMA_VERSION {
+        problems.push(format!(
+            "schema {} is out of date (current {}); write-index rewrites it",
+            schema_version, SCHEMA_VERSION
+        ));
+    } else {
+        let schema = json_schema();
+        check(&v, &schema, &schema, "$", &mut problems);
+    }
+    let old = match read(path) {
+        Ok(idx) => idx.tree,
+        Err(e) => {
+            problems.push(format!("unreadable: {}", e));
+            return Ok(Verdict {
+                schema_version,
+                generated_at,
+                problems,
+            });
+        }
+    };
+    let new = fs_walk::scan_roots(roots)?;
+    let paths = |t: &Tree| t.roots.iter().map(|r| r.path.clone()).collect::<Vec<_>>();
+    if paths(&old) != paths(&new) {
+        problems.push(format!(
+            "indexes {} but the roots are {}",
+            paths(&old).join(", "),
+            paths(&new).join(", ")
+        ));
+    } else {
+        let changes = diff::changes(&old, &new);
+        let count = |k| changes.values().filter(|c| **c == k).count();
+        if !changes.is_empty() {
+            problems.push(format!(
+                "stale: {} new, {} changed, {} gone since it was written",
+                count(diff::Change::Added),
+                count(diff::Change::Changed),
+                count(diff::Change::Removed)
+            ));
+        }
+    }
+    Ok(Verdict {
+        schema_version,
+        generated_at,
+        problems,
+    })
+}
+
+/// Walk `v` against the subset of JSON Schema that schemars emits for our
+/// types: `$ref`, `type`, `enum`, `properties`/`required` and `items`.
+/// Properties the schema doesn't list are reported too, since they mean a
+/// writer and this reader disagree about the format.
+fn check(v: &Value, s: &Value, root: &Value, at: &str, out: &mut Vec<String>) {
+    if let Some(r) = s.get("$ref").and_then(|r| r.as_str()) {
+        let target = r
+            .strip_prefix('#')
+            .and_then(|p| root.pointer(p))
+            .unwrap_or(&Value::Null);
+        return check(v, target, root, at, out);
+    }
+    if let Some(t) = s.get("type") {
+        let allowed: Vec<&str> = match t {
+            Value::String(t) => vec![t.as_str()],
+            Value::Array(ts) => ts.iter().filter_map(|t| t.as_str()).collect(),
+            _ => vec![],
+        };
+        let kind = match v {
+            Value::Null => "null",
+            Value::Bool(_) => "boolean",
+            Value::Number(n) if n.is_u64() || n.is_i64() => "integer",
+            Value::Number(_) => "number",
+            Value::String(_) => "string",
+            Value::Array(_) => "array",
+            Value::Object(_) => "object",
+        };
+        let ok = allowed
+            .iter()
+            .any(|a| *a == kind || (*a == "number" && kind == "integer"));
+        if !ok {
+            out.push(format!(
+                "{}: expected {}, found {}",
+                at,
+                allowed.join(" or "),
+                kind
+            ));
+            return;
+        }
+    }
+    if let Some(choices) = s.get("enum").and_then(|e| e.as_array()) {
+        if !choices.contains(v) {
+            out.push(format!("{}: unexpected value {}", at, v));
+        }
+    }
+    if let (Value::Object(obj), Some(props)) = (v, s.get("properties").and_then(|p| p.as_object()))
+    {
+        for (k, child) in obj {
+            match props.get(k) {
+                Some(ps) => check(child, ps, root, &format!("{}.{}", at, k), out),
+                None => out.push(format!("{}: unknown field {:?}", at, k)),
+            }
+        }
+        for k in s
+            .get("required")
+            .and_then(|r| r.as_array())
+            .into_iter()
+            .flatten()
+        {
+            if let Some(k) = k.as_str() {
+                if !obj.contains_key(k) {
+                    out.push(format!("{}: missing field {:?}", at, k));
+                }
+            }
+        }
+    }
+    if let (Value::Array(items), Some(is)) = (v, s.get("items")) {
+        for (i, item) in items.iter().enumerate() {
+            check(item, is, root, &format!("{}[{}]", at, i), out);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn published_schema_is_current() {
+        let published: Value =
+            serde_json::from_str(include_str!("../schema/jd_index.schema.json")).unwrap();
+        assert_eq!(
+            published,
+            json_schema(),
+            "regenerate with: jd-helper schema > schema/jd_index.schema.json"
+        );
+    }
+
+    #[test]
+    fn migrates_v1_and_refuses_newer() {
+        let v1 = br#"{"roots":[{"id":"a","code":"21","title":"Taxes","path":"/R/21_Taxes",
+            "node_type":"Category","location":null,"url":null,"has_notes":false,"children":[]}]}"#;
+        let idx = parse(v1, 1_767_225_599).unwrap();
+        assert_eq!(idx.schema_version, SCHEMA_VERSION);
+        assert_eq!(idx.generated_at, "2025-12-31T23:59:59Z");
+        assert_eq!(idx.tree.roots[0].code.as_deref(), Some("21"));
+
+        let newer = format!(r#"{{"schema_version":{},"roots":[]}}"#, SCHEMA_VERSION + 1);
+        let err = parse(newer.as_bytes(), 0).unwrap_err().to_string();
+        assert!(err.contains("newer"), "{}", err);
+    }
+
+    #[test]
+    fn verify_flags_unknown_fields_and_staleness() {
+        let td = tempfile::tempdir().unwrap();
+        let roots = [td.path().join("R")];
+        fs::create_dir_all(roots[0].join("20-29_Admin/21_Taxes")).unwrap();
+        let path = td.path().join("index.json");
+        let write = |v: &Value| fs::write(&path, serde_json::to_vec(v).unwrap()).unwrap();
+
+        let mut v = serde_json::to_value(Index::new(fs_walk::scan_roots(&roots).unwrap())).unwrap();
+        write(&v);
+        assert!(verify(&path, &roots).unwrap().problems.is_empty());
+
+        v["roots"][0]["children"][0]["colour"] = "red".into();
+        write(&v);
+        let problems = verify(&path, &roots).unwrap().problems;
+        assert_eq!(
+            problems,
+            [r#"$.roots[0].children[0]: unknown field "colour""#]
+        );
+
+        v["roots"][0]["children"][0]
+            .as_object_mut()
+            .unwrap()
+            .remove("colour");
+        write(&v);
+        fs::create_dir(roots[0].join("20-29_Admin/22_Bills")).unwrap();
+        let problems = verify(&path, &roots).unwrap().problems;
+        assert_eq!(
+            problems,
+            ["stale: 1 new, 0 changed, 0 gone since it was written"]
+        );
+    }
+}
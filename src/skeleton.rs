@@ -0,0 +1,303 @@
+//! Tree skeletons as text manifests, the format of
+//! `tests/fixtures/T99_tree.txt`: one `KIND<TAB>PATH[<TAB>CONTENTS]` entry
+//! per line, the path relative to the root.
+//!
+//! ```text
+//! D  20-29_Admin
+//! D  20-29_Admin/21_Taxes
+//! F  20-29_Admin/21_Taxes/21.02_Portal.url  [InternetShortcut]\nURL=https://example.org\n
+//! ```
+//!
+//! `D` is a folder, `F` a file, empty unless the contents column gives its
+//! text with `\n`, `\t`, `\r` and `\\` escaped. Blank lines and `#`
+//! comments are skipped. `dump` writes the JD structure of a root (ranges,
+//! categories, items and their `.jdmeta` files, not other contents);
+//! `apply` recreates it under another folder without overwriting anything.
+
+use crate::meta;
+use crate::model::{Node, NodeType};
+use anyhow::{anyhow, bail, Result};
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+
+/// The manifest of `root`'s tree, and what couldn't go in it. With
+/// `contents`, `.jdmeta` files, pointer files (`LOCATION=`) and link items
+/// carry their text.
+pub fn dump(root: &Node, contents: bool) -> (String, Vec<String>) {
+    let mut d = Dump {
+        base: PathBuf::from(&root.path),
+        contents,
+        out: String::new(),
+        warnings: Vec::new(),
+    };
+    d.meta_files(Path::new(&root.path));
+    for c in &root.children {
+        d.node(c);
+    }
+    (d.out, d.warnings)
+}
+
+struct Dump {
+    base: PathBuf,
+    contents: bool,
+    out: String,
+    warnings: Vec<String>,
+}
+
+impl Dump {
+    fn node(&mut self, n: &Node) {
+        let path = Path::new(&n.path);
+        match n.node_type {
+            NodeType::File | NodeType::Link => {
+                let text = n.node_type == NodeType::Link || n.location.is_some();
+                self.line(path, text);
+                for s in meta::sidecars(path) {
+                    if s.is_file() {
+                        self.line(&s, true);
+                    }
+                }
+            }
+            _ => {
+                self.line(path, false);
+                self.meta_files(path);
+            }
+        }
+        for c in &n.children {
+            self.node(c);
+        }
+    }
+
+    fn meta_files(&mut self, dir: &Path) {
+        for name in [meta::META_FILE, meta::NOTES_FILE] {
+            let p = dir.join(name);
+            if p.is_file() {
+                self.line(&p, true);
+            }
+        }
+    }
+
+    fn line(&mut self, path: &Path, text: bool) {
+        let rel = path.strip_prefix(&self.base).unwrap_or(path);
+        let rel = rel.to_string_lossy();
+        if path.is_dir() {
+            self.out.push_str(&format!("D\t{}\n", rel));
+            return;
+        }
+        let bytes = match self.contents && text {
+            true => fs::read(path).unwrap_or_default(),
+            false => Vec::new(),
+        };
+        let content = match String::from_utf8(bytes) {
+            Ok(s) if s.is_empty() => None,
+            Ok(s) => Some(escape(&s)),
+            Err(_) => {
+                self.warnings
+                    .push(format!("{}: not text, contents left out", rel));
+                None
+            }
+        };
+        match content {
+            Some(c) => self.out.push_str(&format!("F\t{}\t{}\n", rel, c)),
+            None => self.out.push_str(&format!("F\t{}\n", rel)),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum Entry {
+    Dir(PathBuf),
+    File(PathBuf, Option<String>),
+}
+
+impl Entry {
+    pub fn path(&self) -> &Path {
+        match self {
+            Entry::Dir(p) | Entry::File(p, _) => p,
+        }
+    }
+}
+
+/// Parse a whole manifest before anything is created, naming the first bad
+/// line. Absolute paths and `..` are refused so a manifest can't write
+/// outside its destination.
+pub fn parse(manifest: &str) -> Result<Vec<Entry>> {
+    let mut out = Vec::new();
+    for (i, line) in manifest.lines().enumerate() {
+        let line = line.trim_end_matches('\r');
+        if line.trim().is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let err = |msg: &str| anyhow!("line {}: {}", i + 1, msg);
+        let mut cols = line.splitn(3, '\t');
+        let kind = cols.next().unwrap_or("");
+        let rel = PathBuf::from(cols.next().ok_or_else(|| err("expected KIND<TAB>PATH"))?);
+        let contents = cols.next();
+        let inside = rel
+            .components()
+            .all(|c| matches!(c, Component::Normal(_) | Component::CurDir));
+        if !inside || rel.as_os_str().is_empty() {
+            return Err(err(&format!(
+                "{} is not a relative path inside the destination",
+                rel.display()
+            )));
+        }
+        out.push(match (kind, contents) {
+            ("D", None) => Entry::Dir(rel),
+            ("D", Some(_)) => return Err(err("a folder has no contents")),
+            ("F", c) => Entry::File(
+                rel,
+                c.map(unescape)
+                    .transpose()
+                    .map_err(|e| err(&e.to_string()))?,
+            ),
+            (k, _) => return Err(err(&format!("unknown kind {:?} (D or F)", k))),
+        });
+    }
+    Ok(out)
+}
+
+pub struct Applied {
+    pub created: Vec<PathBuf>,
+    /// Entries already there, left as they were.
+    pub existing: Vec<PathBuf>,
+}
+
+/// Create the entries under `dest` (made if missing), parents as needed.
+/// Nothing that exists is overwritten.
+pub fn apply(entries: &[Entry], dest: &Path) -> Result<Applied> {
+    let mut created = Vec::new();
+    let mut existing = Vec::new();
+    fs::create_dir_all(dest)?;
+    for e in entries {
+        let target = dest.join(e.path());
+        if fs::symlink_metadata(&target).is_ok() {
+            match (e, target.is_dir()) {
+                (Entry::Dir(_), false) => bail!("{} exists and is not a folder", target.display()),
+                (Entry::File(..), true) => bail!("{} exists and is a folder", target.display()),
+                _ => existing.push(target),
+            }
+            continue;
+        }
+        match e {
+            Entry::Dir(_) => fs::create_dir_all(&target)?,
+            Entry::File(_, contents) => {
+                if let Some(parent) = target.parent() {
+                    fs::create_dir_all(parent)?;
+                }
+                fs::write(&target, contents.as_deref().unwrap_or(""))?;
+            }
+        }
+        created.push(target);
+    }
+    Ok(Applied { created, existing })
+}
+
+fn escape(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\t' => out.push_str("\\t"),
+            '\r' => out.push_str("\\r"),
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+fn unescape(s: &str) -> Result<String> {
+    let mut out = String::with_capacity(s.len());
+    let mut chars = s.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            out.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('\\') => out.push('\\'),
+            Some('n') => out.push('\n'),
+            Some('t') => out.push('\t'),
+            Some('r') => out.push('\r'),
+            other => bail!(
+                "bad escape \\{}",
+                other.map(String::from).unwrap_or_default()
+            ),
+        }
+    }
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fs_walk;
+
+    #[test]
+    fn dump_then_apply_reproduces_the_tree() {
+        let td = tempfile::tempdir().unwrap();
+        let roots = [td.path().join("R")];
+        let cat = roots[0].join("20-29_Admin/21_Taxes");
+        fs::create_dir_all(cat.join("21.01_Returns")).unwrap();
+        fs::write(cat.join("21.01_Returns/.jdmeta"), "LOCATION=drawer\t2\n").unwrap();
+        fs::write(cat.join("21.01_Returns/2024.pdf"), b"not JD, not dumped").unwrap();
+        fs::write(
+            cat.join("21.02_Portal.url"),
+            "[InternetShortcut]\nURL=https://example.org\n",
+        )
+        .unwrap();
+        fs::write(cat.join("21.03_Scan.pdf"), b"%PDF").unwrap();
+        let tree = fs_walk::scan_roots(&roots).unwrap();
+
+        let (bare, _) = dump(&tree.roots[0], false);
+        assert_eq!(
+            bare,
+            "D\t20-29_Admin\nD\t20-29_Admin/21_Taxes\nD\t20-29_Admin/21_Taxes/21.01_Returns\n\
+             F\t20-29_Admin/21_Taxes/21.01_Returns/.jdmeta\nF\t20-29_Admin/21_Taxes/21.02_Portal.url\n\
+             F\t20-29_Admin/21_Taxes/21.03_Scan.pdf\n"
+        );
+        let (full, warnings) = dump(&tree.roots[0], true);
+        assert!(warnings.is_empty(), "{:?}", warnings);
+        assert!(
+            full.contains("21.01_Returns/.jdmeta\tLOCATION=drawer\\t2\\n\n"),
+            "{}",
+            full
+        );
+        assert!(full.contains("21.03_Scan.pdf\n"), "{}", full);
+
+        let dest = td.path().join("Copy");
+        let applied = apply(&parse(&full).unwrap(), &dest).unwrap();
+        assert_eq!(applied.created.len(), 6);
+        let copy = dest.join("20-29_Admin/21_Taxes");
+        assert_eq!(
+            fs::read_to_string(copy.join("21.01_Returns/.jdmeta")).unwrap(),
+            "LOCATION=drawer\t2\n"
+        );
+        assert!(fs::read_to_string(copy.join("21.03_Scan.pdf"))
+            .unwrap()
+            .is_empty());
+        assert!(!copy.join("21.01_Returns/2024.pdf").exists());
+
+        // a second run leaves everything alone
+        let again = apply(&parse(&full).unwrap(), &dest).unwrap();
+        assert!(again.created.is_empty());
+        assert_eq!(again.existing.len(), 6);
+    }
+
+    #[test]
+    fn manifests_cannot_escape_the_destination() {
+        assert!(parse("D\t../elsewhere")
+            .unwrap_err()
+            .to_string()
+            .starts_with("line 1:"));
+        assert!(parse("# comment\n\nF\t/etc/passwd")
+            .unwrap_err()
+            .to_string()
+            .starts_with("line 3:"));
+        assert!(parse("X\ta").is_err());
+        assert_eq!(
+            parse("F\ta/b.txt\tone\\ntwo").unwrap(),
+            [Entry::File("a/b.txt".into(), Some("one\ntwo".into()))]
+        );
+    }
+}
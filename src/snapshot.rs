@@ -0,0 +1,128 @@
+//! Dated copies of an index, kept beside it in `.jd_snapshots/` when the
+//! config asks for them:
+//!
+//! ```json
+//! {"snapshots": {"keep": 30, "every": "1d"}}
+//! ```
+//!
+//! Each time the index is written it's copied to
+//! `.jd_snapshots/jd_index-STAMP.json` if the newest copy is at least
+//! `every` old, and copies beyond the newest `keep` are removed. `changes
+//! --since` compares a fresh scan with one of them.
+
+use crate::config::Snapshots;
+use crate::date;
+use anyhow::Result;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+pub const SNAPSHOT_DIR: &str = ".jd_snapshots";
+
+pub struct Snapshot {
+    /// Unix seconds, from the file name.
+    pub taken: u64,
+    pub path: PathBuf,
+}
+
+pub fn dir(index: &Path) -> PathBuf {
+    index.with_file_name(SNAPSHOT_DIR)
+}
+
+/// `jd_index` for `.jd_index.json`; snapshots of different index files
+/// sharing a folder keep apart by it.
+fn stem(index: &Path) -> String {
+    let stem = index.file_stem().unwrap_or_default().to_string_lossy();
+    stem.trim_start_matches('.').to_string()
+}
+
+/// The snapshots of `index`, oldest first.
+pub fn list(index: &Path) -> Vec<Snapshot> {
+    let prefix = format!("{}-", stem(index));
+    let mut out: Vec<Snapshot> = fs::read_dir(dir(index))
+        .into_iter()
+        .flatten()
+        .filter_map(|e| e.ok())
+        .filter_map(|e| {
+            let name = e.file_name().to_string_lossy().to_string();
+            let stamp = name.strip_prefix(&prefix)?.strip_suffix(".json")?;
+            Some(Snapshot {
+                taken: date::parse_stamp(stamp)?,
+                path: e.path(),
+            })
+        })
+        .collect();
+    out.sort_by_key(|s| s.taken);
+    out
+}
+
+/// Copy the index just written at `now` if a snapshot is due, then prune.
+/// Returns the new snapshot, if one was taken.
+pub fn rotate(index: &Path, policy: &Snapshots, now: u64) -> Result<Option<PathBuf>> {
+    if policy.keep == 0 {
+        return Ok(None);
+    }
+    let mut all = list(index);
+    let due = all
+        .last()
+        .is_none_or(|s| now.saturating_sub(s.taken) >= policy.every);
+    let mut taken = None;
+    if due {
+        fs::create_dir_all(dir(index))?;
+        let path = dir(index).join(format!("{}-{}.json", stem(index), date::stamp(now)));
+        fs::copy(index, &path)?;
+        all.push(Snapshot {
+            taken: now,
+            path: path.clone(),
+        });
+        taken = Some(path);
+    }
+    let excess = all.len().saturating_sub(policy.keep);
+    for s in &all[..excess] {
+        fs::remove_file(&s.path)?;
+    }
+    Ok(taken)
+}
+
+/// What "since `cutoff`" compares against: the newest snapshot taken at or
+/// before it, or the oldest there is when none goes back that far.
+pub fn since(index: &Path, cutoff: u64) -> Option<Snapshot> {
+    let mut all = list(index);
+    let at = all.iter().rposition(|s| s.taken <= cutoff).unwrap_or(0);
+    (!all.is_empty()).then(|| all.swap_remove(at))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rotation_is_spaced_and_bounded() {
+        let td = tempfile::tempdir().unwrap();
+        let index = td.path().join(".jd_index.json");
+        fs::write(&index, "{}").unwrap();
+        let policy = Snapshots {
+            keep: 2,
+            every: 86_400,
+        };
+        let day = 86_400;
+        let t0 = 1_767_225_600;
+
+        assert!(rotate(&index, &policy, t0).unwrap().is_some());
+        // too soon after the last one
+        assert!(rotate(&index, &policy, t0 + 3600).unwrap().is_none());
+        rotate(&index, &policy, t0 + day).unwrap();
+        let third = rotate(&index, &policy, t0 + 2 * day).unwrap().unwrap();
+        assert!(third.ends_with(".jd_snapshots/jd_index-20260103-000000.json"));
+        let taken: Vec<u64> = list(&index).iter().map(|s| s.taken).collect();
+        assert_eq!(taken, [t0 + day, t0 + 2 * day]);
+
+        assert_eq!(since(&index, t0 + day + 5).unwrap().taken, t0 + day);
+        // nothing that old: the oldest will do
+        assert_eq!(since(&index, t0).unwrap().taken, t0 + day);
+        // another index in the same folder has none
+        assert!(since(&td.path().join("other.json"), t0 + 9 * day).is_none());
+
+        let off = Snapshots::default();
+        assert!(rotate(&index, &off, t0 + 9 * day).unwrap().is_none());
+    }
+}
@@ -1,3 +1,5 @@
+use crate::model::Tree;
+use crate::schema::{self, Index};
 use crate::tsv::ExpandedState;
 use anyhow::Result;
 use serde::{Deserialize, Serialize};
@@ -28,6 +30,27 @@ pub fn load_state_or_default(path: Option<&PathBuf>) -> Result<ExpandedState> {
     })
 }
 
+/// Where the TUI leaves the tree it ended on, beside its fold state; the
+/// next session marks what changed against it.
+pub fn session_path(state: &Path) -> PathBuf {
+    state.with_extension("session.json")
+}
+
+/// The tree the last session on these roots ended on.
+pub fn load_session(state: &Path, roots: &[PathBuf]) -> Option<Tree> {
+    let tree = schema::read(&session_path(state)).ok()?.tree;
+    crate::io::same_roots(&tree, roots).then_some(tree)
+}
+
+pub fn save_session(state: &Path, tree: &Tree) -> Result<()> {
+    let path = session_path(state);
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(path, serde_json::to_vec(&Index::new(tree.clone()))?)?;
+    Ok(())
+}
+
 pub fn save_state(path: &Path, st: &ExpandedState) -> Result<()> {
     let ser = StateSerde {
         expanded: st.expanded.iter().cloned().collect(),
@@ -0,0 +1,170 @@
+//! Skeletons for new folders. A template is a directory under
+//! `.jdtemplates/` — at the root or in any folder on the way down to where
+//! the new folder goes — whose contents are copied into the new folder:
+//!
+//! ```text
+//! R/.jdtemplates/item/notes.md            # "# {{code}} {{title}}"
+//! R/.jdtemplates/item/data/
+//! R/.jdtemplates/item/{{code}}_refs.bib
+//! R/30-39_Research/.jdtemplates/item/...  # shadows the root one below 30-39
+//! ```
+//!
+//! `{{code}}`, `{{title}}` and `{{date}}` (YYYY-MM-DD) are filled in in file
+//! names and text contents. Templates named `item`, `category` and `range`
+//! are used by default for new folders of that kind.
+
+use crate::{date, plan::clean_title};
+use anyhow::{bail, Result};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+pub const TEMPLATES_DIR: &str = ".jdtemplates";
+
+/// Templates visible from `dir` (inside `root`), sorted by name. A template
+/// in a nearer `.jdtemplates/` shadows one of the same name further up.
+pub fn available(root: &Path, dir: &Path) -> Vec<(String, PathBuf)> {
+    let mut found: Vec<(String, PathBuf)> = Vec::new();
+    let mut cur = Some(dir);
+    while let Some(d) = cur.filter(|d| d.starts_with(root)) {
+        if let Ok(rd) = fs::read_dir(d.join(TEMPLATES_DIR)) {
+            for e in rd.filter_map(|e| e.ok()).filter(|e| e.path().is_dir()) {
+                let name = e.file_name().to_string_lossy().to_string();
+                if !found.iter().any(|(n, _)| *n == name) {
+                    found.push((name, e.path()));
+                }
+            }
+        }
+        cur = d.parent();
+    }
+    found.sort();
+    found
+}
+
+/// The template called `name` as seen from `dir`.
+pub fn find(root: &Path, dir: &Path, name: &str) -> Result<PathBuf> {
+    let all = available(root, dir);
+    match all.iter().find(|(n, _)| n == name) {
+        Some((_, p)) => Ok(p.clone()),
+        None if all.is_empty() => bail!("no templates here (add .jdtemplates/{}/)", name),
+        None => bail!(
+            "no template named {} (have: {})",
+            name,
+            all.iter()
+                .map(|(n, _)| n.as_str())
+                .collect::<Vec<_>>()
+                .join(", ")
+        ),
+    }
+}
+
+/// The template a new folder gets unless another is picked: `item`,
+/// `category` or `range` by the shape of its code, if one exists.
+pub fn default_for(root: &Path, dir: &Path, code: Option<&str>) -> Option<PathBuf> {
+    let name = match code? {
+        c if c.contains('-') => "range",
+        c if c.contains('.') => "item",
+        _ => "category",
+    };
+    find(root, dir, name).ok()
+}
+
+pub struct Vars {
+    pub code: String,
+    pub title: String,
+    pub date: String,
+}
+
+impl Vars {
+    /// Values for a new folder named `NN.MM_Some_Title`; the title reads
+    /// with spaces.
+    pub fn for_name(name: &str) -> Vars {
+        let (code, title) = crate::model::parse_item(name)
+            .map(|(c, t, _)| (c, t))
+            .or_else(|| crate::model::parse_category(name))
+            .or_else(|| crate::model::parse_range(name))
+            .unwrap_or_else(|| (String::new(), name.to_string()));
+        Vars {
+            code,
+            title: title.replace('_', " "),
+            date: date::date(date::now()),
+        }
+    }
+
+    pub fn fill(&self, s: &str) -> String {
+        s.replace("{{code}}", &self.code)
+            .replace("{{title}}", &self.title)
+            .replace("{{date}}", &self.date)
+    }
+
+    /// Like `fill`, with the title made safe for a file name (and refused
+    /// when nothing of it is left).
+    pub fn fill_name(&self, s: &str) -> Result<String> {
+        let title = match s.contains("{{title}}") {
+            true => clean_title(&self.title)?,
+            false => String::new(),
+        };
+        Ok(s.replace("{{code}}", &self.code)
+            .replace("{{title}}", &title)
+            .replace("{{date}}", &self.date))
+    }
+}
+
+/// Copy the template's contents into the existing folder `dest`. Existing
+/// files are left alone; binary files are copied byte for byte.
+pub fn instantiate(template: &Path, dest: &Path, vars: &Vars) -> Result<()> {
+    for e in fs::read_dir(template)? {
+        let e = e?;
+        let name = vars.fill_name(&e.file_name().to_string_lossy())?;
+        let target = dest.join(&name);
+        if e.file_type()?.is_dir() {
+            fs::create_dir_all(&target)?;
+            instantiate(&e.path(), &target, vars)?;
+        } else if !target.exists() {
+            let bytes = fs::read(e.path())?;
+            match String::from_utf8(bytes) {
+                Ok(text) => fs::write(&target, vars.fill(&text))?,
+                Err(raw) => fs::write(&target, raw.into_bytes())?,
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn nearest_template_wins_and_placeholders_fill() {
+        let td = tempfile::tempdir().unwrap();
+        let root = td.path().join("R");
+        let cat = root.join("30-39_Research/31_Papers");
+        fs::create_dir_all(root.join(".jdtemplates/item/data")).unwrap();
+        fs::create_dir_all(root.join(".jdtemplates/paper")).unwrap();
+        fs::create_dir_all(cat.join(".jdtemplates/item")).unwrap();
+        fs::write(root.join(".jdtemplates/item/notes.md"), "root").unwrap();
+        fs::write(
+            cat.join(".jdtemplates/item/{{code}}_notes.md"),
+            "# {{code}} {{title}}\n{{date}}\n",
+        )
+        .unwrap();
+        fs::write(cat.join(".jdtemplates/item/logo.bin"), [0xffu8, 0xfe, 0]).unwrap();
+
+        let names: Vec<String> = available(&root, &cat).into_iter().map(|(n, _)| n).collect();
+        assert_eq!(names, ["item", "paper"]);
+        let t = default_for(&root, &cat, Some("31.05")).unwrap();
+        assert_eq!(t, cat.join(".jdtemplates/item"));
+        assert!(default_for(&root, &cat, Some("32")).is_none());
+        assert!(find(&root, &cat, "missing").is_err());
+
+        let dest = cat.join("31.05_Two_Words");
+        fs::create_dir(&dest).unwrap();
+        let vars = Vars::for_name("31.05_Two_Words");
+        instantiate(&t, &dest, &vars).unwrap();
+        let notes = fs::read_to_string(dest.join("31.05_notes.md")).unwrap();
+        assert!(notes.starts_with("# 31.05 Two Words\n20"));
+        assert_eq!(fs::read(dest.join("logo.bin")).unwrap(), [0xff, 0xfe, 0]);
+        // the root template is shadowed, not merged
+        assert!(!dest.join("notes.md").exists());
+    }
+}
@@ -0,0 +1,228 @@
+//! One soft-delete bin per root: `ROOT/.jd_trash/`, with a manifest
+//! recording where each entry came from so it can be listed, restored and
+//! purged later.
+//!
+//! ```text
+//! R/.jd_trash/.manifest.json
+//! [{"name": "31.03_Note.txt", "original": "30-39_Research/31_Papers/31.03_Note.txt",
+//!   "code": "31.03", "deleted": 1760868000}]
+//! ```
+//!
+//! `original` is relative to the root, so a root can move without orphaning
+//! its trash. A second delete of the same name is stored under a timestamped
+//! name (`31.03_Note~20261019-101500.txt`).
+
+use crate::{date, lock, meta, model};
+use anyhow::{bail, Result};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+pub const TRASH_DIR: &str = ".jd_trash";
+const MANIFEST: &str = ".manifest.json";
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct TrashEntry {
+    /// File name inside `.jd_trash/`.
+    pub name: String,
+    /// Where it lived, relative to the root.
+    pub original: PathBuf,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub code: Option<String>,
+    /// Unix seconds.
+    pub deleted: u64,
+}
+
+pub fn trash_dir(root: &Path) -> PathBuf {
+    root.join(TRASH_DIR)
+}
+
+/// The root among `roots` that contains `p`.
+pub fn root_of<'a>(roots: &'a [PathBuf], p: &Path) -> Option<&'a PathBuf> {
+    roots.iter().find(|r| p.starts_with(r) && p != r.as_path())
+}
+
+pub fn list(root: &Path) -> Result<Vec<TrashEntry>> {
+    let path = trash_dir(root).join(MANIFEST);
+    match fs::read_to_string(&path) {
+        Ok(s) => Ok(serde_json::from_str(&s)?),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Vec::new()),
+        Err(e) => Err(e.into()),
+    }
+}
+
+fn save(root: &Path, entries: &[TrashEntry]) -> Result<()> {
+    let dir = trash_dir(root);
+    fs::create_dir_all(&dir)?;
+    let tmp = dir.join(".manifest.json.tmp");
+    fs::write(&tmp, serde_json::to_vec_pretty(entries)?)?;
+    fs::rename(&tmp, dir.join(MANIFEST))?;
+    Ok(())
+}
+
+/// Move `p` (somewhere under `root`) into the root's trash and record it.
+/// Returns the trashed path.
+pub fn put(root: &Path, p: &Path) -> Result<PathBuf> {
+    let _lock = lock::lock_roots(&[root.to_path_buf()])?;
+    let original = p
+        .strip_prefix(root)
+        .map_err(|_| anyhow::anyhow!("{} is not under {}", p.display(), root.display()))?
+        .to_path_buf();
+    let dir = trash_dir(root);
+    fs::create_dir_all(&dir)?;
+    let now = date::now();
+    let name = free_name(
+        &dir,
+        &p.file_name().unwrap().to_string_lossy(),
+        p.is_dir(),
+        now,
+    );
+    let target = dir.join(&name);
+    let mut entries = list(root)?;
+    fs::rename(p, &target)?;
+    meta::carry_sidecars(p, &target)?;
+    entries.push(TrashEntry {
+        code: code_of(&name_of(&original)),
+        name,
+        original,
+        deleted: now,
+    });
+    if let Err(e) = save(root, &entries) {
+        let _ = fs::rename(&target, p);
+        let _ = meta::carry_sidecars(&target, p);
+        return Err(e);
+    }
+    Ok(target)
+}
+
+/// Put the entry stored as `name` back where it came from (recreating
+/// missing parent folders). Returns the restored path.
+pub fn restore(root: &Path, name: &str) -> Result<PathBuf> {
+    let _lock = lock::lock_roots(&[root.to_path_buf()])?;
+    let mut entries = list(root)?;
+    let Some(i) = entries.iter().position(|e| e.name == name) else {
+        bail!("no trash entry named {}", name);
+    };
+    let dest = root.join(&entries[i].original);
+    if dest.exists() {
+        bail!("original path is occupied: {}", dest.display());
+    }
+    fs::create_dir_all(dest.parent().unwrap())?;
+    let stored = trash_dir(root).join(name);
+    fs::rename(&stored, &dest)?;
+    meta::carry_sidecars(&stored, &dest)?;
+    entries.remove(i);
+    save(root, &entries)?;
+    Ok(dest)
+}
+
+/// Permanently remove entries deleted at least `older_than` seconds ago
+/// (everything when None). Returns what was removed.
+pub fn purge(root: &Path, older_than: Option<u64>) -> Result<Vec<TrashEntry>> {
+    let now = date::now();
+    purge_where(root, |e| {
+        older_than.is_none_or(|age| now.saturating_sub(e.deleted) >= age)
+    })
+}
+
+/// Permanently remove the single entry stored as `name`.
+pub fn purge_entry(root: &Path, name: &str) -> Result<()> {
+    if purge_where(root, |e| e.name == name)?.is_empty() {
+        bail!("no trash entry named {}", name);
+    }
+    Ok(())
+}
+
+fn purge_where(root: &Path, pick: impl Fn(&TrashEntry) -> bool) -> Result<Vec<TrashEntry>> {
+    let _lock = lock::lock_roots(&[root.to_path_buf()])?;
+    let (gone, kept): (Vec<_>, Vec<_>) = list(root)?.into_iter().partition(|e| pick(e));
+    let dir = trash_dir(root);
+    for e in &gone {
+        let p = dir.join(&e.name);
+        match fs::symlink_metadata(&p) {
+            Ok(m) if m.is_dir() => fs::remove_dir_all(&p)?,
+            Ok(_) => fs::remove_file(&p)?,
+            Err(_) => {} // already gone by hand
+        }
+        for s in meta::sidecars(&p) {
+            let _ = fs::remove_file(s);
+        }
+    }
+    save(root, &kept)?;
+    Ok(gone)
+}
+
+fn name_of(p: &Path) -> String {
+    p.file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_default()
+}
+
+fn code_of(name: &str) -> Option<String> {
+    model::parse_range(name)
+        .or_else(|| model::parse_category(name))
+        .map(|(c, _)| c)
+        .or_else(|| model::parse_item(name).map(|(c, _, _)| c))
+}
+
+/// `name` if free in `dir`, else `stem~STAMP.ext` (then `-2`, `-3`, …).
+fn free_name(dir: &Path, name: &str, is_dir: bool, now: u64) -> String {
+    if !dir.join(name).exists() && name != MANIFEST {
+        return name.to_string();
+    }
+    let (stem, ext) = match name.rsplit_once('.') {
+        Some((s, e)) if !is_dir && !s.is_empty() => (s, format!(".{}", e)),
+        _ => (name, String::new()),
+    };
+    let base = format!("{}~{}", stem, date::stamp(now));
+    let mut cand = format!("{}{}", base, ext);
+    let mut n = 2;
+    while dir.join(&cand).exists() {
+        cand = format!("{}-{}{}", base, n, ext);
+        n += 1;
+    }
+    cand
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn put_restore_purge_round_trip() {
+        let td = tempfile::tempdir().unwrap();
+        let root = td.path().join("R");
+        let cat = root.join("30-39_Research/31_Papers");
+        fs::create_dir_all(cat.join("31.01_Thing")).unwrap();
+        fs::write(cat.join("31.03_Note.txt"), b"one").unwrap();
+
+        let first = put(&root, &cat.join("31.03_Note.txt")).unwrap();
+        assert_eq!(first, root.join(".jd_trash/31.03_Note.txt"));
+        // same name again: stored under a timestamped name
+        fs::write(cat.join("31.03_Note.txt"), b"two").unwrap();
+        let second = put(&root, &cat.join("31.03_Note.txt")).unwrap();
+        let second_name = name_of(&second);
+        assert!(second_name.starts_with("31.03_Note~"));
+        assert!(second_name.ends_with(".txt"));
+        put(&root, &cat.join("31.01_Thing")).unwrap();
+
+        let entries = list(&root).unwrap();
+        assert_eq!(entries.len(), 3);
+        assert_eq!(entries[0].code.as_deref(), Some("31.03"));
+        assert_eq!(
+            entries[0].original,
+            Path::new("30-39_Research/31_Papers/31.03_Note.txt")
+        );
+
+        // restore the second copy; the first can't go back while it's there
+        let back = restore(&root, &second_name).unwrap();
+        assert_eq!(fs::read(&back).unwrap(), b"two");
+        assert!(restore(&root, "31.03_Note.txt").is_err());
+
+        // nothing is a day old yet; purging everything empties the manifest
+        assert!(purge(&root, Some(86_400)).unwrap().is_empty());
+        assert_eq!(purge(&root, None).unwrap().len(), 2);
+        assert!(list(&root).unwrap().is_empty());
+        assert!(!root.join(".jd_trash/31.01_Thing").exists());
+    }
+}
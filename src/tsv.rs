@@ -1,118 +1,172 @@
-use crate::model::{Node, NodeType, Tree};
-use regex::Regex;
+//! The `tree` listing. Rows and search are the TUI's (`ui::rows`,
+//! `ui::search`), so a query lists what typing it in the TUI shows: AND-ed
+//! fuzzy atoms over "CODE Title", with unmatched ancestors as context rows.
+
+use crate::model::{NodeType, Tree};
+use crate::ui::rows::{self, Row};
+use crate::ui::search::{self, Search};
+use serde::Serialize;
 use std::collections::BTreeSet;
 
-pub fn flatten_to_tsv(
-    tree: &Tree,
-    filter: Option<&str>,
-    expanded: &ExpandedState,
-    show_all: bool,
-    collapse_root: bool,
-) -> Vec<String> {
-    let mut lines = Vec::new();
-    let matcher = filter.map(|q| build_fuzzy(q));
-    for root in &tree.roots {
-        push_node(
-            &mut lines,
-            root,
-            0,
-            None,
-            matcher.as_ref(),
-            expanded,
-            show_all,
-            collapse_root,
-        );
-    }
-    lines
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Options<'a> {
+    /// Only rows matching this (and their context); None or "" lists all.
+    pub query: Option<&'a str>,
+    /// Ignore fold state.
+    pub show_all: bool,
+    /// Fold the roots too.
+    pub collapse_root: bool,
+    /// Matches best first, without context rows (which only make sense in
+    /// tree order).
+    pub rank: bool,
 }
 
-fn glyph(is_dir_like: bool, expanded: bool) -> &'static str {
-    if is_dir_like {
-        if expanded {
-            "▾"
-        } else {
-            "▸"
-        }
-    } else {
-        " "
-    }
+/// One listed row; the JSON formats print it as is, `tsv_line` as columns.
+#[derive(Clone, Debug, Serialize)]
+pub struct Line {
+    #[serde(rename = "type")]
+    pub kind: &'static str,
+    pub id: String,
+    pub parent_id: Option<String>,
+    pub depth: usize,
+    pub code: Option<String>,
+    pub title: String,
+    /// A link's URL, else the path.
+    pub target: String,
+    pub path: String,
+    pub expanded: bool,
+    /// "match" or "context" while there is a query.
+    #[serde(rename = "match")]
+    pub hit: Option<&'static str>,
+    pub score: Option<u32>,
 }
 
-fn push_node(
-    lines: &mut Vec<String>,
-    node: &Node,
-    depth: usize,
-    parent_id: Option<&str>,
-    matcher: Option<&Regex>,
-    expanded: &ExpandedState,
-    show_all: bool,
-    collapse_root: bool,
-) {
-    let is_dir_like = matches!(
-        node.node_type,
-        NodeType::Range | NodeType::Category | NodeType::ItemDir | NodeType::Other
-    );
-    let is_root = depth == 0;
-    let is_expanded = if show_all {
-        true
-    } else if is_root {
-        !collapse_root
-    } else {
-        expanded.is_expanded(&node.id)
+pub fn list(tree: &Tree, expanded: &ExpandedState, opts: &Options) -> Vec<Line> {
+    let rows = rows::flatten(tree);
+    let is_expanded = |r: &Row| {
+        if opts.show_all {
+            true
+        } else if r.depth == 0 {
+            !opts.collapse_root
+        } else {
+            expanded.is_expanded(&r.id)
+        }
     };
-    let code_prefix = node.code.as_deref().unwrap_or("");
-    let space = if code_prefix.is_empty() { "" } else { " " };
-    let display = format!(
-        "{}{} {}{}{}",
-        "  ".repeat(depth),
-        glyph(is_dir_like, is_expanded),
-        code_prefix,
-        space,
-        node.title
-    );
-    let id = &node.id;
-    let typ = match node.node_type {
-        NodeType::Range | NodeType::Category | NodeType::ItemDir | NodeType::Other => "dir",
-        NodeType::File => "file",
-        NodeType::Link => "link",
+    // every ancestor unfolded
+    let shown: Vec<bool> = rows
+        .iter()
+        .map(|r| {
+            let mut p = r.parent_idx;
+            while let Some(i) = p {
+                if !is_expanded(&rows[i]) {
+                    return false;
+                }
+                p = rows[i].parent_idx;
+            }
+            true
+        })
+        .collect();
+    let line = |i: usize, hit: Option<&'static str>, score: Option<u32>| {
+        let r = &rows[i];
+        Line {
+            kind: match r.node_type {
+                NodeType::File => "file",
+                NodeType::Link => "link",
+                _ => "dir",
+            },
+            id: r.id.clone(),
+            parent_id: r.parent_idx.map(|p| rows[p].id.clone()),
+            depth: r.depth,
+            code: r.code.clone(),
+            title: r.title.clone(),
+            target: match r.node_type {
+                NodeType::Link => r.url.clone().unwrap_or_else(|| r.path.clone()),
+                _ => r.path.clone(),
+            },
+            path: r.path.clone(),
+            expanded: r.dir_like && is_expanded(r),
+            hit,
+            score,
+        }
     };
-    let parent_id = parent_id.unwrap_or("");
-    let code = node.code.as_deref().unwrap_or("");
-    let path_or_url = match node.node_type {
-        NodeType::Link => node.url.as_deref().unwrap_or(&node.path),
-        _ => &node.path,
+    let Some(query) = opts.query.filter(|q| !q.is_empty()) else {
+        return (0..rows.len())
+            .filter(|&i| shown[i])
+            .map(|i| line(i, None, None))
+            .collect();
     };
-    let hay = format!("{} {} {}", display, code, path_or_url);
-    if matcher.map(|re| re.is_match(&hay)).unwrap_or(true) {
-        lines.push(format!(
-            "{}\t{}\t{}\t{}\t{}",
-            typ, id, display, path_or_url, parent_id
-        ));
+    let mut scored: Vec<(usize, u32)> = Search::default()
+        .scored(&rows, query)
+        .into_iter()
+        .filter(|&(i, _)| shown[i])
+        .collect();
+    if opts.rank {
+        // stable: equal scores keep tree order
+        scored.sort_by_key(|&(_, s)| std::cmp::Reverse(s));
+        return scored
+            .into_iter()
+            .map(|(i, s)| line(i, Some("match"), Some(s)))
+            .collect();
     }
-    if is_dir_like && is_expanded {
-        for ch in &node.children {
-            push_node(
-                lines,
-                ch,
-                depth + 1,
-                Some(id),
-                matcher,
-                expanded,
-                show_all,
-                collapse_root,
-            );
-        }
+    let matched: Vec<usize> = scored.iter().map(|&(i, _)| i).collect();
+    let context = search::context(&rows, &matched);
+    let mut out: Vec<(usize, Line)> = scored
+        .into_iter()
+        .map(|(i, s)| (i, line(i, Some("match"), Some(s))))
+        .chain(
+            context
+                .into_iter()
+                .map(|i| (i, line(i, Some("context"), None))),
+        )
+        .collect();
+    out.sort_by_key(|(i, _)| *i);
+    out.into_iter().map(|(_, l)| l).collect()
+}
+
+fn glyph(l: &Line) -> &'static str {
+    match (l.kind, l.expanded) {
+        ("dir", true) => "▾",
+        ("dir", false) => "▸",
+        _ => " ",
     }
 }
 
-fn build_fuzzy(q: &str) -> Regex {
-    // simple subsequence fuzzy match: q chars appear in order, ignoring other chars
-    let mut pat = String::from("(?i)");
-    for ch in q.chars() {
-        let esc = regex::escape(&ch.to_string());
-        pat.push_str(&format!(".*{}", esc));
+/// Tab, newline, carriage return and backslash escaped as `\t`, `\n`, `\r`
+/// and `\\`, so a name can't break the columns.
+fn field(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '\t' => out.push_str("\\t"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\\' => out.push_str("\\\\"),
+            c => out.push(c),
+        }
     }
-    Regex::new(&pat).unwrap()
+    out
+}
+
+/// `type id display target parent_id code title match score`, tab-separated;
+/// display is the indented "▸ CODE Title" the old fzf pipeline showed.
+/// Empty fields stay in place, so the columns never shift.
+pub fn tsv_line(l: &Line) -> String {
+    let display = match &l.code {
+        Some(c) => format!("{}{} {} {}", "  ".repeat(l.depth), glyph(l), c, l.title),
+        None => format!("{}{} {}", "  ".repeat(l.depth), glyph(l), l.title),
+    };
+    [
+        l.kind.to_string(),
+        field(&l.id),
+        field(&display),
+        field(&l.target),
+        field(l.parent_id.as_deref().unwrap_or("")),
+        field(l.code.as_deref().unwrap_or("")),
+        field(&l.title),
+        l.hit.unwrap_or("").to_string(),
+        l.score.map(|s| s.to_string()).unwrap_or_default(),
+    ]
+    .join("\t")
 }
 
 pub struct ExpandedState {
@@ -128,3 +182,59 @@ impl ExpandedState {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fs_walk;
+    use std::fs;
+
+    #[test]
+    fn search_matches_the_tui_with_context_and_ranking() {
+        let td = tempfile::tempdir().unwrap();
+        let roots = [td.path().join("R")];
+        let cat = roots[0].join("50-59_Travel/54_Trips");
+        fs::create_dir_all(cat.join("54.01_Tax letters")).unwrap();
+        fs::create_dir_all(cat.join("54.02_Letters to tax office")).unwrap();
+        fs::create_dir_all(cat.join("54.03_Maps")).unwrap();
+        let tree = fs_walk::scan_roots(&roots).unwrap();
+        let folded = ExpandedState {
+            expanded: BTreeSet::new(),
+        };
+
+        // AND-ed atoms in any order, ancestors as context, folds ignored
+        let opts = Options {
+            query: Some("letters tax"),
+            show_all: true,
+            ..Options::default()
+        };
+        let lines = list(&tree, &folded, &opts);
+        let got: Vec<(Option<&str>, Option<&str>)> =
+            lines.iter().map(|l| (l.code.as_deref(), l.hit)).collect();
+        assert_eq!(
+            got,
+            [
+                (None, Some("context")),
+                (Some("50-59"), Some("context")),
+                (Some("54"), Some("context")),
+                (Some("54.01"), Some("match")),
+                (Some("54.02"), Some("match")),
+            ]
+        );
+        let ranked = list(&tree, &folded, &Options { rank: true, ..opts });
+        assert_eq!(ranked.len(), 2);
+        assert!(ranked[0].score >= ranked[1].score);
+
+        // without a query the fold state holds: only the root and its ranges
+        let lines = list(&tree, &folded, &Options::default());
+        assert_eq!(lines.len(), 2);
+        let cols: Vec<String> = tsv_line(&lines[1])
+            .split('\t')
+            .map(str::to_string)
+            .collect();
+        assert_eq!(cols.len(), 9);
+        assert_eq!(cols[0], "dir");
+        assert_eq!(cols[2], "  ▸ 50-59 Travel");
+        assert_eq!(cols[4], lines[0].id);
+    }
+}
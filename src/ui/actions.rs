@@ -1,3 +1,5 @@
+use crate::model::NodeType;
+use anyhow::{bail, Result};
 use std::{fmt, path::PathBuf};
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub enum FinalAction {
@@ -5,6 +7,66 @@ pub enum FinalAction {
     Edit(PathBuf),
     Open(String),
 }
+/// How `ui` prints its action on stdout. `Line` is `ACTION<TAB>TARGET`,
+/// which a path holding a tab or newline breaks; `Nul` ends both fields
+/// with a NUL byte and `Json` is one `{"action", "target"}` object (JSON
+/// strings can't carry a path that isn't UTF-8, so that's an error).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Format {
+    Line,
+    Nul,
+    Json,
+}
+impl FinalAction {
+    /// What Enter does on a node: a file is edited, a link opened, anything
+    /// else cd'd into. `open` decides the same way.
+    pub fn for_node(node_type: &NodeType, path: &str, url: Option<&str>) -> FinalAction {
+        match node_type {
+            NodeType::File => Self::Edit(path.into()),
+            NodeType::Link => Self::Open(url.unwrap_or(path).to_string()),
+            _ => Self::Cd(path.into()),
+        }
+    }
+    pub fn verb(&self) -> &'static str {
+        match self {
+            Self::Cd(_) => "cd",
+            Self::Edit(_) => "edit",
+            Self::Open(_) => "open",
+        }
+    }
+    fn target(&self) -> Vec<u8> {
+        match self {
+            #[cfg(unix)]
+            Self::Cd(p) | Self::Edit(p) => {
+                use std::os::unix::ffi::OsStrExt;
+                p.as_os_str().as_bytes().to_vec()
+            }
+            #[cfg(not(unix))]
+            Self::Cd(p) | Self::Edit(p) => p.to_string_lossy().into_owned().into_bytes(),
+            Self::Open(u) => u.clone().into_bytes(),
+        }
+    }
+    /// The bytes `ui` prints, trailing separator included.
+    pub fn encode(&self, format: Format) -> Result<Vec<u8>> {
+        Ok(match format {
+            Format::Line => format!("{}\n", self).into_bytes(),
+            Format::Nul => {
+                let mut out = self.verb().as_bytes().to_vec();
+                out.push(0);
+                out.extend(self.target());
+                out.push(0);
+                out
+            }
+            Format::Json => {
+                let Ok(target) = String::from_utf8(self.target()) else {
+                    bail!("{} is not valid UTF-8; use --format nul", self);
+                };
+                let v = serde_json::json!({"action": self.verb(), "target": target});
+                format!("{}\n", v).into_bytes()
+            }
+        })
+    }
+}
 impl fmt::Display for FinalAction {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
@@ -2,19 +2,21 @@ use super::{
     actions::FinalAction,
     prompt::LineEditor,
     rows::{self, Row},
-    search::Search,
+    search::{self, Search},
 };
 use crate::{
-    fs_walk, meta,
+    diff::{self, Change},
+    fs_walk, hooks, meta,
     model::{self, NodeType},
     mutate,
     plan::{self, CreatePlan, MergeAction, MergePlan, MovePlan, PlanKind, RenumberPlan},
-    state,
+    state, template,
+    trash::{self, TrashEntry},
     tsv::ExpandedState,
 };
 use anyhow::Result;
 use ratatui::crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 use std::path::PathBuf;
 
 /// What a key press means for the event loop.
@@ -41,7 +43,7 @@ pub enum PromptKind {
     },
     /// Follow-up URL prompt after forcing kind=Link on input without a URL.
     LinkUrl {
-        plan: CreatePlan,
+        plan: Box<CreatePlan>,
         input: String,
         anchor_id: String,
     },
@@ -50,6 +52,16 @@ pub enum PromptKind {
     MetaAdd {
         id: String,
     },
+    /// New code for an item, prefilled with the current one.
+    Recode {
+        id: String,
+    },
+}
+
+/// A disk change ^Z can reverse.
+pub enum UndoStep {
+    Delete { trash: PathBuf },
+    Recode(RenumberPlan),
 }
 
 pub enum PendingOp {
@@ -75,6 +87,9 @@ pub enum PendingOp {
         drawers: usize,
     },
     Merge(MergePlan),
+    /// An item recoded to a code the user typed.
+    Recode(RenumberPlan),
+    Wrap(plan::WrapPlan),
 }
 
 /// One colliding entry in the duplicate-resolution wizard.
@@ -117,6 +132,13 @@ pub enum Mode {
         id: String,
         cursor: usize,
     },
+    /// Trashed entries of every root, newest first; `purge` while waiting for
+    /// y to delete the highlighted one for good.
+    Trash {
+        entries: Vec<(PathBuf, TrashEntry)>,
+        cursor: usize,
+        purge: bool,
+    },
     /// Resolve duplicate codes: pick which entry of each group to renumber.
     Duplicates {
         groups: Vec<DupGroup>,
@@ -139,14 +161,69 @@ pub struct App {
     pub query: String,
     pub cursor: usize,
     pub mode: Mode,
-    pub last_delete: Option<(PathBuf, PathBuf)>, // (trash, original)
+    /// Reversible changes made this session, newest last; ^Z pops one.
+    pub undo: Vec<UndoStep>,
     pub status: Option<String>,
     pub search: Search,
+    /// The index the session started from, until a scan replaces it.
+    pub cached: Option<crate::model::Tree>,
+    /// A background scan that arrived while a dialog was open.
+    pub incoming: Option<crate::model::Tree>,
+    /// How nodes differ from the cached index (by id).
+    pub marks: HashMap<String, Change>,
+    /// Nodes gone since the cached index, shown under their old parent.
+    pub ghosts: Vec<(String, crate::model::Node)>,
+    /// The tree the previous session ended on. When there is one the first
+    /// scan is marked against it instead of the cached index, so changes
+    /// made outside the TUI in between show too.
+    pub last_session: Option<crate::model::Tree>,
+    /// Puts a line on screen while a long operation blocks the event loop,
+    /// such as a move across filesystems. The terminal loop sets it; it does
+    /// nothing otherwise.
+    pub report: fn(&str),
 }
 
 impl App {
     pub fn new(roots: Vec<PathBuf>, state_path: PathBuf) -> Result<Self> {
         let tree = fs_walk::scan_roots(&roots)?;
+        Self::with_tree(roots, state_path, tree)
+    }
+
+    /// Start from the last `.jd_index.json` for these roots so the first
+    /// frame needs no scan; `merge_scan` brings in the real one. Without a
+    /// usable index this is `new`.
+    pub fn from_cache(roots: Vec<PathBuf>, state_path: PathBuf) -> Result<Self> {
+        let last = state::load_session(&state_path, &roots);
+        let Some(tree) = crate::io::read_cached(&roots) else {
+            let mut app = Self::new(roots, state_path)?;
+            if let Some(old) = last {
+                app.mark(&old);
+                app.rows = app.marked_rows();
+                app.filter();
+            }
+            return Ok(app);
+        };
+        let mut app = Self::with_tree(roots, state_path, tree.clone())?;
+        app.cached = Some(tree);
+        app.last_session = last;
+        Ok(app)
+    }
+
+    /// Keep the tree this session ends on for the next one to mark against.
+    /// Not while still on the cached index: this session's marks were never
+    /// shown, so the older baseline stays.
+    pub fn end_session(&self) -> Result<()> {
+        if self.stale() {
+            return Ok(());
+        }
+        state::save_session(&self.state_path, &self.tree)
+    }
+
+    fn with_tree(
+        roots: Vec<PathBuf>,
+        state_path: PathBuf,
+        tree: crate::model::Tree,
+    ) -> Result<Self> {
         let expanded = state::load_state_or_default(Some(&state_path))?;
         let rows = rows::flatten(&tree);
         let visible = rows::visible(&rows, &expanded);
@@ -161,12 +238,61 @@ impl App {
             query: String::new(),
             cursor: 0,
             mode: Mode::Browse,
-            last_delete: None,
+            undo: Vec::new(),
             status: None,
             search: Search::default(),
+            cached: None,
+            incoming: None,
+            marks: HashMap::new(),
+            ghosts: Vec::new(),
+            last_session: None,
+            report: |_| {},
         })
     }
 
+    /// Still showing the cached index.
+    pub fn stale(&self) -> bool {
+        self.cached.is_some()
+    }
+
+    /// Take in a background scan. Ignored once a rescan after a change has
+    /// already replaced the cached tree; held back while a dialog has row
+    /// indices of its own.
+    pub fn merge_scan(&mut self, scanned: Result<crate::model::Tree>) {
+        if self.cached.is_none() {
+            return;
+        }
+        match scanned {
+            Err(e) => {
+                self.cached = None;
+                self.status = Some(format!("rescan failed, showing the cached index: {}", e));
+            }
+            Ok(tree) if matches!(self.mode, Mode::Browse) => {
+                let keep = self.selected().map(|r| r.id.clone());
+                let since_session = self.last_session.is_some();
+                self.install(tree, keep.as_deref());
+                let count = |c| self.marks.values().filter(|m| **m == c).count();
+                let (new, changed, gone) = (
+                    count(Change::Added),
+                    count(Change::Changed),
+                    count(Change::Removed),
+                );
+                self.status = Some(match (new + changed + gone, since_session) {
+                    (0, false) => "rescanned · the index was current".into(),
+                    (0, true) => "rescanned · nothing changed since the last session".into(),
+                    (_, since) => format!(
+                        "rescanned · {} new · {} changed · {} gone{}",
+                        new,
+                        changed,
+                        gone,
+                        if since { " since the last session" } else { "" }
+                    ),
+                });
+            }
+            Ok(tree) => self.incoming = Some(tree),
+        }
+    }
+
     pub fn selected(&self) -> Option<&Row> {
         self.visible
             .get(self.cursor)
@@ -183,16 +309,7 @@ impl App {
             rows::visible(&self.rows, &self.expanded)
         } else {
             let matched = self.search.matched(&self.rows, &self.query);
-            let hit: HashSet<usize> = matched.iter().copied().collect();
-            for &m in &matched {
-                let mut p = self.rows[m].parent_idx;
-                while let Some(i) = p {
-                    if !hit.contains(&i) {
-                        self.context.insert(i);
-                    }
-                    p = self.rows[i].parent_idx;
-                }
-            }
+            self.context = search::context(&self.rows, &matched);
             let mut all = matched;
             all.extend(self.context.iter().copied());
             all.sort_unstable();
@@ -244,8 +361,21 @@ impl App {
     /// Rescan the filesystem and, if `select` matches a row id or path, expand
     /// its ancestors so it is visible and put the cursor on it.
     fn rescan(&mut self, select: Option<&str>) -> Result<()> {
-        self.tree = fs_walk::scan_roots(&self.roots)?;
-        self.rows = rows::flatten(&self.tree);
+        let tree = fs_walk::scan_roots(&self.roots)?;
+        self.install(tree, select);
+        Ok(())
+    }
+
+    /// Show `tree`, marked against the last session or the cached index if
+    /// this is the first scan since starting from it.
+    fn install(&mut self, tree: crate::model::Tree, select: Option<&str>) {
+        self.incoming = None;
+        self.tree = tree;
+        if let Some(cached) = self.cached.take() {
+            let old = self.last_session.take().unwrap_or(cached);
+            self.mark(&old);
+        }
+        self.rows = self.marked_rows();
         if let Some(key) = select {
             if let Some(ri) = self.rows.iter().position(|r| r.id == key || r.path == key) {
                 let mut p = self.rows[ri].parent_idx;
@@ -272,7 +402,47 @@ impl App {
                 self.cursor = pos;
             }
         }
-        Ok(())
+    }
+
+    /// How the shown tree differs from `old`.
+    fn mark(&mut self, old: &crate::model::Tree) {
+        self.marks = diff::changes(old, &self.tree);
+        self.ghosts = diff::removed(old, &self.tree)
+            .into_iter()
+            .map(|(parent, n)| (parent.to_string(), n.clone()))
+            .collect();
+    }
+
+    /// Rows of the tree plus the ghosts of removed nodes, carrying marks.
+    fn marked_rows(&self) -> Vec<Row> {
+        let mut shown = std::borrow::Cow::Borrowed(&self.tree);
+        for (parent, ghost) in &self.ghosts {
+            fn attach(
+                n: &mut crate::model::Node,
+                parent: &str,
+                ghost: &crate::model::Node,
+            ) -> bool {
+                if n.id == parent {
+                    if !n.children.iter().any(|c| c.id == ghost.id) {
+                        n.children.push(ghost.clone());
+                    }
+                    return true;
+                }
+                n.children.iter_mut().any(|c| attach(c, parent, ghost))
+            }
+            let mut tree = shown.into_owned();
+            for r in &mut tree.roots {
+                if attach(r, parent, ghost) {
+                    break;
+                }
+            }
+            shown = std::borrow::Cow::Owned(tree);
+        }
+        let mut rows = rows::flatten(&shown);
+        for r in &mut rows {
+            r.change = self.marks.get(&r.id).copied();
+        }
+        rows
     }
 
     fn save_folds(&self) {
@@ -361,6 +531,97 @@ impl App {
             .collect()
     }
 
+    /// Every root's trash, most recently deleted first.
+    fn trash_entries(&self) -> Vec<(PathBuf, TrashEntry)> {
+        let mut all: Vec<(PathBuf, TrashEntry)> = self
+            .roots
+            .iter()
+            .flat_map(|r| {
+                trash::list(r)
+                    .unwrap_or_default()
+                    .into_iter()
+                    .map(move |e| (r.clone(), e))
+            })
+            .collect();
+        all.sort_by_key(|(_, e)| std::cmp::Reverse(e.deleted));
+        all
+    }
+
+    /// Open the trash view at `cursor` (or report that it is empty).
+    fn enter_trash(&mut self, cursor: usize) {
+        let entries = self.trash_entries();
+        if entries.is_empty() {
+            self.mode = Mode::Browse;
+            self.status = Some("trash is empty".into());
+            return;
+        }
+        let cursor = cursor.min(entries.len() - 1);
+        self.mode = Mode::Trash {
+            entries,
+            cursor,
+            purge: false,
+        };
+    }
+
+    fn on_trash(
+        &mut self,
+        entries: Vec<(PathBuf, TrashEntry)>,
+        mut cursor: usize,
+        purge: bool,
+        k: KeyEvent,
+    ) {
+        let (root, entry) = &entries[cursor];
+        if purge {
+            if matches!(k.code, KeyCode::Char('y') | KeyCode::Enter) {
+                match mutate::purge_trashed(root, &entry.name) {
+                    Ok(()) => {
+                        self.status = Some(format!("purged {}", entry.name));
+                        self.enter_trash(cursor);
+                    }
+                    Err(e) => self.message(e.to_string()),
+                }
+            } else {
+                self.mode = Mode::Trash {
+                    entries,
+                    cursor,
+                    purge: false,
+                };
+            }
+            return;
+        }
+        match k.code {
+            KeyCode::Esc => return,
+            KeyCode::Enter | KeyCode::Char('r') => {
+                match mutate::restore_trashed(&self.roots, root, &entry.name) {
+                    Ok(restored) => {
+                        self.query.clear();
+                        let key = restored.to_string_lossy().to_string();
+                        let _ = self.rescan(Some(&key));
+                        self.status = Some(format!("restored {}", key));
+                    }
+                    Err(e) => self.message(e.to_string()),
+                }
+                return;
+            }
+            KeyCode::Char('x') => {
+                self.mode = Mode::Trash {
+                    entries,
+                    cursor,
+                    purge: true,
+                };
+                return;
+            }
+            KeyCode::Up => cursor = cursor.saturating_sub(1),
+            KeyCode::Down => cursor = (cursor + 1).min(entries.len() - 1),
+            _ => {}
+        }
+        self.mode = Mode::Trash {
+            entries,
+            cursor,
+            purge,
+        };
+    }
+
     /// Enter the wizard (or report that there is nothing to fix).
     fn enter_duplicates(&mut self) {
         let groups = self.duplicate_groups();
@@ -460,6 +721,26 @@ impl App {
     }
 
     pub fn update(&mut self, k: KeyEvent) -> Option<Outcome> {
+        let out = self.dispatch(k);
+        if matches!(self.mode, Mode::Browse) {
+            if let Some(tree) = self.incoming.take() {
+                self.merge_scan(Ok(tree));
+            }
+        }
+        // The change itself went through; the status line says a post-hook
+        // didn't.
+        let failed = hooks::take_failures();
+        if !failed.is_empty() {
+            let note = failed.join("; ");
+            self.status = Some(match self.status.take() {
+                Some(s) => format!("{} · {}", s, note),
+                None => note,
+            });
+        }
+        out
+    }
+
+    fn dispatch(&mut self, k: KeyEvent) -> Option<Outcome> {
         // Take the mode out so handlers can own its state and set the next
         // mode without fighting the borrow checker.
         let mode = std::mem::replace(&mut self.mode, Mode::Browse);
@@ -487,6 +768,14 @@ impl App {
                 self.on_duplicates(groups, gi, cursor, k);
                 None
             }
+            Mode::Trash {
+                entries,
+                cursor,
+                purge,
+            } => {
+                self.on_trash(entries, cursor, purge, k);
+                None
+            }
             // Message and Help are dismissed by any key.
             Mode::Message { .. } | Mode::Help => None,
         }
@@ -559,6 +848,17 @@ impl App {
 
     fn on_browse(&mut self, k: KeyEvent) -> Option<Outcome> {
         self.status = None;
+        let acts = k.code == KeyCode::Enter
+            || k.modifiers == KeyModifiers::CONTROL
+                && matches!(
+                    k.code,
+                    KeyCode::Char('n' | 'r' | 'v' | 'x' | 'l' | 'e' | 'o' | 'w')
+                );
+        let gone = self.selected().map(|r| r.change) == Some(Some(Change::Removed));
+        if acts && gone {
+            self.message("gone since the cached index — nothing to act on");
+            return None;
+        }
         if k.modifiers == KeyModifiers::CONTROL {
             match k.code {
                 KeyCode::Char('q') | KeyCode::Char('c') => return Some(Outcome::Quit),
@@ -634,6 +934,28 @@ impl App {
                         }
                     }
                 }
+                KeyCode::Char('w') => {
+                    if let Some(r) = self.selected() {
+                        let id = r.id.clone();
+                        let kind = model::find_node(&self.tree, &id).map(|n| &n.node_type);
+                        let planned = match kind {
+                            Some(NodeType::File | NodeType::Link) => {
+                                Some(plan::plan_wrap(&self.tree, &id))
+                            }
+                            Some(NodeType::ItemDir) => Some(plan::plan_unwrap(&self.tree, &id)),
+                            _ => None,
+                        };
+                        match planned {
+                            Some(Ok(p)) => {
+                                self.mode = Mode::Confirm {
+                                    pending: PendingOp::Wrap(p),
+                                }
+                            }
+                            Some(Err(e)) => self.message(e.to_string()),
+                            None => self.message("only file items and one-file item folders wrap"),
+                        }
+                    }
+                }
                 KeyCode::Char('f') => self.enter_duplicates(),
                 KeyCode::Char('k') => self.mode = Mode::Help,
                 KeyCode::Char('l') => {
@@ -665,18 +987,57 @@ impl App {
                         }
                     }
                 }
-                KeyCode::Char('z') => match self.last_delete.take() {
-                    Some((trash, orig)) => match mutate::undo_delete(&self.roots, &trash, &orig) {
-                        Ok(()) => {
+                KeyCode::Char('z') => {
+                    let Some(step) = self.undo.pop() else {
+                        self.status = Some("nothing to undo".into());
+                        return None;
+                    };
+                    let result = match &step {
+                        UndoStep::Delete { trash } => {
+                            mutate::undo_delete(&self.roots, trash).map(|restored| {
+                                let key = restored.to_string_lossy().to_string();
+                                (key.clone(), format!("restored {}", key))
+                            })
+                        }
+                        UndoStep::Recode(p) => mutate::undo_renumber(&self.roots, p).map(|_| {
+                            let key = p.src_path.to_string_lossy().to_string();
+                            (
+                                key,
+                                format!("recode {} → {} undone", p.old_code, p.new_code),
+                            )
+                        }),
+                    };
+                    match result {
+                        Ok((key, status)) => {
                             self.query.clear();
-                            let key = orig.to_string_lossy().to_string();
                             let _ = self.rescan(Some(&key));
-                            self.status = Some(format!("restored {}", key));
+                            self.status = Some(status);
                         }
-                        Err(e) => self.message(e.to_string()),
-                    },
-                    None => self.status = Some("nothing to undo".into()),
-                },
+                        Err(e) => {
+                            self.undo.push(step);
+                            self.message(e.to_string());
+                        }
+                    }
+                }
+                KeyCode::Char('t') => self.enter_trash(0),
+                KeyCode::Char('o') => {
+                    if let Some(r) = self.selected() {
+                        match (&r.code, &r.node_type) {
+                            (Some(code), t)
+                                if !matches!(t, NodeType::Range | NodeType::Category) =>
+                            {
+                                self.mode = Mode::Prompt {
+                                    kind: PromptKind::Recode { id: r.id.clone() },
+                                    editor: LineEditor::new(code),
+                                };
+                            }
+                            (Some(_), _) => self.message(
+                                "ranges and categories: jd-helper recode --id ID --code CODE",
+                            ),
+                            (None, _) => self.message("no JD code to change"),
+                        }
+                    }
+                }
                 _ => {}
             }
             return None;
@@ -729,13 +1090,11 @@ impl App {
             }
             KeyCode::Enter => {
                 if let Some(r) = self.selected() {
-                    return Some(Outcome::Act(match r.node_type {
-                        NodeType::File => FinalAction::Edit(r.path.clone().into()),
-                        NodeType::Link => {
-                            FinalAction::Open(r.url.clone().unwrap_or_else(|| r.path.clone()))
-                        }
-                        _ => FinalAction::Cd(r.path.clone().into()),
-                    }));
+                    return Some(Outcome::Act(FinalAction::for_node(
+                        &r.node_type,
+                        &r.path,
+                        r.url.as_deref(),
+                    )));
                 }
             }
             KeyCode::F(1) => self.mode = Mode::Help,
@@ -791,6 +1150,20 @@ impl App {
                             Err(e) => self.message(e.to_string()),
                         }
                     }
+                    PromptKind::Recode { id } => {
+                        if input.is_empty() {
+                            self.mode = Mode::Browse;
+                            return;
+                        }
+                        match plan::plan_recode_item(&self.tree, &id, &input) {
+                            Ok(p) => {
+                                self.mode = Mode::Confirm {
+                                    pending: PendingOp::Recode(p),
+                                }
+                            }
+                            Err(e) => self.message(e.to_string()),
+                        }
+                    }
                     PromptKind::LinkUrl {
                         mut plan,
                         input: orig_input,
@@ -803,7 +1176,7 @@ impl App {
                         plan.url = Some(input);
                         self.mode = Mode::Confirm {
                             pending: PendingOp::Create {
-                                plan,
+                                plan: *plan,
                                 input: orig_input,
                                 anchor_id,
                             },
@@ -854,7 +1227,7 @@ impl App {
             Ok(plan) if plan.kind == PlanKind::Link && plan.url.is_none() => {
                 self.mode = Mode::Prompt {
                     kind: PromptKind::LinkUrl {
-                        plan,
+                        plan: Box::new(plan),
                         input: input.to_string(),
                         anchor_id: anchor_id.to_string(),
                     },
@@ -874,7 +1247,16 @@ impl App {
         }
     }
 
-    fn on_confirm(&mut self, pending: PendingOp, k: KeyEvent) {
+    /// Templates a pending directory create can pick from (`t` cycles).
+    pub fn templates_for(&self, p: &CreatePlan) -> Vec<(String, PathBuf)> {
+        let dir = p.dest_path.parent().unwrap();
+        match (p.kind, trash::root_of(&self.roots, &p.dest_path)) {
+            (PlanKind::Dir, Some(root)) => template::available(root, dir),
+            _ => Vec::new(),
+        }
+    }
+
+    fn on_confirm(&mut self, mut pending: PendingOp, k: KeyEvent) {
         // Renumbers flow back into the wizard (or the meta editor when the
         // renumbered entry has external locations to update).
         if let PendingOp::Renumber { plan, drawers } = &pending {
@@ -883,6 +1265,7 @@ impl App {
                 KeyCode::Enter | KeyCode::Char('y') => {
                     match mutate::execute_renumber(&self.roots, plan) {
                         Ok(new_path) => {
+                            self.undo.push(UndoStep::Recode(plan.clone()));
                             let drawers = *drawers;
                             let (old_code, new_code) =
                                 (plan.old_code.clone(), plan.new_code.clone());
@@ -940,8 +1323,8 @@ impl App {
                                 plan.target_name.clone(),
                                 plan.target_path.to_string_lossy().to_string(),
                             );
-                            if undo.is_some() {
-                                self.last_delete = undo;
+                            if let Some((trash, _)) = undo {
+                                self.undo.push(UndoStep::Delete { trash });
                             }
                             self.query.clear();
                             let _ = self.rescan(Some(&target_path));
@@ -992,6 +1375,21 @@ impl App {
             }
             return;
         }
+        // t cycles a directory's template: none, then each available one.
+        if let (PendingOp::Create { plan, .. }, KeyCode::Char('t')) = (&mut pending, k.code) {
+            let options = self.templates_for(plan);
+            if !options.is_empty() {
+                let at = options
+                    .iter()
+                    .position(|(_, p)| Some(p) == plan.template.as_ref());
+                plan.template = match at {
+                    None => Some(options[0].1.clone()),
+                    Some(i) => options.get(i + 1).map(|(_, p)| p.clone()),
+                };
+            }
+            self.mode = Mode::Confirm { pending };
+            return;
+        }
         // d/f/l override the inferred kind for pending creates.
         if let PendingOp::Create {
             input, anchor_id, ..
@@ -1014,17 +1412,66 @@ impl App {
             KeyCode::Enter | KeyCode::Char('y') => {
                 let result = match &pending {
                     PendingOp::Create { plan, .. } => {
-                        let key = plan.dest_path.to_string_lossy().to_string();
-                        mutate::execute_create(&self.roots, plan)
-                            .map(|_| (Some(key), format!("created {}", plan.final_name)))
+                        mutate::execute_create(&self.roots, plan).map(|done| {
+                            let key = done.dest_path.to_string_lossy().to_string();
+                            let msg = if done.final_name == plan.final_name {
+                                format!("created {}", done.final_name)
+                            } else {
+                                // another session took the suggested code
+                                format!(
+                                    "created {} ({})",
+                                    done.final_name,
+                                    done.warnings.last().unwrap()
+                                )
+                            };
+                            (Some(key), msg)
+                        })
                     }
-                    PendingOp::Move(p) => mutate::execute_move(&self.roots, p)
-                        .map(|_| (Some(p.id.clone()), format!("moved to {}", p.final_name))),
-                    PendingOp::Delete { id, path, display } => mutate::delete_node(&self.roots, id)
+                    PendingOp::Move(p) => {
+                        let report = self.report;
+                        let mut progress = |m: mutate::Progress| {
+                            report(&format!(
+                                "moving across filesystems · {}/{} files · {} of {}",
+                                m.files,
+                                m.total_files,
+                                crate::preview::human_size(m.bytes),
+                                crate::preview::human_size(m.total_bytes)
+                            ))
+                        };
+                        mutate::execute_move(&self.roots, p, &mut progress).map(|_| {
+                            // a move across devices is a copy with new inode ids
+                            let key = p.dest_path.to_string_lossy().to_string();
+                            (Some(key), format!("moved to {}", p.final_name))
+                        })
+                    }
+                    PendingOp::Delete { id, display, .. } => mutate::delete_node(&self.roots, id)
                         .map(|trash| {
-                            self.last_delete = Some((trash, path.clone()));
+                            self.undo.push(UndoStep::Delete { trash });
                             (None, format!("trashed {} · ctrl-z to undo", display))
                         }),
+                    PendingOp::Recode(p) => mutate::execute_renumber(&self.roots, p).map(|dest| {
+                        self.undo.push(UndoStep::Recode(p.clone()));
+                        (
+                            Some(dest.to_string_lossy().to_string()),
+                            format!("recoded {} → {} · ctrl-z to undo", p.old_code, p.new_code),
+                        )
+                    }),
+                    PendingOp::Wrap(p) => mutate::execute_wrap(&self.roots, p).map(|out| {
+                        let name = out
+                            .file_name()
+                            .unwrap_or_default()
+                            .to_string_lossy()
+                            .to_string();
+                        let verb = if p.unwrap {
+                            "unwrapped to"
+                        } else {
+                            "wrapped into"
+                        };
+                        (
+                            Some(out.to_string_lossy().to_string()),
+                            format!("{} {}", verb, name),
+                        )
+                    }),
                     PendingOp::MetaRemove { .. }
                     | PendingOp::Renumber { .. }
                     | PendingOp::Merge(_) => {
@@ -6,10 +6,17 @@ Navigate      type to filter (spaces ok) · ↑/↓ PgUp/PgDn Home/End select
 Fold          tab toggle · →/← expand/collapse · ^A expand all · ^G collapse all
 Open          enter — dir: cd · file: $EDITOR · link: open URL
 Create        ^N — one prompt: '21.04 Title' | 'Title' | 'notes.md' | paste a URL
-              kind is inferred; d/f/l in the confirm step overrides it
+              kind is inferred; d/f/l in the confirm step overrides it;
+              t cycles folder templates (.jdtemplates/NAME/)
 Rename        ^R — edits the title, the code is preserved
 Move          ^V — pick a destination; items moved under a category are recoded
-Delete        ^X — to .jd_trash/ next to the item · ^Z undoes the last delete
+Recode        ^O — give an item a code you choose under the same parent;
+              stamped children and .jdmeta follow
+Wrap          ^W — file item → folder item with the file inside; on a folder
+              holding one file, back again (.jdmeta comes along)
+Delete        ^X — to the root's .jd_trash/ · ^T opens the trash: enter
+              restores, x purges for good
+Undo          ^Z — steps back through this session's deletes and recodes
 Locations     ^L — a number's other homes (reMarkable, Notion, …) in .jdmeta;
               shown atop the preview · a add ('drawer 2' or a URL) · x remove
 Notes         ^E — edit the selected folder's .jdmeta.md (e from Locations)
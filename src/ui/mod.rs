@@ -13,9 +13,14 @@ use app::Outcome;
 use ratatui::{
     backend::CrosstermBackend,
     crossterm::{
+        cursor::MoveTo,
         event::{self, Event, KeyEventKind},
         execute,
-        terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
+        style::Print,
+        terminal::{
+            self, disable_raw_mode, enable_raw_mode, Clear, ClearType, EnterAlternateScreen,
+            LeaveAlternateScreen,
+        },
     },
     Terminal,
 };
@@ -24,6 +29,8 @@ use std::{
     io::{self, IsTerminal},
     path::{Path, PathBuf},
     process::{Command, ExitStatus, Stdio},
+    sync::mpsc,
+    time::{Duration, SystemTime},
 };
 
 fn restore_terminal() {
@@ -31,6 +38,19 @@ fn restore_terminal() {
     let _ = execute!(io::stderr(), LeaveAlternateScreen);
 }
 
+/// Write `text` over the bottom line, outside a draw: the next draw repaints
+/// it. Used while a long operation keeps the loop from drawing.
+fn status_line(text: &str) {
+    if let Ok((_, rows)) = terminal::size() {
+        let _ = execute!(
+            io::stderr(),
+            MoveTo(0, rows.saturating_sub(1)),
+            Clear(ClearType::CurrentLine),
+            Print(text)
+        );
+    }
+}
+
 fn suspend_tui() -> Result<()> {
     disable_raw_mode()?;
     execute!(io::stderr(), LeaveAlternateScreen)?;
@@ -69,6 +89,50 @@ pub fn spawn_editor(path: &Path) -> io::Result<ExitStatus> {
     command.status()
 }
 
+/// `opener` from the config, else the platform's.
+pub fn opener_command() -> Vec<String> {
+    let default = if cfg!(target_os = "macos") {
+        "open"
+    } else {
+        "xdg-open"
+    };
+    crate::config::get()
+        .opener
+        .clone()
+        .filter(|s| !s.trim().is_empty())
+        .unwrap_or_else(|| default.into())
+        .split_whitespace()
+        .map(str::to_string)
+        .collect()
+}
+
+/// Carry out an action without a shell to hand it to: text files go to the
+/// editor when there's a terminal for it; folders, links and everything
+/// else to the opener.
+pub fn execute(action: &FinalAction) -> Result<()> {
+    let target = match action {
+        FinalAction::Edit(p) if io::stdin().is_terminal() && crate::preview::looks_like_text(p) => {
+            let status = spawn_editor(p)?;
+            if !status.success() {
+                bail!("editor exited with {}", status);
+            }
+            return Ok(());
+        }
+        FinalAction::Cd(p) | FinalAction::Edit(p) => p.as_os_str().to_owned(),
+        FinalAction::Open(u) => u.into(),
+    };
+    let parts = opener_command();
+    let status = Command::new(&parts[0])
+        .args(&parts[1..])
+        .arg(&target)
+        .status()
+        .map_err(|e| anyhow::anyhow!("{}: {}", parts[0], e))?;
+    if !status.success() {
+        bail!("{} exited with {}", parts[0], status);
+    }
+    Ok(())
+}
+
 struct Guard;
 impl Drop for Guard {
     fn drop(&mut self) {
@@ -76,6 +140,19 @@ impl Drop for Guard {
     }
 }
 
+/// Write a background scan as the index, under the root lock like any
+/// mutation, unless something rewrote the index after the scan began: that
+/// one saw a newer tree.
+fn save_scan(roots: &[PathBuf], tree: &crate::model::Tree, started: SystemTime) -> Result<()> {
+    let _lock = crate::lock::lock_roots(roots)?;
+    let written = std::fs::metadata(crate::io::index_path(roots)).and_then(|m| m.modified());
+    if written.is_ok_and(|w| w > started) {
+        return Ok(());
+    }
+    crate::io::IndexIo.write_index(None, tree)?;
+    Ok(())
+}
+
 pub fn run(roots: &[PathBuf], state: &Path) -> Result<Option<FinalAction>> {
     if !io::stderr().is_terminal() {
         bail!("jd-helper ui requires stderr to be a tty")
@@ -89,16 +166,43 @@ pub fn run(roots: &[PathBuf], state: &Path) -> Result<Option<FinalAction>> {
     execute!(io::stderr(), EnterAlternateScreen)?;
     let _guard = Guard;
     let mut terminal = Terminal::new(CrosstermBackend::new(io::stderr()))?;
-    let mut app = app::App::new(roots.to_vec(), state.to_path_buf())?;
+    let mut app = app::App::from_cache(roots.to_vec(), state.to_path_buf())?;
+    app.report = status_line;
+    // Navigate the cached index while the real scan runs.
+    let (tx, scanned) = mpsc::channel();
+    if app.stale() {
+        let roots = roots.to_vec();
+        std::thread::spawn(move || {
+            let started = SystemTime::now();
+            let tree = crate::fs_walk::scan_roots(&roots);
+            if let Ok(t) = &tree {
+                // next time starts from this one
+                let _ = save_scan(&roots, t, started);
+            }
+            tx.send(tree)
+        });
+    }
     loop {
+        if let Ok(tree) = scanned.try_recv() {
+            app.merge_scan(tree);
+        }
         terminal.draw(|f| render::draw(f, &mut app))?;
+        if app.stale() && !event::poll(Duration::from_millis(100))? {
+            continue;
+        }
         if let Event::Key(k) = event::read()? {
             if k.kind != KeyEventKind::Press {
                 continue;
             }
             match app.update(k) {
-                Some(Outcome::Quit) => return Ok(None),
-                Some(Outcome::Act(a)) => return Ok(Some(a)),
+                Some(Outcome::Quit) => {
+                    let _ = app.end_session();
+                    return Ok(None);
+                }
+                Some(Outcome::Act(a)) => {
+                    let _ = app.end_session();
+                    return Ok(Some(a));
+                }
                 Some(Outcome::Suspend(req)) => {
                     suspend_tui()?;
                     let result = spawn_editor(&req.file);
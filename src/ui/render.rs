@@ -4,6 +4,8 @@ use super::{
     rows::Row,
     theme,
 };
+use crate::date;
+use crate::diff::Change;
 use crate::model::NodeType;
 use crate::plan;
 use ratatui::{prelude::*, widgets::*};
@@ -48,6 +50,12 @@ pub fn draw(f: &mut Frame, app: &mut App) {
                 groups.len()
             ),
         ),
+        Mode::Trash { cursor, .. } => (Vec::new(), *cursor, "Trash".into()),
+        _ if app.stale() => (
+            app.visible.clone(),
+            app.cursor,
+            "Johnny.Decimal · cached, rescanning…".into(),
+        ),
         _ => (app.visible.clone(), app.cursor, "Johnny.Decimal".into()),
     };
     let query = match &app.mode {
@@ -71,6 +79,15 @@ pub fn draw(f: &mut Frame, app: &mut App) {
                 }
             })
             .collect(),
+        Mode::Trash { entries, .. } => entries
+            .iter()
+            .map(|(_, e)| {
+                Line::from(vec![
+                    Span::styled(format!("{}  ", date::date_time(e.deleted)), theme::MUTED),
+                    Span::raw(e.original.display().to_string()),
+                ])
+            })
+            .collect(),
         _ => indices.iter().map(|i| row_line(app, *i, &query)).collect(),
     };
     let list = List::new(lines)
@@ -78,6 +95,7 @@ pub fn draw(f: &mut Frame, app: &mut App) {
         .highlight_style(theme::SELECTED);
     let n_lines = match &app.mode {
         Mode::Duplicates { groups, gi, .. } => groups[*gi].entries.len(),
+        Mode::Trash { entries, .. } => entries.len(),
         _ => indices.len(),
     };
     let mut st = ListState::default().with_selected((n_lines > 0).then_some(list_cursor));
@@ -123,6 +141,27 @@ pub fn draw(f: &mut Frame, app: &mut App) {
             .highlight_style(theme::SELECTED);
         let mut st = ListState::default().with_selected((!entries.is_empty()).then_some(*cursor));
         f.render_stateful_widget(list, preview_pane, &mut st);
+    } else if let Mode::Trash {
+        entries, cursor, ..
+    } = &app.mode
+    {
+        let (root, e) = &entries[*cursor];
+        let field = |k: &str, v: String| {
+            Line::from(vec![
+                Span::styled(format!("{:<10}", k), theme::LABEL),
+                Span::raw(v),
+            ])
+        };
+        let lines = vec![
+            field("code", e.code.clone().unwrap_or_else(|| "—".into())),
+            field("from", root.join(&e.original).display().to_string()),
+            field("deleted", date::date_time(e.deleted)),
+            field("stored as", e.name.clone()),
+        ];
+        f.render_widget(
+            Paragraph::new(Text::from(lines)).wrap(Wrap { trim: false }),
+            preview_pane,
+        );
     } else {
         let preview = previewed.map(preview_content).unwrap_or_default();
         f.render_widget(
@@ -193,16 +232,24 @@ fn row_line(app: &mut App, row_idx: usize, query: &str) -> Line<'static> {
         "  "
     };
     let prefix = format!("{}{}", "  ".repeat(r.depth), glyph);
+    if r.change == Some(Change::Removed) {
+        return Line::styled(format!("{}{}", prefix, r.display), theme::GONE);
+    }
     // Ancestors pulled in only to situate matches (browse filter, not the
     // move picker's own candidate list) render dimmed, no hit highlighting.
     if app.context.contains(&row_idx) && !matches!(app.mode, Mode::MovePicker { .. }) {
         return Line::styled(format!("{}{}", prefix, r.display), theme::MUTED);
     }
+    let mark = match r.change {
+        Some(Change::Added) => Span::styled("+ ", theme::ADDED),
+        Some(Change::Changed) => Span::styled("~ ", theme::CHANGED),
+        _ => Span::raw(""),
+    };
     if query.is_empty() {
-        return Line::from(format!("{}{}", prefix, r.display));
+        return Line::from(vec![Span::raw(prefix), mark, Span::raw(r.display.clone())]);
     }
     let hits = app.search.indices(r, query);
-    let mut spans = vec![Span::raw(prefix)];
+    let mut spans = vec![Span::raw(prefix), mark];
     let mut run = String::new();
     let mut run_hit = false;
     for (ci, ch) in r.display.chars().enumerate() {
@@ -243,14 +290,33 @@ pub fn preview_content(r: &Row) -> Text<'static> {
     }
     match r.node_type {
         NodeType::File => {
-            let body = crate::preview::preview_file(p)
-                .unwrap_or_else(|e| format!("preview unavailable: {e}"));
-            let markdown = p
-                .extension()
-                .and_then(|e| e.to_str())
-                .is_some_and(|e| matches!(e.to_ascii_lowercase().as_str(), "md" | "markdown"));
-            if markdown {
+            let info = match crate::preview::inspect(p) {
+                Ok(info) => info,
+                Err(e) => {
+                    lines.push(Line::styled(
+                        format!("preview unavailable: {e}"),
+                        theme::ERR,
+                    ));
+                    return Text::from(lines);
+                }
+            };
+            lines.extend(
+                crate::preview::summary(&info)
+                    .into_iter()
+                    .map(|s| Line::styled(s, theme::LABEL)),
+            );
+            if info.encoding.is_none() {
+                return Text::from(lines);
+            }
+            lines.push(Line::default());
+            let body = crate::preview::text_head(p, 200).unwrap_or_default();
+            let ext = p.extension().and_then(|e| e.to_str()).unwrap_or("");
+            let lang =
+                crate::highlight::language(ext).filter(|_| crate::config::get().preview.highlight);
+            if matches!(ext.to_ascii_lowercase().as_str(), "md" | "markdown") {
                 lines.extend(crate::md::render(&body).lines);
+            } else if let Some(lang) = lang {
+                lines.extend(crate::highlight::render(&body, lang));
             } else {
                 lines.extend(body.lines().map(|s| Line::raw(s.to_string())));
             }
@@ -297,6 +363,7 @@ fn prompt_label(kind: &super::app::PromptKind) -> &'static str {
         Rename { .. } => "Rename to: ",
         LinkUrl { .. } => "URL: ",
         MetaAdd { .. } => "Add location or URL: ",
+        Recode { .. } => "Recode to: ",
     }
 }
 
@@ -331,7 +398,7 @@ fn bottom_lines(app: &App) -> (Line<'static>, Line<'static>) {
             Line::from(format!("{}{}", prompt_label(kind), editor.buffer)),
             hint("enter submit · esc cancel"),
         ),
-        Mode::Confirm { pending } => confirm_lines(pending),
+        Mode::Confirm { pending } => confirm_lines(app, pending),
         Mode::MovePicker { query, .. } => (
             Line::from(format!("Move to: {}", query)),
             hint("type to filter · ↑/↓ select · enter choose · esc cancel"),
@@ -359,50 +426,72 @@ fn bottom_lines(app: &App) -> (Line<'static>, Line<'static>) {
             ),
             hint("press any key"),
         ),
+        Mode::Trash {
+            entries,
+            cursor,
+            purge,
+        } => {
+            if *purge {
+                (
+                    Line::styled(
+                        format!("permanently delete {}?", entries[*cursor].1.name),
+                        theme::WARN,
+                    ),
+                    hint("y/enter delete for good · any other key cancels"),
+                )
+            } else {
+                (
+                    Line::from(format!("{} in the trash, newest first", entries.len())),
+                    hint("↑/↓ select · enter restore · x purge · esc done"),
+                )
+            }
+        }
         Mode::Help => (Line::raw(""), Line::raw("")),
     }
 }
 
-fn confirm_lines(pending: &PendingOp) -> (Line<'static>, Line<'static>) {
+fn confirm_lines(app: &App, pending: &PendingOp) -> (Line<'static>, Line<'static>) {
     match pending {
         PendingOp::Create { plan: p, .. } => {
             let line1 = Line::from(plan::create_summary(p));
+            let t = if app.templates_for(p).is_empty() {
+                ""
+            } else {
+                " · t template"
+            };
             let line2 = if p.warnings.is_empty() {
                 Line::styled(
-                    "y/enter confirm · n/esc cancel · d/f/l override kind",
+                    format!("y/enter confirm · n/esc cancel · d/f/l override kind{}", t),
                     theme::HINT,
                 )
             } else {
                 Line::styled(
-                    format!("⚠ {} · y/n · d/f/l override", p.warnings.join(" · ")),
+                    format!("⚠ {} · y/n · d/f/l override{}", p.warnings.join(" · "), t),
                     theme::WARN,
                 )
             };
             (line1, line2)
         }
         PendingOp::Move(p) => (
-            Line::from(format!(
-                "will move {} → {} as {}",
-                p.src_path
-                    .file_name()
-                    .map(|n| n.to_string_lossy().into_owned())
-                    .unwrap_or_default(),
-                p.dest_path
-                    .parent()
-                    .map(|n| n.display().to_string())
-                    .unwrap_or_default(),
-                p.final_name
-            )),
+            Line::from(plan::move_summary(p)),
             Line::styled("y/enter confirm · n/esc cancel", theme::HINT),
         ),
         PendingOp::Delete { display, .. } => (
-            Line::from(format!("move {} to .jd_trash/?", display)),
+            Line::from(format!("move {} to the trash?", display)),
             Line::styled("y/enter confirm · n/esc cancel", theme::HINT),
         ),
         PendingOp::MetaRemove { entry, .. } => (
             Line::from(format!("remove {}?", entry.display())),
             Line::styled("y/enter confirm · n/esc cancel", theme::HINT),
         ),
+        PendingOp::Recode(p) => (
+            Line::from(plan::renumber_summary(p)),
+            Line::styled("y/enter confirm · n/esc cancel", theme::HINT),
+        ),
+        PendingOp::Wrap(p) => (
+            Line::from(plan::wrap_summary(p)),
+            Line::styled("y/enter confirm · n/esc cancel", theme::HINT),
+        ),
         PendingOp::Merge(p) => (
             Line::from(plan::merge_summary(p)),
             Line::styled("y/enter confirm · n/esc cancel", theme::HINT),
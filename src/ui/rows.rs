@@ -1,3 +1,4 @@
+use crate::diff::Change;
 use crate::model::{Node, NodeType, Tree};
 use crate::tsv::ExpandedState;
 
@@ -18,10 +19,22 @@ pub struct Row {
     /// LOCATION= file items.
     pub meta_lines: Vec<String>,
     pub has_notes: bool,
+    /// The code before archiving; search matches it too.
+    pub archived_from: Option<String>,
+    /// Set against the cached index the session started from.
+    pub change: Option<Change>,
 }
 
-fn meta_lines(n: &Node) -> Vec<String> {
+/// The archived-from line for any node; the rest only for folders, whose
+/// children are aggregated.
+fn meta_lines(n: &Node, dir_like: bool) -> Vec<String> {
     let mut out = Vec::new();
+    if let Some(from) = &n.archived_from {
+        out.push(format!("⟲ archived from {}", from));
+    }
+    if !dir_like {
+        return out;
+    }
     for loc in &n.locations {
         out.push(format!("⌂ {}", loc));
     }
@@ -72,8 +85,10 @@ pub fn flatten(t: &Tree) -> Vec<Row> {
             node_type: n.node_type.clone(),
             dir_like,
             url: n.url.clone(),
-            meta_lines: if dir_like { meta_lines(n) } else { Vec::new() },
+            meta_lines: meta_lines(n, dir_like),
             has_notes: n.has_notes,
+            archived_from: n.archived_from.clone(),
+            change: None,
         });
         let me = out.len() - 1;
         for c in &n.children {
@@ -3,6 +3,7 @@ use nucleo_matcher::{
     pattern::{CaseMatching, Normalization, Pattern},
     Config, Matcher, Utf32Str,
 };
+use std::collections::HashSet;
 
 pub struct Search {
     matcher: Matcher,
@@ -19,20 +20,33 @@ impl Default for Search {
 impl Search {
     /// Row indices whose display ("CODE Title") matches the query, in tree
     /// order. Whitespace splits the query into AND-ed atoms (spaces are fine —
-    /// that was the headline bug of the fzf pipeline).
+    /// that was the headline bug of the fzf pipeline). Archived rows also
+    /// match as "FORMER-CODE Title".
     pub fn matched(&mut self, rows: &[Row], query: &str) -> Vec<usize> {
+        self.scored(rows, query)
+            .into_iter()
+            .map(|(i, _)| i)
+            .collect()
+    }
+
+    /// `matched` with each row's score (higher fits better; the better of
+    /// its display and its former code). An empty query scores every row 0.
+    pub fn scored(&mut self, rows: &[Row], query: &str) -> Vec<(usize, u32)> {
         if query.is_empty() {
-            return (0..rows.len()).collect();
+            return (0..rows.len()).map(|i| (i, 0)).collect();
         }
         let pat = Pattern::parse(query, CaseMatching::Ignore, Normalization::Smart);
         let mut buf = Vec::new();
         rows.iter()
             .enumerate()
-            .filter(|(_, r)| {
-                pat.score(Utf32Str::new(&r.display, &mut buf), &mut self.matcher)
-                    .is_some()
+            .filter_map(|(i, r)| {
+                let now = pat.score(Utf32Str::new(&r.display, &mut buf), &mut self.matcher);
+                let before = r.archived_from.as_ref().and_then(|from| {
+                    let former = format!("{} {}", from, r.title);
+                    pat.score(Utf32Str::new(&former, &mut buf), &mut self.matcher)
+                });
+                now.max(before).map(|s| (i, s))
             })
-            .map(|(i, _)| i)
             .collect()
     }
 
@@ -51,3 +65,20 @@ impl Search {
         out
     }
 }
+
+/// Ancestors of matched rows that don't match themselves: shown (dimmed) so
+/// the tree shape (54.01 under 54 under 50-59) stays legible.
+pub fn context(rows: &[Row], matched: &[usize]) -> HashSet<usize> {
+    let hit: HashSet<usize> = matched.iter().copied().collect();
+    let mut out = HashSet::new();
+    for &m in matched {
+        let mut p = rows[m].parent_idx;
+        while let Some(i) = p {
+            if !hit.contains(&i) {
+                out.insert(i);
+            }
+            p = rows[i].parent_idx;
+        }
+    }
+    out
+}
@@ -15,3 +15,12 @@ pub const OK: Style = Style::new().fg(Color::Blue);
 pub const WARN: Style = Style::new().fg(Color::Yellow);
 pub const ERR: Style = Style::new().fg(Color::Red).add_modifier(Modifier::BOLD);
 pub const RULE: Style = Style::new().add_modifier(Modifier::DIM);
+/// Row marks against the cached index: new, changed, gone.
+pub const ADDED: Style = Style::new().fg(Color::Green);
+pub const CHANGED: Style = Style::new().fg(Color::Yellow);
+pub const GONE: Style = Style::new().add_modifier(Modifier::DIM.union(Modifier::CROSSED_OUT));
+/// Source previews (`highlight`).
+pub const KEYWORD: Style = Style::new().fg(Color::Magenta);
+pub const STRING: Style = Style::new().fg(Color::Green);
+pub const COMMENT: Style = Style::new().add_modifier(Modifier::DIM.union(Modifier::ITALIC));
+pub const NUMBER: Style = Style::new().fg(Color::Yellow);
@@ -36,3 +36,272 @@ fn scan_and_write_index_roundtrip() {
     let s = fs::read_to_string(out_path).unwrap();
     assert!(s.contains("30-39"));
 }
+
+#[test]
+fn schema_and_index_verify() {
+    let dir = tempdir().unwrap();
+    let root = dir.path().join("R");
+    fs::create_dir_all(root.join("20-29_Admin/21_Taxes")).unwrap();
+    let run = |args: &[&str]| {
+        let mut cmd = Command::cargo_bin("jd-helper").unwrap();
+        cmd.env("HOME", dir.path()).args(args);
+        cmd.assert()
+    };
+
+    let out = run(&["schema"]).success().get_output().stdout.clone();
+    let schema: serde_json::Value = serde_json::from_slice(&out).unwrap();
+    assert_eq!(schema["title"], "JdIndex");
+    assert!(schema["$defs"]["Node"]["properties"]["archived_from"].is_object());
+
+    let r = root.to_str().unwrap();
+    run(&["write-index", r]).success();
+    let index = fs::read_to_string(root.join(".jd_index.json")).unwrap();
+    assert!(index.contains("\"schema_version\": 2"), "{}", index);
+    run(&["index", "verify", r])
+        .success()
+        .stdout(predicates::str::contains("schema 2, generated 20"));
+
+    fs::create_dir(root.join("20-29_Admin/22_Bills")).unwrap();
+    run(&["index", "verify", r])
+        .failure()
+        .stdout(predicates::str::contains("stale: 1 new, 0 changed, 0 gone"));
+
+    // a pre-version index still reads, but verify asks for a rewrite
+    let mut v: serde_json::Value = serde_json::from_str(&index).unwrap();
+    let obj = v.as_object_mut().unwrap();
+    obj.remove("schema_version");
+    obj.remove("generated_at");
+    let old = dir.path().join("old.json");
+    fs::write(&old, serde_json::to_vec(&v).unwrap()).unwrap();
+    run(&["index", "verify", "--file", old.to_str().unwrap(), r])
+        .failure()
+        .stdout(predicates::str::contains("schema 1 is out of date"));
+}
+
+#[test]
+fn export_subtree_without_paths() {
+    let dir = tempdir().unwrap();
+    let root = dir.path().join("R");
+    let cat = root.join("20-29_Admin/21_Taxes");
+    fs::create_dir_all(cat.join("21.01_Returns")).unwrap();
+    fs::create_dir_all(root.join("30-39_Projects/31_House")).unwrap();
+    let mut cmd = Command::cargo_bin("jd-helper").unwrap();
+    cmd.env("HOME", dir.path()).args([
+        "export",
+        "--format",
+        "html",
+        "--under",
+        "21",
+        "--no-paths",
+        root.to_str().unwrap(),
+    ]);
+    let out = cmd.assert().success().get_output().stdout.clone();
+    let html = String::from_utf8(out).unwrap();
+    assert!(html.starts_with("<!DOCTYPE html>"));
+    assert!(html.contains("<title>21 Taxes</title>"), "{}", html);
+    assert!(html.contains("<li><span class=\"code\">21.01</span>Returns"));
+    assert!(!html.contains("House"));
+    assert!(!html.contains(dir.path().to_str().unwrap()));
+}
+
+#[test]
+fn diff_index_against_a_fresh_scan() {
+    let dir = tempdir().unwrap();
+    let root = dir.path().join("R");
+    let cat = root.join("20-29_Admin/21_Taxes");
+    fs::create_dir_all(cat.join("21.01_Returns")).unwrap();
+    fs::create_dir_all(root.join("20-29_Admin/22_Bills")).unwrap();
+    let run = |args: &[&str]| {
+        let mut cmd = Command::cargo_bin("jd-helper").unwrap();
+        cmd.env("HOME", dir.path()).args(args);
+        cmd.assert()
+    };
+    let r = root.to_str().unwrap();
+    let index = root.join(".jd_index.json");
+    let old = index.to_str().unwrap();
+    run(&["write-index", r]).success();
+    run(&["diff", old])
+        .success()
+        .stdout(predicates::str::contains("no changes"));
+
+    fs::rename(
+        cat.join("21.01_Returns"),
+        root.join("20-29_Admin/22_Bills/22.01_Returns"),
+    )
+    .unwrap();
+    fs::create_dir(cat.join("21.02_Receipts")).unwrap();
+    run(&["diff", old, r])
+        .success()
+        .stdout(predicates::str::contains("added      21.02 Receipts"))
+        .stdout(predicates::str::contains(
+            "recoded    21.01 → 22.01 Returns",
+        ));
+
+    let out = run(&["diff", "--json", old])
+        .success()
+        .get_output()
+        .stdout
+        .clone();
+    let changes: serde_json::Value = serde_json::from_slice(&out).unwrap();
+    let kinds: Vec<&str> = changes
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|c| c["change"].as_str().unwrap())
+        .collect();
+    assert_eq!(kinds, ["added", "moved", "recoded"], "{}", changes);
+}
+
+#[test]
+fn resolve_ranks_candidates_and_flags_ambiguity() {
+    let dir = tempdir().unwrap();
+    let root = dir.path().join("R");
+    let cat = root.join("20-29_Admin/21_Taxes");
+    fs::create_dir_all(cat.join("21.04_Returns")).unwrap();
+    fs::create_dir_all(cat.join("21.05_Tax_letters")).unwrap();
+    let run = |args: &[&str]| {
+        let mut cmd = Command::cargo_bin("jd-helper").unwrap();
+        cmd.env("HOME", dir.path())
+            .arg("resolve")
+            .args(args)
+            .arg(&root);
+        cmd.assert()
+    };
+    let stdout =
+        |a: assert_cmd::assert::Assert| String::from_utf8(a.get_output().stdout.clone()).unwrap();
+    let returns = fs::canonicalize(cat.join("21.04_Returns")).unwrap();
+
+    let out = stdout(run(&["21.4"]).success());
+    assert_eq!(out.trim_end(), returns.to_str().unwrap());
+    let out = stdout(run(&["21.0"]).code(3));
+    let lines: Vec<&str> = out.lines().collect();
+    assert_eq!(lines.len(), 2, "{}", out);
+    assert!(lines[0].ends_with("\t21.04 Returns"), "{}", out);
+
+    let out = stdout(run(&["tax", "--format", "json"]).code(3));
+    let v: serde_json::Value = serde_json::from_str(&out).unwrap();
+    assert_eq!(v[0]["match"], "title");
+    assert_eq!(v[0]["code"], "21");
+    let out = stdout(run(&["letters", "--format", "url"]).success());
+    assert!(out.starts_with("file:///") && out.trim_end().ends_with("21.05_Tax_letters"));
+    run(&["99.99"]).code(1);
+}
+
+#[test]
+fn complete_answers_from_the_cached_index() {
+    let dir = tempdir().unwrap();
+    let root = dir.path().join("R");
+    fs::create_dir_all(root.join("30-39_Research/31_Reading/31.01_Books")).unwrap();
+    fs::create_dir_all(root.join("30-39_Research/32_Writing")).unwrap();
+    Command::cargo_bin("jd-helper")
+        .unwrap()
+        .env("HOME", dir.path())
+        .args(["write-index", root.to_str().unwrap()])
+        .assert()
+        .success();
+    // the index still says "Books": no rescan per TAB
+    fs::rename(
+        root.join("30-39_Research/31_Reading/31.01_Books"),
+        root.join("30-39_Research/31_Reading/31.01_Novels"),
+    )
+    .unwrap();
+
+    let out = Command::cargo_bin("jd-helper")
+        .unwrap()
+        .env("HOME", dir.path())
+        .args(["complete", "--shell", "fish", "31", "--root"])
+        .arg(&root)
+        .output()
+        .unwrap();
+    assert!(out.status.success());
+    assert_eq!(
+        String::from_utf8_lossy(&out.stdout),
+        "31\tReading\n31.01\tBooks\n"
+    );
+
+    let out = Command::cargo_bin("jd-helper")
+        .unwrap()
+        .args(["completions", "zsh"])
+        .output()
+        .unwrap();
+    let script = String::from_utf8_lossy(&out.stdout);
+    assert!(script.contains("'--id=[]:ID:_jd-helper_nodes ids'"));
+    assert!(script.contains("jd-helper complete --shell zsh --ids"));
+}
+
+#[test]
+fn open_decides_like_enter_and_runs_the_opener() {
+    use std::os::unix::fs::PermissionsExt;
+    let dir = tempdir().unwrap();
+    let root = dir.path().join("R");
+    let cat = root.join("30-39_Research/31_Reading");
+    fs::create_dir_all(cat.join("31.01_Books")).unwrap();
+    fs::write(cat.join("31.02_Notes.md"), "# notes\n").unwrap();
+    fs::write(cat.join("31.03_Scan.pdf"), b"%PDF-1.4\0\x01\x02").unwrap();
+    fs::write(cat.join("31.04_Notes old.md"), "").unwrap();
+    let log = dir.path().join("opened");
+    let opener = dir.path().join("opener.sh");
+    fs::write(
+        &opener,
+        format!("#!/bin/sh\necho \"$1\" >> {}\n", log.display()),
+    )
+    .unwrap();
+    fs::set_permissions(&opener, fs::Permissions::from_mode(0o755)).unwrap();
+    let config = dir.path().join("config.json");
+    fs::write(&config, serde_json::json!({"opener": opener}).to_string()).unwrap();
+    let open = |args: &[&str]| {
+        let out = Command::cargo_bin("jd-helper")
+            .unwrap()
+            .env("HOME", dir.path())
+            .env("JD_CONFIG", &config)
+            .arg("open")
+            .args(args)
+            .arg(&root)
+            .output()
+            .unwrap();
+        assert!(out.status.success(), "{:?}", out);
+        String::from_utf8(out.stdout).unwrap()
+    };
+
+    assert_eq!(
+        open(&["31.01"]),
+        format!("cd\t{}\n", cat.join("31.01_Books").display())
+    );
+    let v: serde_json::Value = serde_json::from_str(&open(&["31.2", "--format", "json"])).unwrap();
+    assert_eq!(v["action"], "edit");
+
+    // two notes: the candidates, in the asked-for format, and exit 3
+    let ambiguous = |format: &str| {
+        let out = Command::cargo_bin("jd-helper")
+            .unwrap()
+            .env("HOME", dir.path())
+            .args(["open", "notes", "--format", format])
+            .arg(&root)
+            .output()
+            .unwrap();
+        assert_eq!(out.status.code(), Some(3), "{:?}", out);
+        out.stdout
+    };
+    let v: serde_json::Value = serde_json::from_slice(&ambiguous("json")).unwrap();
+    assert_eq!(v[0]["code"], "31.02");
+    assert_eq!(
+        v[1]["path"],
+        cat.join("31.04_Notes old.md").to_str().unwrap()
+    );
+    let fields = ambiguous("nul");
+    let fields: Vec<&[u8]> = fields.split(|b| *b == 0).collect();
+    assert_eq!(fields.len(), 5);
+    assert_eq!(fields[1], b"31.02 Notes");
+
+    // no terminal here, so even the text file goes to the opener
+    open(&["31.03", "--exec"]);
+    open(&["31.02", "--exec"]);
+    assert_eq!(
+        fs::read_to_string(&log).unwrap(),
+        format!(
+            "{}\n{}\n",
+            cat.join("31.03_Scan.pdf").display(),
+            cat.join("31.02_Notes.md").display()
+        )
+    );
+}
@@ -55,7 +55,10 @@ fn recreate_t99_into(dest: &Path) {
         .suffix(".sh")
         .tempfile()
         .unwrap();
-    let script_path: PathBuf = tmp.path().to_path_buf();
+    // Close our handle first: exec fails with ETXTBSY while the file is
+    // open for writing. The path is still removed on drop.
+    let tmp = tmp.into_temp_path();
+    let script_path: PathBuf = tmp.to_path_buf();
     fs::write(&script_path, contents).unwrap();
     #[cfg(unix)]
     {
@@ -90,3 +93,27 @@ fn rebuild_fixture_and_scan() {
     cmd.arg("tree").arg(dest.to_str().unwrap());
     cmd.assert().success();
 }
+
+#[test]
+fn skeleton_apply_reads_the_fixture_manifest() {
+    let td = tempdir().unwrap();
+    let dest = td.path().join("T99_Test_Root");
+    let manifest = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/T99_tree.txt");
+    let mut cmd = Command::cargo_bin("jd-helper").unwrap();
+    cmd.args(["skeleton", "apply"]).arg(&manifest).arg(&dest);
+    cmd.assert().success();
+    assert!(dest
+        .join("99-99_Test_Range/99_TestCat/99.01_TestItem/.jdmeta.md")
+        .is_file());
+    assert!(dest.join("90-98_Second_Range/91_Sparse_Cat").is_dir());
+
+    // dumping it back gives the same entries
+    let mut cmd = Command::cargo_bin("jd-helper").unwrap();
+    cmd.args(["skeleton", "dump"]).arg(&dest);
+    let out = cmd.assert().success().get_output().stdout.clone();
+    let mut dumped: Vec<&str> = std::str::from_utf8(&out).unwrap().lines().collect();
+    let mut listed: Vec<&str> = include_str!("fixtures/T99_tree.txt").lines().collect();
+    dumped.sort();
+    listed.sort();
+    assert_eq!(dumped, listed);
+}
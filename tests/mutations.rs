@@ -368,3 +368,611 @@ fn move_under_category_keeps_underscores_in_title() {
         .join("30-39_Research/31_Other/31.01_Two_Word_Note.txt")
         .exists());
 }
+
+#[test]
+fn apply_batch_from_stdin() {
+    let td = tempdir().unwrap();
+    let home = td.path().join("home");
+    fs::create_dir_all(&home).unwrap();
+    let root = td.path().join("R50_Research");
+    fs::create_dir_all(root.join("30-39_Research/30_Topic/30.01_Old")).unwrap();
+    fs::create_dir_all(root.join("30-39_Research/31_Other")).unwrap();
+    let plan = r#"[
+        {"op": "create", "parent": "30", "input": "Fresh notes.md"},
+        {"op": "rename", "id": "30.01", "title": "New title"},
+        {"op": "move", "id": "30.01", "parent": "31"}
+    ]"#;
+
+    // --dry-run prints the numbered steps and leaves disk alone
+    let apply = || {
+        let mut cmd = assert_cmd::Command::cargo_bin("jd-helper").unwrap();
+        cmd.env("HOME", &home);
+        cmd
+    };
+    let out = apply()
+        .arg("apply")
+        .arg("-")
+        .arg("--dry-run")
+        .arg(&root)
+        .write_stdin(plan)
+        .assert()
+        .success()
+        .get_output()
+        .stdout
+        .clone();
+    let out = String::from_utf8(out).unwrap();
+    assert_eq!(out.lines().count(), 3);
+    assert!(out.starts_with("1\twill create FILE 30.02_Fresh_notes.md"));
+    assert!(root.join("30-39_Research/30_Topic/30.01_Old").is_dir());
+
+    apply()
+        .arg("apply")
+        .arg("-")
+        .arg(&root)
+        .write_stdin(plan)
+        .assert()
+        .success();
+    assert!(root
+        .join("30-39_Research/30_Topic/30.02_Fresh_notes.md")
+        .is_file());
+    assert!(root
+        .join("30-39_Research/31_Other/31.01_New_title")
+        .is_dir());
+    assert!(!root.join("30-39_Research/30_Topic/30.01_Old").exists());
+}
+
+#[test]
+fn recode_category_cascades_to_items_and_notes() {
+    let td = tempdir().unwrap();
+    let home = td.path().join("home");
+    fs::create_dir_all(&home).unwrap();
+    let root = td.path().join("R50_Research");
+    let cat = root.join("40-49_Work/42_Clients");
+    fs::create_dir_all(cat.join("42.07_Acme")).unwrap();
+    fs::create_dir_all(root.join("40-49_Work/44_Misc/44.01_Index")).unwrap();
+    fs::write(cat.join("42.07_Acme/42.07_contract.pdf"), b"x").unwrap();
+    let index = root.join("40-49_Work/44_Misc/44.01_Index");
+    fs::write(
+        index.join(".jdmeta"),
+        "LOCATION=see 42.07 and 41.07, fee 42.50 paid 42.12.2024\n",
+    )
+    .unwrap();
+    // prose: a date and a price, left alone without --rewrite-notes
+    let notes = "Filed 42.12.2024, fee 42.50 EUR, see 42.07\n";
+    fs::write(index.join(".jdmeta.md"), notes).unwrap();
+    let id = jd_helper::model::make_id(&cat);
+
+    let recode = |dry: bool| {
+        let mut cmd = cargo_bin();
+        set_home(&mut cmd, &home);
+        cmd.arg("recode")
+            .arg("--id")
+            .arg(&id)
+            .arg("--code")
+            .arg("43");
+        if dry {
+            cmd.arg("--dry-run");
+        }
+        cmd.arg(&root);
+        let out = cmd.assert().success().get_output().stdout.clone();
+        String::from_utf8(out).unwrap()
+    };
+    // the preview lists every rename and leaves disk alone
+    let out = recode(true);
+    assert!(out.starts_with("will recode 42 → 43: 3 rename(s), 1 notes file(s) rewritten"));
+    assert!(out.contains("  42.07_contract.pdf → 43.07_contract.pdf"));
+    assert!(out.contains(&format!(
+        "  ~ {}:1: LOCATION=see 42.07 and 41.07, fee 42.50 paid 42.12.2024 → LOCATION=see 43.07 and 41.07, fee 42.50 paid 42.12.2024",
+        index.join(".jdmeta").display()
+    )));
+    assert!(cat.is_dir());
+
+    recode(false);
+    let moved = root.join("40-49_Work/43_Clients/43.07_Acme");
+    assert!(moved.join("43.07_contract.pdf").is_file());
+    assert_eq!(
+        fs::read_to_string(index.join(".jdmeta")).unwrap(),
+        "LOCATION=see 43.07 and 41.07, fee 42.50 paid 42.12.2024\n"
+    );
+    assert_eq!(fs::read_to_string(index.join(".jdmeta.md")).unwrap(), notes);
+
+    // 44 is taken: refused before anything changes
+    let id = jd_helper::model::make_id(&root.join("40-49_Work/43_Clients"));
+    let mut cmd = cargo_bin();
+    set_home(&mut cmd, &home);
+    cmd.arg("recode")
+        .arg("--id")
+        .arg(&id)
+        .arg("--code")
+        .arg("44")
+        .arg(&root);
+    cmd.assert().failure();
+    assert!(moved.is_dir());
+}
+
+#[test]
+fn move_across_roots_recodes_under_destination() {
+    let td = tempdir().unwrap();
+    let home = td.path().join("home");
+    fs::create_dir_all(&home).unwrap();
+    let a = td.path().join("R10_Home");
+    let b = td.path().join("R50_Research");
+    fs::create_dir_all(a.join("10-19_Home/11_House/11.02_Boiler")).unwrap();
+    fs::write(a.join("10-19_Home/11_House/11.02_Boiler/manual.pdf"), b"x").unwrap();
+    fs::create_dir_all(b.join("30-39_Research/31_Papers/31.01_Existing")).unwrap();
+    let id = jd_helper::model::make_id(&a.join("10-19_Home/11_House/11.02_Boiler"));
+    let parent = jd_helper::model::make_id(&b.join("30-39_Research/31_Papers"));
+
+    let mut cmd = cargo_bin();
+    set_home(&mut cmd, &home);
+    cmd.arg("move")
+        .arg("--id")
+        .arg(&id)
+        .arg("--parent")
+        .arg(&parent)
+        .arg(&a)
+        .arg(&b);
+    cmd.assert().success();
+    assert!(b
+        .join("30-39_Research/31_Papers/31.02_Boiler/manual.pdf")
+        .is_file());
+    assert!(!a.join("10-19_Home/11_House/11.02_Boiler").exists());
+}
+
+#[test]
+fn trash_list_restore_purge() {
+    let td = tempdir().unwrap();
+    let home = td.path().join("home");
+    fs::create_dir_all(&home).unwrap();
+    let root = td.path().join("R50_Research");
+    let cat = root.join("30-39_Research/31_Papers");
+    fs::create_dir_all(&cat).unwrap();
+    let run = |args: &[&str]| {
+        let mut cmd = cargo_bin();
+        set_home(&mut cmd, &home);
+        cmd.args(args).arg(&root);
+        let out = cmd.assert().success().get_output().stdout.clone();
+        String::from_utf8(out).unwrap()
+    };
+    // the same name deleted twice: both kept
+    for body in ["one", "two"] {
+        let note = cat.join("31.03_Note.txt");
+        fs::write(&note, body).unwrap();
+        let id = jd_helper::model::make_id(&note);
+        run(&["delete", "--id", &id]);
+        assert!(!note.exists());
+    }
+    let listed = run(&["trash", "list"]);
+    let rows: Vec<Vec<&str>> = listed.lines().map(|l| l.split('\t').collect()).collect();
+    assert_eq!(rows.len(), 2);
+    assert_eq!(rows[0][0], "31.03_Note.txt");
+    assert_eq!(rows[0][1], "31.03");
+    assert!(rows[1][0].starts_with("31.03_Note~"));
+    assert!(rows[0][3].ends_with("31_Papers/31.03_Note.txt"));
+
+    run(&["trash", "restore", "--name", rows[1][0]]);
+    assert_eq!(
+        fs::read_to_string(cat.join("31.03_Note.txt")).unwrap(),
+        "two"
+    );
+
+    // nothing is old enough yet; a purge needs an age or --all
+    assert_eq!(run(&["trash", "purge", "--older-than", "30d"]), "");
+    let mut cmd = cargo_bin();
+    set_home(&mut cmd, &home);
+    cmd.args(["trash", "purge"]).arg(&root).assert().failure();
+    // a failing pre-hook keeps the trash as it is
+    let config = td.path().join("config.json");
+    fs::write(&config, r#"{"hooks": {"pre": {"purge": "exit 1"}}}"#).unwrap();
+    let mut cmd = cargo_bin();
+    set_home(&mut cmd, &home);
+    cmd.env("JD_CONFIG", &config)
+        .args(["trash", "purge", "--all"])
+        .arg(&root)
+        .assert()
+        .failure();
+    assert_eq!(run(&["trash", "list"]).lines().count(), 1);
+    assert_eq!(run(&["trash", "purge", "--all"]).lines().count(), 1);
+    assert_eq!(run(&["trash", "list"]), "");
+}
+
+#[test]
+fn new_dir_with_named_template() {
+    let td = tempdir().unwrap();
+    let home = td.path().join("home");
+    fs::create_dir_all(&home).unwrap();
+    let root = td.path().join("R50_Research");
+    let cat = root.join("30-39_Research/31_Papers");
+    fs::create_dir_all(&cat).unwrap();
+    fs::create_dir_all(cat.join(".jdtemplates/paper")).unwrap();
+    fs::write(
+        cat.join(".jdtemplates/paper/{{code}}_refs.bib"),
+        "% {{title}} {{date}}\n",
+    )
+    .unwrap();
+    let parent = jd_helper::model::make_id(&cat);
+    let new = |name: &str, template: &str| {
+        let mut cmd = cargo_bin();
+        set_home(&mut cmd, &home);
+        cmd.args(["new", "dir", "--parent", &parent, "--name", name]);
+        if !template.is_empty() {
+            cmd.args(["--template", template]);
+        }
+        cmd.arg(&root).assert()
+    };
+    new("31.01_Deep_Learning", "paper").success();
+    let bib = fs::read_to_string(cat.join("31.01_Deep_Learning/31.01_refs.bib")).unwrap();
+    assert!(bib.starts_with("% Deep Learning 20"));
+    // no default `item` template here, so a plain folder
+    new("31.02_Plain", "").success();
+    assert_eq!(fs::read_dir(cat.join("31.02_Plain")).unwrap().count(), 0);
+    new("31.03_Nope", "missing").failure();
+    assert!(!cat.join("31.03_Nope").exists());
+    // a title the name policy empties would leave an unparseable `31.05_`
+    new("31.05_???", "").failure();
+    assert!(!cat.join("31.05_").exists());
+    // a file takes no template
+    let mut cmd = cargo_bin();
+    set_home(&mut cmd, &home);
+    cmd.args(["new", "file", "--parent", &parent, "--template", "paper"])
+        .args(["--name", "31.04_Notes.md"])
+        .arg(&root);
+    let err = cmd.assert().failure().get_output().stderr.clone();
+    let err = String::from_utf8(err).unwrap();
+    assert!(err.contains("templates apply to folders only"), "{}", err);
+    assert!(!cat.join("31.04_Notes.md").exists());
+}
+
+#[test]
+fn file_outside_paths_into_category_and_item() {
+    let td = tempdir().unwrap();
+    let home = td.path().join("home");
+    fs::create_dir_all(&home).unwrap();
+    let root = td.path().join("R50_Research");
+    let cat = root.join("20-29_Admin/21_Taxes");
+    fs::create_dir_all(cat.join("21.01_Returns")).unwrap();
+    let inbox = td.path().join("inbox");
+    fs::create_dir_all(&inbox).unwrap();
+    fs::write(inbox.join("receipt 2026.pdf"), b"r").unwrap();
+    fs::write(inbox.join("letter.txt"), b"l").unwrap();
+
+    let file = |args: &[&str], paths: &[PathBuf]| {
+        let mut cmd = cargo_bin();
+        set_home(&mut cmd, &home);
+        cmd.arg("file")
+            .args(paths)
+            .args(args)
+            .arg("--root")
+            .arg(&root);
+        let out = cmd.assert().success().get_output().stdout.clone();
+        String::from_utf8(out).unwrap()
+    };
+    // into a category by code: next free code, source kept with --copy
+    let out = file(
+        &["--into", "21", "--copy"],
+        &[inbox.join("receipt 2026.pdf")],
+    );
+    let filed = cat.join("21.02_receipt_2026.pdf");
+    assert!(out.trim_end().ends_with(&format!("\t{}", filed.display())));
+    assert_eq!(fs::read(&filed).unwrap(), b"r");
+    assert!(inbox.join("receipt 2026.pdf").exists());
+
+    // into an item: stamped with its code and moved
+    file(&["--into", "21.01"], &[inbox.join("letter.txt")]);
+    assert!(cat.join("21.01_Returns/21.01_letter.txt").is_file());
+    assert!(!inbox.join("letter.txt").exists());
+
+    // a dry run names the file but leaves it where it is
+    let out = file(
+        &["--into", "21", "--dry-run"],
+        &[inbox.join("receipt 2026.pdf")],
+    );
+    assert!(out.contains("21.03_receipt_2026.pdf"));
+    assert!(!cat.join("21.03_receipt_2026.pdf").exists());
+}
+
+#[test]
+fn name_policy_from_config_and_check_names() {
+    let td = tempdir().unwrap();
+    let home = td.path().join("home");
+    fs::create_dir_all(&home).unwrap();
+    let root = td.path().join("R50_Research");
+    let cat = root.join("30-39_Research/30_Topic");
+    fs::create_dir_all(&cat).unwrap();
+    let config = td.path().join("config.json");
+    fs::write(&config, r#"{"names": {"separator": "-", "max_len": 12}}"#).unwrap();
+    let cat_id = jd_helper::model::make_id(&cat);
+
+    let mut cmd = cargo_bin();
+    set_home(&mut cmd, &home);
+    cmd.env("JD_CONFIG", &config)
+        .args([
+            "new",
+            "dir",
+            "--parent",
+            &cat_id,
+            "--name",
+            "30.01_Q3: plan? for the team",
+        ])
+        .arg(&root);
+    cmd.assert().success();
+    assert!(cat.join("30.01_Q3-plan-for").is_dir());
+
+    let check = |config: &PathBuf| {
+        let mut cmd = cargo_bin();
+        set_home(&mut cmd, &home);
+        cmd.env("JD_CONFIG", config).arg("check-names").arg(&root);
+        cmd.assert()
+    };
+    check(&config).success();
+    fs::write(cat.join("30.02_Draft?.txt"), b"x").unwrap();
+    let out = check(&config).failure().get_output().stdout.clone();
+    let out = String::from_utf8(out).unwrap();
+    assert_eq!(
+        out,
+        format!(
+            "{}\tforbidden character(s) \"?\"\n",
+            cat.join("30.02_Draft?.txt").display()
+        )
+    );
+    // a broken config is reported rather than silently replaced
+    fs::write(&config, "{").unwrap();
+    check(&config).failure();
+}
+
+#[test]
+fn hooks_run_around_mutations() {
+    let td = tempdir().unwrap();
+    let home = td.path().join("home");
+    fs::create_dir_all(&home).unwrap();
+    let root = td.path().join("R50_Research");
+    let cat = root.join("30-39_Research/30_Topic");
+    fs::create_dir_all(cat.join("30.01_Keep")).unwrap();
+    let log = td.path().join("hook.log");
+    let config = td.path().join("config.json");
+    let hooks = serde_json::json!({"hooks": {
+        "pre": {"delete": "echo deletes are off >&2; exit 1"},
+        "post": {
+            "*": format!("{{ echo \"$JD_OP $JD_NEW_PATH $JD_CODE\"; cat; echo; }} >> {}", log.display()),
+            "rename": "exit 7",
+        },
+    }});
+    fs::write(&config, hooks.to_string()).unwrap();
+    let run = |args: &[&str]| {
+        let mut cmd = cargo_bin();
+        set_home(&mut cmd, &home);
+        cmd.env("JD_CONFIG", &config).args(args).arg(&root);
+        cmd.assert()
+    };
+    let cat_id = jd_helper::model::make_id(&cat);
+    let item_id = jd_helper::model::make_id(&cat.join("30.01_Keep"));
+
+    run(&["new", "dir", "--parent", &cat_id, "--name", "30.02_Fresh"]).success();
+    let logged = fs::read_to_string(&log).unwrap();
+    let (head, plan) = logged.split_once('\n').unwrap();
+    assert_eq!(
+        head,
+        format!("create {} 30.02", cat.join("30.02_Fresh").display())
+    );
+    let plan: serde_json::Value = serde_json::from_str(plan.trim()).unwrap();
+    assert_eq!(plan["final_name"], "30.02_Fresh");
+
+    // a failing pre-hook stops the delete
+    let out = run(&["delete", "--id", &item_id])
+        .failure()
+        .get_output()
+        .stderr
+        .clone();
+    assert!(String::from_utf8(out).unwrap().contains("deletes are off"));
+    assert!(cat.join("30.01_Keep").is_dir());
+
+    // a failing post-hook is a warning; the rename stands
+    let out = run(&["rename", "--id", &item_id, "--name", "Kept"])
+        .success()
+        .get_output()
+        .stderr
+        .clone();
+    assert!(String::from_utf8(out)
+        .unwrap()
+        .contains("warning: post-rename hook failed"));
+    assert!(cat.join("30.01_Kept").is_dir());
+}
+
+#[test]
+fn wrap_and_unwrap_carry_jdmeta() {
+    let td = tempdir().unwrap();
+    let home = td.path().join("home");
+    fs::create_dir_all(&home).unwrap();
+    let root = td.path().join("R50_Research");
+    let cat = root.join("20-29_Admin/21_Taxes");
+    fs::create_dir_all(&cat).unwrap();
+    let file = cat.join("21.03_Return.pdf");
+    fs::write(&file, b"pdf").unwrap();
+    fs::write(cat.join(".21.03_Return.pdf.jdmeta"), "drawer 2\n").unwrap();
+
+    let run = |verb: &str, path: &PathBuf| {
+        let mut cmd = cargo_bin();
+        set_home(&mut cmd, &home);
+        cmd.arg(verb)
+            .arg("--id")
+            .arg(jd_helper::model::make_id(path))
+            .arg(&root);
+        let out = cmd.assert().success().get_output().stdout.clone();
+        String::from_utf8(out).unwrap()
+    };
+    let dir = cat.join("21.03_Return");
+    let out = run("wrap", &file);
+    assert!(out.starts_with("will wrap 21.03_Return.pdf into 21.03_Return/"));
+    assert_eq!(fs::read(dir.join("21.03_Return.pdf")).unwrap(), b"pdf");
+    assert_eq!(
+        fs::read_to_string(dir.join(".jdmeta")).unwrap(),
+        "drawer 2\n"
+    );
+    assert!(!file.exists());
+    assert!(!cat.join(".21.03_Return.pdf.jdmeta").exists());
+
+    // a second file inside blocks unwrapping
+    fs::write(dir.join("notes.txt"), b"n").unwrap();
+    let mut cmd = cargo_bin();
+    set_home(&mut cmd, &home);
+    cmd.args(["unwrap", "--id", &jd_helper::model::make_id(&dir)])
+        .arg(&root)
+        .assert()
+        .failure();
+    fs::remove_file(dir.join("notes.txt")).unwrap();
+
+    // so do entries the scan ignores, which unwrapping would delete
+    fs::create_dir(dir.join(".git")).unwrap();
+    fs::write(dir.join(".git/HEAD"), b"ref").unwrap();
+    fs::write(dir.join("draft.bak"), b"b").unwrap();
+    let mut cmd = cargo_bin();
+    set_home(&mut cmd, &home);
+    cmd.args(["unwrap", "--id", &jd_helper::model::make_id(&dir)])
+        .arg(&root)
+        .assert()
+        .failure();
+    assert_eq!(fs::read(dir.join(".git/HEAD")).unwrap(), b"ref");
+    assert_eq!(fs::read(dir.join("draft.bak")).unwrap(), b"b");
+    fs::remove_dir_all(dir.join(".git")).unwrap();
+    fs::remove_file(dir.join("draft.bak")).unwrap();
+
+    run("unwrap", &dir);
+    assert_eq!(fs::read(&file).unwrap(), b"pdf");
+    assert!(!dir.exists());
+    assert_eq!(
+        fs::read_to_string(cat.join(".21.03_Return.pdf.jdmeta")).unwrap(),
+        "drawer 2\n"
+    );
+}
+
+#[test]
+fn archive_records_provenance_and_unarchive_restores() {
+    let td = tempdir().unwrap();
+    let home = td.path().join("home");
+    fs::create_dir_all(&home).unwrap();
+    let root = td.path().join("R50_Research");
+    let item = root.join("20-29_Admin/21_Taxes/21.04_Audit");
+    fs::create_dir_all(&item).unwrap();
+    fs::write(item.join("21.04_letter.pdf"), b"l").unwrap();
+    fs::create_dir_all(root.join("90-99_Archive/90_Done")).unwrap();
+    let config = td.path().join("config.json");
+    fs::write(&config, r#"{"archive": "90"}"#).unwrap();
+
+    let run = |args: &[&str]| {
+        let mut cmd = cargo_bin();
+        set_home(&mut cmd, &home);
+        cmd.env("JD_CONFIG", &config).args(args).arg(&root);
+        let out = cmd.assert().success().get_output().stdout.clone();
+        String::from_utf8(out).unwrap()
+    };
+    let id = jd_helper::model::make_id(&item);
+    run(&["archive", "--id", &id]);
+    let archived = root.join("90-99_Archive/90_Done/90.01_Audit");
+    assert!(archived.join("90.01_letter.pdf").is_file());
+    assert!(!item.exists());
+    let meta = fs::read_to_string(archived.join(".jdmeta")).unwrap();
+    assert!(
+        meta.starts_with("ARCHIVED_FROM=21.04\nARCHIVED=20"),
+        "{}",
+        meta
+    );
+
+    // the old number still finds it
+    let out = run(&["resolve", "21.04"]);
+    assert_eq!(out.trim_end(), archived.display().to_string());
+
+    run(&["unarchive", "--id", &id]);
+    assert!(item.join("21.04_letter.pdf").is_file());
+    assert!(!archived.exists());
+    assert!(!item.join(".jdmeta").exists());
+}
+
+#[test]
+fn import_csv_plans_then_creates_missing_codes() {
+    let td = tempdir().unwrap();
+    let home = td.path().join("home");
+    fs::create_dir_all(&home).unwrap();
+    let root = td.path().join("R");
+    fs::create_dir_all(root.join("20-29_Admin/21_Taxes")).unwrap();
+    let csv = td.path().join("plan.csv");
+    fs::write(
+        &csv,
+        "code,title,type\n20-29,Admin,Range\n21.01,Returns,ItemDir\n22,Bills,\n\"22.01\",\"Power, gas\",\n31.01,Orphan,\n",
+    )
+    .unwrap();
+
+    let run = |dry: bool| {
+        let mut cmd = cargo_bin();
+        set_home(&mut cmd, &home);
+        cmd.arg("import").arg(&csv).arg("--root").arg(&root);
+        if dry {
+            cmd.arg("--dry-run");
+        }
+        let out = cmd.assert().success().get_output().stdout.clone();
+        String::from_utf8(out).unwrap()
+    };
+    let plan = run(true);
+    assert!(plan.contains("skip\t20-29 Admin: exists\n"), "{}", plan);
+    assert!(plan.contains("skip\t31.01 Orphan: no parent in the tree or the file\n"));
+    assert_eq!(plan.lines().filter(|l| !l.starts_with("skip")).count(), 3);
+    assert!(!root.join("20-29_Admin/22_Bills").exists());
+
+    run(false);
+    assert!(root.join("20-29_Admin/21_Taxes/21.01_Returns").is_dir());
+    assert!(root.join("20-29_Admin/22_Bills/22.01_Power,_gas").is_dir());
+}
+
+#[test]
+fn snapshots_rotate_and_answer_changes_since() {
+    let td = tempdir().unwrap();
+    let home = td.path().join("home");
+    fs::create_dir_all(&home).unwrap();
+    let root = td.path().join("R");
+    let cat = root.join("20-29_Admin/21_Taxes");
+    fs::create_dir_all(&cat).unwrap();
+    let config = td.path().join("config.json");
+    let run = |args: &[&str]| {
+        let mut cmd = cargo_bin();
+        set_home(&mut cmd, &home);
+        cmd.env("JD_CONFIG", &config).args(args).arg(&root);
+        cmd.assert()
+    };
+
+    // off by default
+    run(&["write-index"]).success();
+    assert!(!root.join(".jd_snapshots").exists());
+    let err = run(&["changes"]).failure().get_output().stderr.clone();
+    assert!(String::from_utf8_lossy(&err).contains("no snapshots of"));
+
+    fs::write(&config, r#"{"snapshots": {"keep": 3, "every": "1d"}}"#).unwrap();
+    run(&["write-index"]).success();
+    let cat_id = jd_helper::model::make_id(&cat);
+    run(&["new", "dir", "--parent", &cat_id, "--name", "21.01_Returns"]).success();
+    // the index was rewritten, but a day hasn't passed
+    let snaps: Vec<_> = fs::read_dir(root.join(".jd_snapshots")).unwrap().collect();
+    assert_eq!(snaps.len(), 1);
+
+    let out = run(&["changes", "--since", "0s"])
+        .success()
+        .get_output()
+        .stdout
+        .clone();
+    let out = String::from_utf8(out).unwrap();
+    assert!(out.starts_with("since 20"), "{}", out);
+    assert!(
+        out.contains(": 1 added\nadded      21.01 Returns\n"),
+        "{}",
+        out
+    );
+
+    let out = run(&["changes", "--json"])
+        .success()
+        .get_output()
+        .stdout
+        .clone();
+    let v: serde_json::Value = serde_json::from_slice(&out).unwrap();
+    assert_eq!(v["changes"][0]["change"], "added");
+    // the scan doesn't see the snapshots
+    let out = run(&["scan"]).success().get_output().stdout.clone();
+    assert!(!String::from_utf8(out).unwrap().contains("jd_snapshots"));
+}
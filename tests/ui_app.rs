@@ -1,6 +1,7 @@
 //! Headless tests for the TUI state machine: drive `App` with synthetic key
 //! events against the T99 fixture and observe filesystem + state effects.
 
+use jd_helper::diff::Change;
 use jd_helper::ui::app::{App, Mode, Outcome, SuspendRequest};
 use jd_helper::ui::render;
 use ratatui::backend::TestBackend;
@@ -94,6 +95,46 @@ fn query_with_space_filters_across_folds() {
     assert!(!h.app.visible.is_empty());
 }
 
+#[test]
+fn archived_item_matches_its_former_code() {
+    let mut h = harness();
+    let item = h
+        .root
+        .join("90-98_Second_Range/90_Another_Cat/90.01_Alpha_Item");
+    fs::write(item.join(".jdmeta"), "ARCHIVED_FROM=12.07\n").unwrap();
+    h.app = App::new(vec![h.root.clone()], h.state.clone()).unwrap();
+    type_str(&mut h.app, "12.07 alpha");
+    let hits: Vec<&str> = h
+        .app
+        .visible
+        .iter()
+        .filter(|i| !h.app.context.contains(i))
+        .map(|i| h.app.rows[*i].display.as_str())
+        .collect();
+    assert_eq!(hits, ["90.01 Alpha Item"]);
+}
+
+#[test]
+fn archived_file_item_previews_its_former_code() {
+    let mut h = harness();
+    let cat = h.root.join("90-98_Second_Range/90_Another_Cat");
+    fs::write(cat.join("90.02_Old_Scan.txt"), "scan\n").unwrap();
+    fs::write(
+        cat.join(".90.02_Old_Scan.txt.jdmeta"),
+        "ARCHIVED_FROM=12.07\n",
+    )
+    .unwrap();
+    h.app = App::new(vec![h.root.clone()], h.state.clone()).unwrap();
+    let row = h
+        .app
+        .rows
+        .iter()
+        .find(|r| r.display == "90.02 Old Scan")
+        .unwrap();
+    let text = render::preview_content(row).to_string();
+    assert!(text.starts_with("⟲ archived from 12.07"), "{}", text);
+}
+
 #[test]
 fn filter_shows_ancestor_context_and_cursor_skips_it() {
     let mut h = harness();
@@ -225,16 +266,141 @@ fn delete_then_undo_restores() {
     ctrl(&mut h.app, 'x');
     h.app.handle_key(KeyCode::Char('y'), KeyModifiers::NONE);
     assert!(!orig.exists());
-    assert!(orig
-        .parent()
-        .unwrap()
-        .join(".jd_trash/90.01_Alpha_Item")
-        .is_dir());
+    assert!(h.root.join(".jd_trash/90.01_Alpha_Item").is_dir());
     ctrl(&mut h.app, 'z');
     assert!(orig.is_dir());
     assert!(selected_path(&h.app).ends_with("90.01_Alpha_Item"));
 }
 
+#[test]
+fn wrap_then_unwrap_round_trips() {
+    let mut h = harness();
+    move_cursor_to(&mut h.app, "90-98_Second_Range");
+    h.app.handle_key(KeyCode::Tab, KeyModifiers::NONE);
+    move_cursor_to(&mut h.app, "90_Another_Cat");
+    h.app.handle_key(KeyCode::Tab, KeyModifiers::NONE);
+    move_cursor_to(&mut h.app, "90.02_Two_Word_Notes.md");
+    let cat = h.root.join("90-98_Second_Range/90_Another_Cat");
+    ctrl(&mut h.app, 'w');
+    h.app.handle_key(KeyCode::Char('y'), KeyModifiers::NONE);
+    assert!(cat
+        .join("90.02_Two_Word_Notes/90.02_Two_Word_Notes.md")
+        .is_file());
+    assert!(selected_path(&h.app).ends_with("90.02_Two_Word_Notes"));
+    assert!(h.app.status.as_deref().unwrap().starts_with("wrapped into"));
+
+    ctrl(&mut h.app, 'w');
+    h.app.handle_key(KeyCode::Char('y'), KeyModifiers::NONE);
+    assert!(cat.join("90.02_Two_Word_Notes.md").is_file());
+    assert!(!cat.join("90.02_Two_Word_Notes").exists());
+    assert!(selected_path(&h.app).ends_with("90.02_Two_Word_Notes.md"));
+
+    // a category is neither
+    move_cursor_to(&mut h.app, "90_Another_Cat");
+    ctrl(&mut h.app, 'w');
+    assert!(matches!(h.app.mode, Mode::Message { error: true, .. }));
+}
+
+#[test]
+fn cached_start_then_background_scan_marks_changes() {
+    let h = harness();
+    let roots = vec![h.root.clone()];
+    let cat = h.root.join("90-98_Second_Range/90_Another_Cat");
+    let tree = jd_helper::fs_walk::scan_roots(&roots).unwrap();
+    jd_helper::io::IndexIo.write_index(None, &tree).unwrap();
+    fs::create_dir(cat.join("90.05_Fresh")).unwrap();
+    fs::rename(
+        cat.join("90.02_Two_Word_Notes.md"),
+        cat.join("90.02_Notes.md"),
+    )
+    .unwrap();
+    fs::remove_dir_all(cat.join("90.01_Alpha_Item")).unwrap();
+
+    let mut app = App::from_cache(roots.clone(), h.state.clone()).unwrap();
+    assert!(app.stale());
+    assert!(app.rows.iter().any(|r| r.display == "90.01 Alpha Item"));
+    assert!(!app.rows.iter().any(|r| r.display == "90.05 Fresh"));
+
+    app.merge_scan(jd_helper::fs_walk::scan_roots(&roots));
+    assert!(!app.stale());
+    let change = |display: &str| {
+        app.rows
+            .iter()
+            .find(|r| r.display == display)
+            .unwrap_or_else(|| panic!("no row {}", display))
+            .change
+    };
+    assert_eq!(change("90.05 Fresh"), Some(Change::Added));
+    assert_eq!(change("90.02 Notes"), Some(Change::Changed));
+    // the removed item stays visible, struck out, and can't be acted on
+    assert_eq!(change("90.01 Alpha Item"), Some(Change::Removed));
+    assert_eq!(change("90 Another Cat"), None);
+    assert_eq!(
+        app.status.as_deref(),
+        Some("rescanned · 1 new · 1 changed · 1 gone")
+    );
+    type_str(&mut app, "alpha");
+    app.handle_key(KeyCode::Enter, KeyModifiers::NONE);
+    assert!(matches!(app.mode, Mode::Message { error: true, .. }));
+
+    // a late second scan is dropped
+    app.status = None;
+    app.merge_scan(jd_helper::fs_walk::scan_roots(&roots));
+    assert!(app.status.is_none());
+
+    // held back while a dialog is open, merged once it closes
+    let mut app = App::from_cache(roots.clone(), h.state.clone()).unwrap();
+    ctrl(&mut app, 'k');
+    app.merge_scan(jd_helper::fs_walk::scan_roots(&roots));
+    assert!(app.stale());
+    app.handle_key(KeyCode::Esc, KeyModifiers::NONE);
+    assert!(!app.stale());
+    assert!(app.rows.iter().any(|r| r.display == "90.05 Fresh"));
+}
+
+#[test]
+fn marks_are_made_against_the_last_session() {
+    let h = harness();
+    let roots = vec![h.root.clone()];
+    let cat = h.root.join("90-98_Second_Range/90_Another_Cat");
+    let before = jd_helper::fs_walk::scan_roots(&roots).unwrap();
+    jd_helper::state::save_session(&h.state, &before).unwrap();
+    // a change from the command line rewrites the index between sessions
+    fs::create_dir(cat.join("90.06_Between")).unwrap();
+    let tree = jd_helper::fs_walk::scan_roots(&roots).unwrap();
+    jd_helper::io::IndexIo.write_index(None, &tree).unwrap();
+
+    let mut app = App::from_cache(roots.clone(), h.state.clone()).unwrap();
+    app.merge_scan(jd_helper::fs_walk::scan_roots(&roots));
+    let row = app.rows.iter().find(|r| r.display == "90.06 Between");
+    assert_eq!(row.unwrap().change, Some(Change::Added));
+    assert_eq!(
+        app.status.as_deref(),
+        Some("rescanned · 1 new · 0 changed · 0 gone since the last session")
+    );
+
+    // not while the scan is still out
+    let stale = App::from_cache(roots.clone(), h.state.clone()).unwrap();
+    stale.end_session().unwrap();
+    let kept = jd_helper::state::load_session(&h.state, &roots).unwrap();
+    assert!(jd_helper::model::find_by_code(&kept, "90.06").is_none());
+    app.end_session().unwrap();
+    let mut app = App::from_cache(roots.clone(), h.state.clone()).unwrap();
+    app.merge_scan(jd_helper::fs_walk::scan_roots(&roots));
+    assert!(app.rows.iter().all(|r| r.change.is_none()));
+    assert_eq!(
+        app.status.as_deref(),
+        Some("rescanned · nothing changed since the last session")
+    );
+
+    // without an index to start from, the first frame is marked
+    fs::remove_file(h.root.join(".jd_index.json")).unwrap();
+    fs::create_dir(cat.join("90.07_Later")).unwrap();
+    let app = App::from_cache(roots, h.state.clone()).unwrap();
+    let row = app.rows.iter().find(|r| r.display == "90.07 Later");
+    assert_eq!(row.unwrap().change, Some(Change::Added));
+}
+
 #[test]
 fn enter_emits_cd_action_for_dirs() {
     let mut h = harness();
@@ -377,7 +543,7 @@ fn duplicate_wizard_merges_pointer_file_into_folder() {
         "LOCATION=Box\n"
     );
     assert!(!cat.join("99.01_TestItem_remote").exists());
-    assert!(cat.join(".jd_trash/99.01_TestItem_remote").is_file());
+    assert!(h.root.join(".jd_trash/99.01_TestItem_remote").is_file());
     assert!(h.app.duplicate_groups().is_empty());
     assert!(matches!(h.app.mode, Mode::Browse));
     // undoable: ctrl-z restores the pointer file
@@ -463,6 +629,51 @@ fn markdown_file_is_rendered_in_preview() {
     assert!(text.to_string().contains("Some code."));
 }
 
+#[test]
+fn binary_files_preview_a_summary_and_source_is_highlighted() {
+    use jd_helper::preview::{inspect, summary};
+    let mut h = harness();
+    let cat = h.root.join("90-98_Second_Range/90_Another_Cat");
+    let mut png = b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR".to_vec();
+    png.extend([0, 0, 3, 32, 0, 0, 2, 88, 8, 6, 0, 0, 0]);
+    fs::write(cat.join("90.03_Photo.png"), &png).unwrap();
+    fs::write(
+        cat.join("90.04_Paper.pdf"),
+        "%PDF-1.4\n1 0 obj << /Type /Pages /Kids [2 0 R] /Count 3 >> endobj\n\0\x01",
+    )
+    .unwrap();
+    fs::write(
+        cat.join("90.05_Script.py"),
+        "# run\ndef main():\n    return 1\n",
+    )
+    .unwrap();
+
+    let png = inspect(&cat.join("90.03_Photo.png")).unwrap();
+    assert_eq!((png.kind, png.dimensions), ("PNG image", Some((800, 600))));
+    let pdf = inspect(&cat.join("90.04_Paper.pdf")).unwrap();
+    assert_eq!(summary(&pdf)[1], "PDF document · 3 pages");
+    let py = inspect(&cat.join("90.05_Script.py")).unwrap();
+    assert_eq!(summary(&py)[1], "text · ASCII · 3 lines");
+
+    h.app = App::new(vec![h.root.clone()], h.state.clone()).unwrap();
+    type_str(&mut h.app, "photo");
+    move_cursor_to(&mut h.app, "90.03_Photo.png");
+    let text = render::preview_content(h.app.selected().unwrap());
+    assert!(text.to_string().contains("PNG image · 800×600"));
+
+    h.app.query.clear();
+    type_str(&mut h.app, "script");
+    move_cursor_to(&mut h.app, "90.05_Script.py");
+    let text = render::preview_content(h.app.selected().unwrap());
+    let def = text
+        .lines
+        .iter()
+        .flat_map(|l| &l.spans)
+        .find(|s| s.content == "def")
+        .unwrap();
+    assert_eq!(def.style, jd_helper::ui::theme::KEYWORD);
+}
+
 #[test]
 fn notes_on_file_show_a_message() {
     let mut h = harness();
@@ -489,3 +700,151 @@ fn editor_command_resolution_order() {
         }
     }
 }
+
+#[test]
+fn recode_item_then_undo() {
+    let mut h = harness();
+    ctrl(&mut h.app, 'a');
+    move_cursor_to(&mut h.app, "99.01_TestItem");
+    ctrl(&mut h.app, 'o');
+    // prefilled with the current code
+    for _ in 0.."99.01".len() {
+        h.app.handle_key(KeyCode::Backspace, KeyModifiers::NONE);
+    }
+    type_str(&mut h.app, "98.10");
+    h.app.handle_key(KeyCode::Enter, KeyModifiers::NONE);
+    // not under the same category
+    assert!(matches!(h.app.mode, Mode::Message { error: true, .. }));
+    h.app.handle_key(KeyCode::Esc, KeyModifiers::NONE);
+
+    ctrl(&mut h.app, 'o');
+    h.app.handle_key(KeyCode::Backspace, KeyModifiers::NONE);
+    h.app.handle_key(KeyCode::Backspace, KeyModifiers::NONE);
+    type_str(&mut h.app, "10");
+    h.app.handle_key(KeyCode::Enter, KeyModifiers::NONE);
+    assert!(matches!(h.app.mode, Mode::Confirm { .. }));
+    h.app.handle_key(KeyCode::Char('y'), KeyModifiers::NONE);
+    let cat = h.root.join("99-99_Test_Range/99_TestCat");
+    assert!(cat
+        .join("99.10_TestItem/99.10.01_Nested_Note.txt")
+        .is_file());
+    assert!(!cat.join("99.01_TestItem").exists());
+    assert!(selected_path(&h.app).ends_with("99.10_TestItem"));
+
+    ctrl(&mut h.app, 'z');
+    assert!(cat
+        .join("99.01_TestItem/99.01.01_Nested_Note.txt")
+        .is_file());
+    assert!(!cat.join("99.10_TestItem").exists());
+}
+
+#[test]
+fn trash_view_restores_and_purges() {
+    let mut h = harness();
+    ctrl(&mut h.app, 't');
+    assert!(matches!(h.app.mode, Mode::Browse));
+    ctrl(&mut h.app, 'a');
+    let cat = h.root.join("99-99_Test_Range/99_TestCat");
+    for name in ["99.02_Example.url", "99.03_Website.webloc"] {
+        move_cursor_to(&mut h.app, name);
+        ctrl(&mut h.app, 'x');
+        h.app.handle_key(KeyCode::Char('y'), KeyModifiers::NONE);
+        assert!(!cat.join(name).exists());
+    }
+
+    ctrl(&mut h.app, 't');
+    let Mode::Trash { entries, .. } = &h.app.mode else {
+        panic!("trash view not open");
+    };
+    assert_eq!(entries.len(), 2);
+    let first = entries[0].1.name.clone();
+    // x asks first; anything but y keeps it
+    h.app.handle_key(KeyCode::Char('x'), KeyModifiers::NONE);
+    h.app.handle_key(KeyCode::Char('n'), KeyModifiers::NONE);
+    assert!(h.root.join(".jd_trash").join(&first).exists());
+    h.app.handle_key(KeyCode::Char('x'), KeyModifiers::NONE);
+    h.app.handle_key(KeyCode::Char('y'), KeyModifiers::NONE);
+    assert!(!h.root.join(".jd_trash").join(&first).exists());
+
+    // the remaining entry goes back where it came from
+    let Mode::Trash { entries, .. } = &h.app.mode else {
+        panic!("trash view closed after purge");
+    };
+    let last = entries[0].1.original.clone();
+    h.app.handle_key(KeyCode::Enter, KeyModifiers::NONE);
+    assert!(h.root.join(&last).is_file());
+    assert!(matches!(h.app.mode, Mode::Browse));
+    assert!(selected_path(&h.app).ends_with(last.file_name().unwrap().to_str().unwrap()));
+}
+
+#[test]
+fn create_fills_folder_from_template() {
+    let mut h = harness();
+    let tpl = h.root.join(".jdtemplates");
+    fs::create_dir_all(tpl.join("item/data")).unwrap();
+    fs::create_dir_all(tpl.join("bare")).unwrap();
+    fs::write(tpl.join("item/notes.md"), "# {{code}} {{title}}\n").unwrap();
+    h.app = App::new(vec![h.root.clone()], h.state.clone()).unwrap();
+
+    move_cursor_to(&mut h.app, "90-98_Second_Range");
+    h.app.handle_key(KeyCode::Tab, KeyModifiers::NONE);
+    move_cursor_to(&mut h.app, "91_Sparse_Cat");
+    ctrl(&mut h.app, 'n');
+    type_str(&mut h.app, "Field trip");
+    h.app.handle_key(KeyCode::Enter, KeyModifiers::NONE);
+    // `item` is the default for items; t cycles bare → none → bare → item
+    let template = |app: &App| match &app.mode {
+        Mode::Confirm {
+            pending: jd_helper::ui::app::PendingOp::Create { plan, .. },
+        } => plan.template.clone(),
+        _ => panic!("not confirming a create"),
+    };
+    assert_eq!(template(&h.app), Some(tpl.join("item")));
+    h.app.handle_key(KeyCode::Char('t'), KeyModifiers::NONE);
+    assert_eq!(template(&h.app), None);
+    h.app.handle_key(KeyCode::Char('t'), KeyModifiers::NONE);
+    assert_eq!(template(&h.app), Some(tpl.join("bare")));
+    h.app.handle_key(KeyCode::Char('t'), KeyModifiers::NONE);
+    assert_eq!(template(&h.app), Some(tpl.join("item")));
+    h.app.handle_key(KeyCode::Char('y'), KeyModifiers::NONE);
+
+    let dest = h
+        .root
+        .join("90-98_Second_Range/91_Sparse_Cat/91.01_Field_trip");
+    assert_eq!(
+        fs::read_to_string(dest.join("notes.md")).unwrap(),
+        "# 91.01 Field trip\n"
+    );
+    assert!(dest.join("data").is_dir());
+    // templates never show up as tree rows
+    assert!(!h.app.rows.iter().any(|r| r.path.contains(".jdtemplates")));
+}
+
+#[test]
+fn actions_survive_tabs_and_newlines_in_paths() {
+    use jd_helper::ui::actions::Format;
+    use jd_helper::ui::FinalAction;
+    let a = FinalAction::Cd(PathBuf::from("/r/21.04_Tax\tletters\n2026"));
+    assert_eq!(
+        a.encode(Format::Nul).unwrap(),
+        b"cd\0/r/21.04_Tax\tletters\n2026\0"
+    );
+    let v: serde_json::Value = serde_json::from_slice(&a.encode(Format::Json).unwrap()).unwrap();
+    assert_eq!(v["action"], "cd");
+    assert_eq!(v["target"], "/r/21.04_Tax\tletters\n2026");
+    // the line format stays as the zsh wrapper has always read it
+    assert_eq!(
+        FinalAction::Open("https://x".into())
+            .encode(Format::Line)
+            .unwrap(),
+        b"open\thttps://x\n"
+    );
+    // JSON can't hold a path that isn't UTF-8; NUL can
+    use std::os::unix::ffi::OsStrExt;
+    let raw = FinalAction::Edit(PathBuf::from(std::ffi::OsStr::from_bytes(
+        b"/r/caf\xe9.txt",
+    )));
+    let err = raw.encode(Format::Json).unwrap_err().to_string();
+    assert!(err.contains("--format nul"), "{}", err);
+    assert_eq!(raw.encode(Format::Nul).unwrap(), b"edit\0/r/caf\xe9.txt\0");
+}